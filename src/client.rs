@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use bytes::BytesMut;
+use tokio::sync::Notify;
+
+use crate::resp::{ProtocolVersion, RespDataTypes};
+
+/// Initial capacity of a client's reply buffer; it grows for large replies and is reused.
+const OUTPUT_BUFFER_SIZE: usize = 16 * 1024;
+
+/// Reply buffers that grew past this are reallocated instead of kept around.
+const MAX_RETAINED_OUTPUT_SIZE: usize = 1024 * 1024;
+
+/// Client ids are never reused for the lifetime of the server.
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// What `CLIENT REPLY` asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyMode {
+    On,
+
+    Off,
+
+    /// Only the reply to the next command is dropped.
+    Skip,
+}
+
+/// The client types `CLIENT LIST TYPE` and `CLIENT KILL TYPE` filter on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientKind {
+    Normal,
+
+    Master,
+
+    Replica,
+}
+
+impl ClientKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "normal" => Some(Self::Normal),
+
+            "master" => Some(Self::Master),
+
+            "replica" | "slave" => Some(Self::Replica),
+
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ClientFlags {
+    /// The link a replica keeps to its master; replies on it are never sent.
+    pub master: bool,
+
+    /// A replica attached to this server.
+    pub replica: bool,
+
+    /// Close the connection once the pending replies are written.
+    pub close_after_reply: bool,
+
+    /// Waiting in a blocking command.
+    pub blocked: bool,
+}
+
+/// State that belongs to a single connection rather than to the whole server.
+#[derive(Debug)]
+pub struct Client {
+    pub id: u64,
+
+    pub addr: String,
+
+    pub laddr: String,
+
+    pub name: Option<String>,
+
+    /// Index of the database the client's commands run against.
+    pub db: u32,
+
+    pub protocol: ProtocolVersion,
+
+    pub user: String,
+
+    pub flags: ClientFlags,
+
+    pub created_at: Instant,
+
+    pub last_interaction: Instant,
+
+    /// Full name of the last command, `container|subcommand` for subcommands.
+    pub last_command: &'static str,
+
+    pub reply_mode: ReplyMode,
+
+    /// Whether replies to the command being executed are dropped.
+    skip_reply: bool,
+
+    /// Woken by `CLIENT KILL` from another connection.
+    pub killed: Arc<Notify>,
+
+    /// Encoded replies waiting to be written to the socket, so that a pipelined batch of
+    /// commands is answered with a single write.
+    pub output: BytesMut,
+}
+
+impl Client {
+    pub fn new(addr: String, laddr: String) -> Self {
+        let now = Instant::now();
+
+        Self {
+            id: NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed),
+            addr,
+            laddr,
+            name: None,
+            db: 0,
+            protocol: ProtocolVersion::Resp2,
+            user: String::from("default"),
+            flags: ClientFlags::default(),
+            created_at: now,
+            last_interaction: now,
+            last_command: "NULL",
+            reply_mode: ReplyMode::On,
+            skip_reply: false,
+            killed: Arc::new(Notify::new()),
+            output: BytesMut::with_capacity(OUTPUT_BUFFER_SIZE),
+        }
+    }
+
+    /// Called before every command, so that `CLIENT REPLY` takes effect.
+    pub fn begin_command(&mut self) {
+        self.last_interaction = Instant::now();
+
+        self.skip_reply = match self.reply_mode {
+            ReplyMode::On => false,
+
+            ReplyMode::Off => true,
+
+            ReplyMode::Skip => {
+                self.reply_mode = ReplyMode::On;
+
+                true
+            }
+        };
+    }
+
+    /// Switches the reply mode; `CLIENT REPLY OFF` and `SKIP` are not answered themselves.
+    pub fn set_reply_mode(&mut self, mode: ReplyMode) {
+        self.reply_mode = mode;
+
+        self.skip_reply = mode != ReplyMode::On;
+    }
+
+    pub fn add_reply(&mut self, reply: &RespDataTypes) {
+        if self.skip_reply || self.flags.master {
+            return;
+        }
+
+        reply.encode_into(&mut self.output, self.protocol);
+    }
+
+    /// Empties the reply buffer once it was written, letting go of the memory a single huge
+    /// reply made it grow to.
+    pub fn clear_output(&mut self) {
+        if self.output.capacity() > MAX_RETAINED_OUTPUT_SIZE {
+            self.output = BytesMut::with_capacity(OUTPUT_BUFFER_SIZE);
+        } else {
+            self.output.clear();
+        }
+    }
+
+    pub fn kind(&self) -> ClientKind {
+        if self.flags.master {
+            ClientKind::Master
+        } else if self.flags.replica {
+            ClientKind::Replica
+        } else {
+            ClientKind::Normal
+        }
+    }
+
+    pub fn info(&self) -> ClientInfo {
+        let mut flags = String::new();
+
+        if self.flags.replica {
+            flags.push('S');
+        }
+
+        if self.flags.master {
+            flags.push('M');
+        }
+
+        if self.flags.blocked {
+            flags.push('b');
+        }
+
+        if self.flags.close_after_reply {
+            flags.push('c');
+        }
+
+        if flags.is_empty() {
+            flags.push('N');
+        }
+
+        ClientInfo {
+            id: self.id,
+            addr: self.addr.clone(),
+            laddr: self.laddr.clone(),
+            name: self.name.clone(),
+            db: self.db,
+            user: self.user.clone(),
+            kind: self.kind(),
+            flags,
+            protocol: self.protocol,
+            created_at: self.created_at,
+            last_interaction: self.last_interaction,
+            last_command: self.last_command,
+        }
+    }
+}
+
+/// A copy of a client's state that other connections can look at.
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub id: u64,
+
+    pub addr: String,
+
+    pub laddr: String,
+
+    pub name: Option<String>,
+
+    pub db: u32,
+
+    pub user: String,
+
+    pub kind: ClientKind,
+
+    pub flags: String,
+
+    pub protocol: ProtocolVersion,
+
+    pub created_at: Instant,
+
+    pub last_interaction: Instant,
+
+    pub last_command: &'static str,
+}
+
+impl ClientInfo {
+    /// One line of `CLIENT LIST`, without the trailing newline.
+    pub fn to_line(&self) -> String {
+        format!(
+            "id={} addr={} laddr={} name={} age={} idle={} flags={} db={} multi=-1 cmd={} user={} resp={}",
+            self.id,
+            self.addr,
+            self.laddr,
+            self.name.as_deref().unwrap_or_default(),
+            self.created_at.elapsed().as_secs(),
+            self.last_interaction.elapsed().as_secs(),
+            self.flags,
+            self.db,
+            self.last_command,
+            self.user,
+            self.protocol.as_number(),
+        )
+    }
+}
+
+#[derive(Debug)]
+struct ClientEntry {
+    info: ClientInfo,
+
+    killed: Arc<Notify>,
+}
+
+/// Every connected client, as of the end of its last batch of commands.
+#[derive(Debug, Default)]
+pub struct ClientRegistry {
+    clients: Mutex<HashMap<u64, ClientEntry>>,
+}
+
+impl ClientRegistry {
+    /// Adds the client, or refreshes what other connections see of it.
+    pub fn update(&self, client: &Client) {
+        self.clients.lock().unwrap().insert(
+            client.id,
+            ClientEntry {
+                info: client.info(),
+                killed: client.killed.clone(),
+            },
+        );
+    }
+
+    pub fn remove(&self, id: u64) {
+        self.clients.lock().unwrap().remove(&id);
+    }
+
+    /// All clients ordered by id, with `current` described from its live state.
+    pub fn snapshot(&self, current: &Client) -> Vec<ClientInfo> {
+        let mut clients: Vec<ClientInfo> = self
+            .clients
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|entry| entry.info.id != current.id)
+            .map(|entry| entry.info.clone())
+            .collect();
+
+        clients.push(current.info());
+
+        clients.sort_by_key(|info| info.id);
+
+        clients
+    }
+
+    /// Asks the connection of client `id` to close; false when there is no such client.
+    pub fn kill(&self, id: u64) -> bool {
+        match self.clients.lock().unwrap().get(&id) {
+            Some(entry) => {
+                entry.killed.notify_one();
+
+                true
+            }
+
+            None => false,
+        }
+    }
+}
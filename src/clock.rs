@@ -0,0 +1,103 @@
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+/// Where expirations get the current time from, so that they can be checked against a clock
+/// that does not move on its own.
+pub trait Clock: Send + Sync + Debug {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The wall clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to, with millisecond precision like Redis.
+#[derive(Debug)]
+pub struct ManualClock {
+    now_ms: AtomicI64,
+}
+
+impl ManualClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now_ms: AtomicI64::new(now.timestamp_millis()),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        self.now_ms.store(now.timestamp_millis(), Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now_ms
+            .fetch_add(by.as_millis() as i64, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(self.now_ms.load(Ordering::Relaxed)).unwrap_or_default()
+    }
+}
+
+/// The clock the server runs on: the system clock, until `DEBUG FREEZE-TIME` swaps in a
+/// [`ManualClock`] stopped at the current time.
+#[derive(Debug, Default)]
+pub struct ServerClock {
+    system: SystemClock,
+
+    frozen: RwLock<Option<ManualClock>>,
+}
+
+impl ServerClock {
+    /// Stops time at `at`, or where it is now.
+    pub fn freeze(&self, at: Option<DateTime<Utc>>) {
+        let at = at.unwrap_or_else(|| self.now());
+
+        let mut frozen = self.frozen.write().unwrap();
+
+        match frozen.as_ref() {
+            Some(clock) => clock.set(at),
+
+            None => *frozen = Some(ManualClock::new(at)),
+        }
+    }
+
+    /// Lets time run again from the system clock.
+    pub fn unfreeze(&self) {
+        *self.frozen.write().unwrap() = None;
+    }
+
+    /// Moves frozen time forward; false when time is not frozen.
+    pub fn advance(&self, by: Duration) -> bool {
+        match self.frozen.read().unwrap().as_ref() {
+            Some(clock) => {
+                clock.advance(by);
+
+                true
+            }
+
+            None => false,
+        }
+    }
+}
+
+impl Clock for ServerClock {
+    fn now(&self) -> DateTime<Utc> {
+        match self.frozen.read().unwrap().as_ref() {
+            Some(clock) => clock.now(),
+
+            None => self.system.now(),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use super::lists::{list_end, lmpop_keys, parse_lmpop};
+use super::{text, BlockingOp, CommandError, Commands, ListEnd};
+
+pub fn parse_blpop(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_keys_then_timeout(args, BlockingOp::Pop(ListEnd::Left))
+}
+
+pub fn parse_brpop(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_keys_then_timeout(args, BlockingOp::Pop(ListEnd::Right))
+}
+
+pub fn parse_bzpopmin(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_keys_then_timeout(args, BlockingOp::ZPopMin)
+}
+
+pub fn parse_bzpopmax(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_keys_then_timeout(args, BlockingOp::ZPopMax)
+}
+
+/// `COMMAND key [key ...] timeout`
+fn parse_keys_then_timeout(args: &[Bytes], op: BlockingOp) -> Result<Commands, CommandError> {
+    let last = args.len() - 1;
+
+    Ok(Commands::Blocking(
+        args[1..last].to_vec(),
+        op,
+        timeout(&args[last])?,
+    ))
+}
+
+pub fn parse_blmove(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Blocking(
+        vec![args[1].clone()],
+        BlockingOp::Move(args[2].clone(), list_end(&args[3])?, list_end(&args[4])?),
+        timeout(&args[5])?,
+    ))
+}
+
+pub fn parse_brpoplpush(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Blocking(
+        vec![args[1].clone()],
+        BlockingOp::Move(args[2].clone(), ListEnd::Right, ListEnd::Left),
+        timeout(&args[3])?,
+    ))
+}
+
+/// `BLMPOP timeout numkeys key [key ...] LEFT|RIGHT [COUNT count]`, which is `LMPOP` with a
+/// timeout in front.
+pub fn parse_blmpop(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let timeout = timeout(&args[1])?;
+
+    let Commands::LMPop(keys, end, count) = parse_lmpop(&args[1..])? else {
+        unreachable!("LMPOP parses into Commands::LMPop");
+    };
+
+    Ok(Commands::Blocking(
+        keys,
+        BlockingOp::MPop(end, count),
+        timeout,
+    ))
+}
+
+/// Key positions of `BLMPOP timeout numkeys key [key ...] ...`.
+pub fn blmpop_keys(args: &[Bytes]) -> Vec<usize> {
+    lmpop_keys(&args[1..])
+        .into_iter()
+        .map(|index| index + 1)
+        .collect()
+}
+
+/// A timeout in seconds, possibly fractional. Zero waits forever and gives `None`.
+fn timeout(arg: &[u8]) -> Result<Option<Duration>, CommandError> {
+    let seconds: f64 = text(arg)
+        .parse()
+        .ok()
+        .filter(|seconds: &f64| !seconds.is_nan())
+        .ok_or_else(|| CommandError::from("ERR timeout is not a float or out of range"))?;
+
+    if seconds < 0.0 {
+        return Err(CommandError::from("ERR timeout is negative"));
+    }
+
+    if seconds == 0.0 {
+        return Ok(None);
+    }
+
+    Duration::try_from_secs_f64(seconds)
+        .map(Some)
+        .map_err(|_| CommandError::from("ERR timeout is out of range"))
+}
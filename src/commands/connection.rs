@@ -0,0 +1,199 @@
+use bytes::Bytes;
+
+use crate::client::{ClientInfo, ClientKind, ReplyMode};
+use crate::glob;
+
+use super::{integer, keyword, text, ClientCommand, CommandError, Commands, KillFilter};
+
+/// Client names are shown in `CLIENT LIST`, so they must be a single printable word.
+pub fn check_client_name(name: &[u8]) -> Result<(), CommandError> {
+    if name.iter().all(|byte| (b'!'..=b'~').contains(byte)) {
+        Ok(())
+    } else {
+        Err(CommandError::from(
+            "ERR Client names cannot contain spaces, newlines or special characters.",
+        ))
+    }
+}
+
+fn client_kind(arg: &[u8]) -> Result<ClientKind, CommandError> {
+    ClientKind::from_name(&text(arg))
+        .ok_or_else(|| CommandError::Custom(format!("ERR Unknown client type '{}'", text(arg))))
+}
+
+pub fn parse_select(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Select(integer(&args[1])?))
+}
+
+pub fn parse_client_id(_args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Client(ClientCommand::Id))
+}
+
+pub fn parse_client_setname(args: &[Bytes]) -> Result<Commands, CommandError> {
+    check_client_name(&args[2])?;
+
+    let name = Some(text(&args[2])).filter(|name| !name.is_empty());
+
+    Ok(Commands::Client(ClientCommand::SetName(name)))
+}
+
+pub fn parse_client_getname(_args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Client(ClientCommand::GetName))
+}
+
+pub fn parse_client_info(_args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Client(ClientCommand::Info))
+}
+
+pub fn parse_client_list(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let mut kind = None;
+    let mut ids = Vec::new();
+
+    let mut i = 2;
+
+    while i < args.len() {
+        match keyword(&args[i]).as_str() {
+            "TYPE" if i + 1 < args.len() => {
+                kind = Some(client_kind(&args[i + 1])?);
+
+                i += 2;
+            }
+
+            "ID" if i + 1 < args.len() => {
+                for arg in &args[i + 1..] {
+                    let id = integer(arg)
+                        .ok()
+                        .filter(|id| *id > 0)
+                        .ok_or(CommandError::from("ERR Invalid client ID"))?;
+
+                    ids.push(id as u64);
+                }
+
+                i = args.len();
+            }
+
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    Ok(Commands::Client(ClientCommand::List(kind, ids)))
+}
+
+pub fn parse_client_kill(args: &[Bytes]) -> Result<Commands, CommandError> {
+    if args.len() == 3 {
+        return Ok(Commands::Client(ClientCommand::Kill(KillFilter {
+            addr: Some(text(&args[2])),
+            legacy: true,
+            ..KillFilter::default()
+        })));
+    }
+
+    let mut filter = KillFilter {
+        skip_me: true,
+        ..KillFilter::default()
+    };
+
+    for pair in args[2..].chunks(2) {
+        let [option, value] = pair else {
+            return Err(CommandError::Syntax);
+        };
+
+        match keyword(option).as_str() {
+            "ID" => {
+                let id = integer(value)
+                    .ok()
+                    .filter(|id| *id > 0)
+                    .ok_or(CommandError::from("ERR client-id should be greater than 0"))?;
+
+                filter.id = Some(id as u64);
+            }
+
+            "ADDR" => filter.addr = Some(text(value)),
+
+            "LADDR" => filter.laddr = Some(text(value)),
+
+            "USER" => filter.user = Some(text(value)),
+
+            "TYPE" => filter.kind = Some(client_kind(value)?),
+
+            "SKIPME" => {
+                filter.skip_me = match keyword(value).as_str() {
+                    "YES" => true,
+
+                    "NO" => false,
+
+                    _ => return Err(CommandError::Syntax),
+                }
+            }
+
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    Ok(Commands::Client(ClientCommand::Kill(filter)))
+}
+
+pub fn parse_client_reply(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let mode = match keyword(&args[2]).as_str() {
+        "ON" => ReplyMode::On,
+
+        "OFF" => ReplyMode::Off,
+
+        "SKIP" => ReplyMode::Skip,
+
+        _ => return Err(CommandError::Syntax),
+    };
+
+    Ok(Commands::Client(ClientCommand::Reply(mode)))
+}
+
+pub fn parse_client_unblock(args: &[Bytes]) -> Result<Commands, CommandError> {
+    if args.len() > 4 {
+        return Err(CommandError::Syntax);
+    }
+
+    let id = u64::try_from(integer(&args[2])?).map_err(|_| CommandError::NotInteger)?;
+
+    let error = match args.get(3).map(|reason| keyword(reason)).as_deref() {
+        None | Some("TIMEOUT") => false,
+
+        Some("ERROR") => true,
+
+        _ => {
+            return Err(CommandError::from(
+                "ERR CLIENT UNBLOCK reason should be TIMEOUT or ERROR",
+            ))
+        }
+    };
+
+    Ok(Commands::Client(ClientCommand::Unblock(id, error)))
+}
+
+impl KillFilter {
+    pub fn matches(&self, client: &ClientInfo, current_id: u64) -> bool {
+        if self.skip_me && client.id == current_id {
+            return false;
+        }
+
+        self.id.is_none_or(|id| id == client.id)
+            && self
+                .addr
+                .as_ref()
+                .is_none_or(|addr| address_matches(addr, &client.addr))
+            && self
+                .laddr
+                .as_ref()
+                .is_none_or(|laddr| address_matches(laddr, &client.laddr))
+            && self
+                .user
+                .as_ref()
+                .is_none_or(|user| glob::matches(user.as_bytes(), client.user.as_bytes(), false))
+            && self.kind.is_none_or(|kind| kind == client.kind)
+    }
+}
+
+/// An address filter is either the exact address or a glob pattern, as the brackets around
+/// IPv6 addresses would otherwise be read as a character class.
+fn address_matches(filter: &str, address: &str) -> bool {
+    filter == address || glob::matches(filter.as_bytes(), address.as_bytes(), false)
+}
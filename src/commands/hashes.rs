@@ -0,0 +1,324 @@
+use bytes::Bytes;
+
+use crate::database::value::MAX_FIELD_EXPIRE_MS;
+
+use super::{
+    expiration, float, integer, keyword, random_count, text, CommandError, Commands, Expiration,
+    ExpireCondition, HSetExCommand, SetCondition, TimeUnit, TtlUpdate,
+};
+
+pub fn parse_hset(args: &[Bytes]) -> Result<Commands, CommandError> {
+    if !args.len().is_multiple_of(2) {
+        return Err(CommandError::WrongArity("hset".to_string()));
+    }
+
+    let pairs = args[2..]
+        .chunks(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect();
+
+    Ok(Commands::HSet(args[1].clone(), pairs))
+}
+
+pub fn parse_hsetnx(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::HSetNx(
+        args[1].clone(),
+        args[2].clone(),
+        args[3].clone(),
+    ))
+}
+
+pub fn parse_hget(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::HGet(args[1].clone(), args[2].clone()))
+}
+
+pub fn parse_hmget(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::HMGet(args[1].clone(), args[2..].to_vec()))
+}
+
+pub fn parse_hdel(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::HDel(args[1].clone(), args[2..].to_vec()))
+}
+
+pub fn parse_hexists(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::HExists(args[1].clone(), args[2].clone()))
+}
+
+pub fn parse_hlen(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::HLen(args[1].clone()))
+}
+
+pub fn parse_hstrlen(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::HStrLen(args[1].clone(), args[2].clone()))
+}
+
+pub fn parse_hkeys(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::HKeys(args[1].clone()))
+}
+
+pub fn parse_hvals(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::HVals(args[1].clone()))
+}
+
+pub fn parse_hgetall(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::HGetAll(args[1].clone()))
+}
+
+pub fn parse_hincrby(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::HIncrBy(
+        args[1].clone(),
+        args[2].clone(),
+        integer(&args[3])?,
+    ))
+}
+
+pub fn parse_hincrbyfloat(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let increment = float(&args[3])?;
+
+    if increment.is_infinite() {
+        return Err(CommandError::from("ERR value is NaN or Infinity"));
+    }
+
+    Ok(Commands::HIncrByFloat(
+        args[1].clone(),
+        args[2].clone(),
+        increment,
+    ))
+}
+
+pub fn parse_hrandfield(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let count = args.get(2).map(|count| random_count(count)).transpose()?;
+
+    let with_values = match args.get(3) {
+        Some(option) if args.len() == 4 && keyword(option) == "WITHVALUES" => true,
+
+        Some(_) => return Err(CommandError::Syntax),
+
+        None => false,
+    };
+
+    Ok(Commands::HRandField(args[1].clone(), count, with_values))
+}
+
+/// The arguments of the `FIELDS numfields ...` block that must end the command, starting
+/// at `args[at]`, each field taking `per_field` arguments.
+fn fields(args: &[Bytes], at: usize, per_field: usize) -> Result<&[Bytes], CommandError> {
+    if args.get(at).is_none_or(|arg| keyword(arg) != "FIELDS") {
+        return Err(CommandError::from(
+            "ERR Mandatory argument FIELDS is missing or not at the right position",
+        ));
+    }
+
+    let count = args
+        .get(at + 1)
+        .and_then(|count| integer(count).ok())
+        .filter(|count| *count > 0)
+        .ok_or_else(|| CommandError::from("ERR Parameter `numFields` should be greater than 0"))?;
+
+    let fields = &args[at + 2..];
+
+    if fields.len() as u64 != count as u64 * per_field as u64 {
+        return Err(CommandError::from(
+            "ERR The `numfields` parameter must match the number of arguments",
+        ));
+    }
+
+    Ok(fields)
+}
+
+/// Shared by the `HEXPIRE` family, like the `EXPIRE` one: `unit` is the number of
+/// milliseconds in one unit of the time argument, and `absolute` tells a Unix time from a
+/// duration. At most one condition may be given.
+fn parse_hexpire_command(
+    args: &[Bytes],
+    unit: i64,
+    absolute: bool,
+) -> Result<Commands, CommandError> {
+    let value = integer(&args[2])?;
+
+    if value < 0 {
+        return Err(CommandError::from("ERR invalid expire time, must be >= 0"));
+    }
+
+    let ms = value
+        .checked_mul(unit)
+        .filter(|ms| *ms <= MAX_FIELD_EXPIRE_MS)
+        .ok_or_else(|| CommandError::InvalidExpireTime(text(&args[0]).to_lowercase()))?;
+
+    let expiration = if absolute {
+        Expiration::At(ms)
+    } else {
+        Expiration::In(ms)
+    };
+
+    let mut condition = ExpireCondition::default();
+
+    let at = match args.get(3).map(|arg| keyword(arg)).as_deref() {
+        Some("NX") => {
+            condition.nx = true;
+            4
+        }
+
+        Some("XX") => {
+            condition.xx = true;
+            4
+        }
+
+        Some("GT") => {
+            condition.gt = true;
+            4
+        }
+
+        Some("LT") => {
+            condition.lt = true;
+            4
+        }
+
+        _ => 3,
+    };
+
+    let fields = fields(args, at, 1)?.to_vec();
+
+    Ok(Commands::HExpire(
+        args[1].clone(),
+        expiration,
+        condition,
+        fields,
+    ))
+}
+
+pub fn parse_hexpire(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_hexpire_command(args, 1000, false)
+}
+
+pub fn parse_hpexpire(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_hexpire_command(args, 1, false)
+}
+
+pub fn parse_hexpireat(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_hexpire_command(args, 1000, true)
+}
+
+pub fn parse_hpexpireat(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_hexpire_command(args, 1, true)
+}
+
+pub fn parse_httl(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let fields = fields(args, 2, 1)?.to_vec();
+
+    Ok(Commands::HTtl(args[1].clone(), TimeUnit::Seconds, fields))
+}
+
+pub fn parse_hpttl(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let fields = fields(args, 2, 1)?.to_vec();
+
+    Ok(Commands::HTtl(
+        args[1].clone(),
+        TimeUnit::Milliseconds,
+        fields,
+    ))
+}
+
+pub fn parse_hexpiretime(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let fields = fields(args, 2, 1)?.to_vec();
+
+    Ok(Commands::HExpireTime(
+        args[1].clone(),
+        TimeUnit::Seconds,
+        fields,
+    ))
+}
+
+pub fn parse_hpexpiretime(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let fields = fields(args, 2, 1)?.to_vec();
+
+    Ok(Commands::HExpireTime(
+        args[1].clone(),
+        TimeUnit::Milliseconds,
+        fields,
+    ))
+}
+
+pub fn parse_hpersist(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let fields = fields(args, 2, 1)?.to_vec();
+
+    Ok(Commands::HPersist(args[1].clone(), fields))
+}
+
+/// `HGETEX key [EX s | PX ms | EXAT ts | PXAT ms-ts | PERSIST] FIELDS numfields field ...`
+pub fn parse_hgetex(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let mut update = None;
+
+    let mut i = 2;
+
+    while i < args.len() && keyword(&args[i]) != "FIELDS" {
+        let option = keyword(&args[i]);
+
+        match option.as_str() {
+            "PERSIST" if update.is_none() => update = Some(TtlUpdate::Persist),
+
+            "EX" | "PX" | "EXAT" | "PXAT" if i + 1 < args.len() && update.is_none() => {
+                update = Some(TtlUpdate::Expire(expiration(
+                    &option,
+                    &args[i + 1],
+                    "hgetex",
+                )?));
+
+                i += 1;
+            }
+
+            _ => return Err(CommandError::Syntax),
+        }
+
+        i += 1;
+    }
+
+    let fields = fields(args, i, 1)?.to_vec();
+
+    Ok(Commands::HGetEx(args[1].clone(), update, fields))
+}
+
+/// `HSETEX key [FNX | FXX] [EX s | PX ms | EXAT ts | PXAT ms-ts | KEEPTTL]
+/// FIELDS numfields field value ...`
+pub fn parse_hsetex(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let mut command = HSetExCommand {
+        key: args[1].clone(),
+        pairs: Vec::new(),
+        condition: None,
+        keep_ttl: false,
+        expiration: None,
+    };
+
+    let mut i = 2;
+
+    while i < args.len() && keyword(&args[i]) != "FIELDS" {
+        let option = keyword(&args[i]);
+
+        let ttl_given = command.keep_ttl || command.expiration.is_some();
+
+        match option.as_str() {
+            "FNX" if command.condition.is_none() => command.condition = Some(SetCondition::Nx),
+
+            "FXX" if command.condition.is_none() => command.condition = Some(SetCondition::Xx),
+
+            "KEEPTTL" if !ttl_given => command.keep_ttl = true,
+
+            "EX" | "PX" | "EXAT" | "PXAT" if i + 1 < args.len() && !ttl_given => {
+                command.expiration = Some(expiration(&option, &args[i + 1], "hsetex")?);
+
+                i += 1;
+            }
+
+            _ => return Err(CommandError::Syntax),
+        }
+
+        i += 1;
+    }
+
+    command.pairs = fields(args, i, 2)?
+        .chunks(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect();
+
+    Ok(Commands::HSetEx(command))
+}
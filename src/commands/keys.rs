@@ -0,0 +1,165 @@
+use bytes::Bytes;
+
+use super::{
+    integer, keyword, text, CommandError, Commands, CopyCommand, Expiration, ExpireCondition,
+    TimeUnit,
+};
+
+pub fn parse_keys(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Keys(args[1].clone()))
+}
+
+pub fn parse_type(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Type(args[1].clone()))
+}
+
+pub fn parse_del(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Del(args[1..].to_vec()))
+}
+
+pub fn parse_exists(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Exists(args[1..].to_vec()))
+}
+
+pub fn parse_touch(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Touch(args[1..].to_vec()))
+}
+
+pub fn parse_rename(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Rename(args[1].clone(), args[2].clone(), false))
+}
+
+pub fn parse_renamenx(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Rename(args[1].clone(), args[2].clone(), true))
+}
+
+pub fn parse_copy(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let mut command = CopyCommand {
+        source: args[1].clone(),
+        destination: args[2].clone(),
+        db: None,
+        replace: false,
+    };
+
+    let mut i = 3;
+
+    while i < args.len() {
+        match keyword(&args[i]).as_str() {
+            "REPLACE" => command.replace = true,
+
+            "DB" if i + 1 < args.len() => {
+                command.db = Some(integer(&args[i + 1])?);
+
+                i += 1;
+            }
+
+            _ => return Err(CommandError::Syntax),
+        }
+
+        i += 1;
+    }
+
+    Ok(Commands::Copy(command))
+}
+
+pub fn parse_randomkey(_args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::RandomKey)
+}
+
+pub fn parse_move(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Move(args[1].clone(), integer(&args[2])?))
+}
+
+/// Shared by the `EXPIRE` family: `unit` is the number of milliseconds in one unit of the
+/// time argument, and `absolute` tells a Unix time from a duration.
+fn parse_expire_command(
+    args: &[Bytes],
+    unit: i64,
+    absolute: bool,
+) -> Result<Commands, CommandError> {
+    let value = integer(&args[2])?;
+
+    let ms = value
+        .checked_mul(unit)
+        .ok_or_else(|| CommandError::InvalidExpireTime(text(&args[0]).to_lowercase()))?;
+
+    let expiration = if absolute {
+        Expiration::At(ms)
+    } else {
+        Expiration::In(ms)
+    };
+
+    let mut condition = ExpireCondition::default();
+
+    for arg in &args[3..] {
+        match keyword(arg).as_str() {
+            "NX" => condition.nx = true,
+
+            "XX" => condition.xx = true,
+
+            "GT" => condition.gt = true,
+
+            "LT" => condition.lt = true,
+
+            _ => {
+                return Err(CommandError::Custom(format!(
+                    "ERR Unsupported option {}",
+                    text(arg)
+                )))
+            }
+        }
+    }
+
+    if condition.nx && (condition.xx || condition.gt || condition.lt) {
+        return Err(CommandError::from(
+            "ERR NX and XX, GT or LT options at the same time are not compatible",
+        ));
+    }
+
+    if condition.gt && condition.lt {
+        return Err(CommandError::from(
+            "ERR GT and LT options at the same time are not compatible",
+        ));
+    }
+
+    Ok(Commands::Expire(args[1].clone(), expiration, condition))
+}
+
+pub fn parse_expire(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_expire_command(args, 1000, false)
+}
+
+pub fn parse_pexpire(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_expire_command(args, 1, false)
+}
+
+pub fn parse_expireat(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_expire_command(args, 1000, true)
+}
+
+pub fn parse_pexpireat(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_expire_command(args, 1, true)
+}
+
+pub fn parse_ttl(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Ttl(args[1].clone(), TimeUnit::Seconds))
+}
+
+pub fn parse_pttl(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Ttl(args[1].clone(), TimeUnit::Milliseconds))
+}
+
+pub fn parse_expiretime(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ExpireTime(args[1].clone(), TimeUnit::Seconds))
+}
+
+pub fn parse_pexpiretime(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ExpireTime(
+        args[1].clone(),
+        TimeUnit::Milliseconds,
+    ))
+}
+
+pub fn parse_persist(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Persist(args[1].clone()))
+}
@@ -0,0 +1,256 @@
+use bytes::Bytes;
+
+use super::{integer, keyword, CommandError, Commands, LPosOptions, ListEnd};
+
+pub fn parse_lpush(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Push(
+        args[1].clone(),
+        args[2..].to_vec(),
+        ListEnd::Left,
+        false,
+    ))
+}
+
+pub fn parse_rpush(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Push(
+        args[1].clone(),
+        args[2..].to_vec(),
+        ListEnd::Right,
+        false,
+    ))
+}
+
+pub fn parse_lpushx(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Push(
+        args[1].clone(),
+        args[2..].to_vec(),
+        ListEnd::Left,
+        true,
+    ))
+}
+
+pub fn parse_rpushx(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Push(
+        args[1].clone(),
+        args[2..].to_vec(),
+        ListEnd::Right,
+        true,
+    ))
+}
+
+pub fn parse_lpop(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Pop(
+        args[1].clone(),
+        ListEnd::Left,
+        pop_count(args)?,
+    ))
+}
+
+pub fn parse_rpop(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Pop(
+        args[1].clone(),
+        ListEnd::Right,
+        pop_count(args)?,
+    ))
+}
+
+/// The optional count of `LPOP` and `RPOP`.
+fn pop_count(args: &[Bytes]) -> Result<Option<usize>, CommandError> {
+    match args.get(2) {
+        Some(count) => usize::try_from(integer(count)?)
+            .map(Some)
+            .map_err(|_| CommandError::from("ERR value is out of range, must be positive")),
+
+        None => Ok(None),
+    }
+}
+
+pub fn parse_lrange(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::LRange(
+        args[1].clone(),
+        integer(&args[2])?,
+        integer(&args[3])?,
+    ))
+}
+
+pub fn parse_lindex(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::LIndex(args[1].clone(), integer(&args[2])?))
+}
+
+pub fn parse_lset(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::LSet(
+        args[1].clone(),
+        integer(&args[2])?,
+        args[3].clone(),
+    ))
+}
+
+pub fn parse_llen(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::LLen(args[1].clone()))
+}
+
+pub fn parse_lrem(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::LRem(
+        args[1].clone(),
+        integer(&args[2])?,
+        args[3].clone(),
+    ))
+}
+
+pub fn parse_ltrim(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::LTrim(
+        args[1].clone(),
+        integer(&args[2])?,
+        integer(&args[3])?,
+    ))
+}
+
+pub fn parse_linsert(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let before = match keyword(&args[2]).as_str() {
+        "BEFORE" => true,
+
+        "AFTER" => false,
+
+        _ => return Err(CommandError::Syntax),
+    };
+
+    Ok(Commands::LInsert(
+        args[1].clone(),
+        before,
+        args[3].clone(),
+        args[4].clone(),
+    ))
+}
+
+pub fn parse_lpos(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let mut options = LPosOptions {
+        rank: 1,
+        count: None,
+        max_len: 0,
+    };
+
+    for pair in args[3..].chunks(2) {
+        let [option, value] = pair else {
+            return Err(CommandError::Syntax);
+        };
+
+        match keyword(option).as_str() {
+            "RANK" => {
+                options.rank = match integer(value)? {
+                    0 => {
+                        return Err(CommandError::from(
+                            "ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list",
+                        ))
+                    }
+
+                    i64::MIN => {
+                        return Err(CommandError::from(
+                            "ERR value is out of range, value must between -9223372036854775807 and 9223372036854775807",
+                        ))
+                    }
+
+                    rank => rank,
+                };
+            }
+
+            "COUNT" => {
+                options.count = Some(
+                    usize::try_from(integer(value)?)
+                        .map_err(|_| CommandError::from("ERR COUNT can't be negative"))?,
+                );
+            }
+
+            "MAXLEN" => {
+                options.max_len = usize::try_from(integer(value)?)
+                    .map_err(|_| CommandError::from("ERR MAXLEN can't be negative"))?;
+            }
+
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    Ok(Commands::LPos(args[1].clone(), args[2].clone(), options))
+}
+
+pub fn parse_lmove(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::LMove(
+        args[1].clone(),
+        args[2].clone(),
+        list_end(&args[3])?,
+        list_end(&args[4])?,
+    ))
+}
+
+pub fn parse_rpoplpush(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::LMove(
+        args[1].clone(),
+        args[2].clone(),
+        ListEnd::Right,
+        ListEnd::Left,
+    ))
+}
+
+pub fn parse_lmpop(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let numkeys = integer(&args[1])
+        .ok()
+        .and_then(|numkeys| usize::try_from(numkeys).ok())
+        .filter(|numkeys| *numkeys > 0)
+        .ok_or_else(|| CommandError::from("ERR numkeys should be greater than 0"))?;
+
+    let end_idx = numkeys.saturating_add(2);
+
+    if end_idx >= args.len() {
+        return Err(CommandError::Syntax);
+    }
+
+    let end = list_end(&args[end_idx])?;
+
+    let mut count = None;
+
+    let mut i = end_idx + 1;
+
+    while i < args.len() {
+        match args.get(i + 1) {
+            Some(value) if count.is_none() && keyword(&args[i]) == "COUNT" => {
+                count = Some(
+                    integer(value)
+                        .ok()
+                        .and_then(|count| usize::try_from(count).ok())
+                        .filter(|count| *count > 0)
+                        .ok_or_else(|| CommandError::from("ERR count should be greater than 0"))?,
+                );
+            }
+
+            _ => return Err(CommandError::Syntax),
+        }
+
+        i += 2;
+    }
+
+    Ok(Commands::LMPop(
+        args[2..end_idx].to_vec(),
+        end,
+        count.unwrap_or(1),
+    ))
+}
+
+/// Key positions of `LMPOP numkeys key [key ...] ...`.
+pub fn lmpop_keys(args: &[Bytes]) -> Vec<usize> {
+    let numkeys = args
+        .get(1)
+        .and_then(|numkeys| integer(numkeys).ok())
+        .and_then(|numkeys| usize::try_from(numkeys).ok())
+        .unwrap_or(0);
+
+    (2..args.len()).take(numkeys).collect()
+}
+
+pub fn list_end(arg: &[u8]) -> Result<ListEnd, CommandError> {
+    match keyword(arg).as_str() {
+        "LEFT" => Ok(ListEnd::Left),
+
+        "RIGHT" => Ok(ListEnd::Right),
+
+        _ => Err(CommandError::Syntax),
+    }
+}
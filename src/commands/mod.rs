@@ -0,0 +1,821 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use crate::client::{ClientKind, ReplyMode};
+use crate::database::value::{LexBound, ScoreBound};
+use crate::resp::{ProtocolVersion, RespDataTypes};
+
+mod blocking;
+mod connection;
+mod hashes;
+mod keys;
+mod lists;
+mod scan;
+mod server;
+mod sets;
+mod sorted_sets;
+mod strings;
+pub mod table;
+
+use table::CommandSpec;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CommandError {
+    #[error("ERR unknown command '{0}', with args beginning with: {1}")]
+    UnknownCommand(String, String),
+
+    #[error("ERR unknown subcommand '{0}'. Try {1} HELP.")]
+    UnknownSubcommand(String, String),
+
+    #[error("ERR wrong number of arguments for '{0}' command")]
+    WrongArity(String),
+
+    #[error("ERR syntax error")]
+    Syntax,
+
+    #[error("ERR value is not an integer or out of range")]
+    NotInteger,
+
+    #[error("ERR invalid expire time in '{0}' command")]
+    InvalidExpireTime(String),
+
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        Self::Custom(message.to_string())
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug)]
+pub enum Commands {
+    Ping(Option<Bytes>),
+
+    Echo(Bytes),
+
+    Set(SetCommand),
+
+    Get(Bytes),
+
+    GetEx(Bytes, Option<TtlUpdate>),
+
+    GetDel(Bytes),
+
+    GetSet(Bytes, Bytes),
+
+    /// `EXPIRE`, `PEXPIRE`, `EXPIREAT` and `PEXPIREAT`.
+    Expire(Bytes, Expiration, ExpireCondition),
+
+    /// `TTL` and `PTTL`.
+    Ttl(Bytes, TimeUnit),
+
+    /// `EXPIRETIME` and `PEXPIRETIME`.
+    ExpireTime(Bytes, TimeUnit),
+
+    Persist(Bytes),
+
+    Del(Vec<Bytes>),
+
+    /// Keys named twice are counted twice.
+    Exists(Vec<Bytes>),
+
+    Touch(Vec<Bytes>),
+
+    /// `RENAME`, or `RENAMENX` when the flag is set.
+    Rename(Bytes, Bytes, bool),
+
+    Copy(CopyCommand),
+
+    RandomKey,
+
+    /// `LPUSH`, `RPUSH`, and `LPUSHX`/`RPUSHX` when the flag only allows existing lists.
+    Push(Bytes, Vec<Bytes>, ListEnd, bool),
+
+    /// `LPOP` and `RPOP`, with their optional count.
+    Pop(Bytes, ListEnd, Option<usize>),
+
+    LRange(Bytes, i64, i64),
+
+    LIndex(Bytes, i64),
+
+    LSet(Bytes, i64, Bytes),
+
+    LLen(Bytes),
+
+    LRem(Bytes, i64, Bytes),
+
+    LTrim(Bytes, i64, i64),
+
+    /// `LINSERT key BEFORE|AFTER pivot element`, `true` for `BEFORE`.
+    LInsert(Bytes, bool, Bytes, Bytes),
+
+    LPos(Bytes, Bytes, LPosOptions),
+
+    /// `LMOVE` and `RPOPLPUSH`: pop from one end of the source, push to one end of the
+    /// destination.
+    LMove(Bytes, Bytes, ListEnd, ListEnd),
+
+    LMPop(Vec<Bytes>, ListEnd, usize),
+
+    /// A blocking pop: the keys to wait on, what to do with the first of them that has
+    /// something to pop, and how long to wait, forever when `None`.
+    Blocking(Vec<Bytes>, BlockingOp, Option<Duration>),
+
+    /// `HSET key field value [field value ...]`
+    HSet(Bytes, Vec<(Bytes, Bytes)>),
+
+    HSetNx(Bytes, Bytes, Bytes),
+
+    HGet(Bytes, Bytes),
+
+    HMGet(Bytes, Vec<Bytes>),
+
+    HDel(Bytes, Vec<Bytes>),
+
+    HExists(Bytes, Bytes),
+
+    HLen(Bytes),
+
+    HStrLen(Bytes, Bytes),
+
+    HKeys(Bytes),
+
+    HVals(Bytes),
+
+    HGetAll(Bytes),
+
+    HIncrBy(Bytes, Bytes, i64),
+
+    HIncrByFloat(Bytes, Bytes, f64),
+
+    /// `HRANDFIELD key [count [WITHVALUES]]`; a negative count allows repeated fields.
+    HRandField(Bytes, Option<i64>, bool),
+
+    /// The `HEXPIRE` family: key, expiration, condition and the fields to expire.
+    HExpire(Bytes, Expiration, ExpireCondition, Vec<Bytes>),
+
+    /// `HTTL` and `HPTTL`
+    HTtl(Bytes, TimeUnit, Vec<Bytes>),
+
+    /// `HEXPIRETIME` and `HPEXPIRETIME`
+    HExpireTime(Bytes, TimeUnit, Vec<Bytes>),
+
+    HPersist(Bytes, Vec<Bytes>),
+
+    /// `HGETEX`: the fields to read, and what to do with their expiration.
+    HGetEx(Bytes, Option<TtlUpdate>, Vec<Bytes>),
+
+    HSetEx(HSetExCommand),
+
+    SAdd(Bytes, Vec<Bytes>),
+
+    SRem(Bytes, Vec<Bytes>),
+
+    SIsMember(Bytes, Bytes),
+
+    SMIsMember(Bytes, Vec<Bytes>),
+
+    SMembers(Bytes),
+
+    SCard(Bytes),
+
+    /// `SPOP key [count]`; without a count a single member is popped.
+    SPop(Bytes, Option<usize>),
+
+    /// `SRANDMEMBER key [count]`; a negative count allows repeated members.
+    SRandMember(Bytes, Option<i64>),
+
+    /// `SMOVE source destination member`
+    SMove(Bytes, Bytes, Bytes),
+
+    /// `SINTER`, `SUNION` and `SDIFF` over the keys, storing the result at the destination
+    /// for their `STORE` variants.
+    SetAlgebra(SetOperation, Option<Bytes>, Vec<Bytes>),
+
+    /// `SINTERCARD numkeys key [key ...] [LIMIT limit]`, a limit of 0 meaning none.
+    SInterCard(Vec<Bytes>, usize),
+
+    /// `ZADD`, and `ZINCRBY` as `ZADD INCR`.
+    ZAdd(ZAddCommand),
+
+    ZRem(Bytes, Vec<Bytes>),
+
+    ZScore(Bytes, Bytes),
+
+    ZMScore(Bytes, Vec<Bytes>),
+
+    ZCard(Bytes),
+
+    /// `ZCOUNT` over a score range and `ZLEXCOUNT` over a lex one.
+    ZCount(Bytes, ZRange),
+
+    /// `ZRANK` and `ZREVRANK`: the member, whether ranks count from the highest score, and
+    /// `WITHSCORE`.
+    ZRank(Bytes, Bytes, bool, bool),
+
+    /// `ZRANGE` and its older forms like `ZRANGEBYSCORE` or `ZREVRANGE`.
+    ZRange(ZRangeCommand),
+
+    /// `ZREMRANGEBYRANK`, `ZREMRANGEBYSCORE` and `ZREMRANGEBYLEX`.
+    ZRemRange(Bytes, ZRange),
+
+    /// `ZPOPMIN` and `ZPOPMAX`: whether the highest scores go first, and the count if one
+    /// was given.
+    ZPop(Bytes, bool, Option<usize>),
+
+    /// `ZRANDMEMBER key [count [WITHSCORES]]`; a negative count allows repeated members.
+    ZRandMember(Bytes, Option<i64>, bool),
+
+    /// `ZMPOP numkeys key [key ...] MIN|MAX [COUNT count]`, `true` popping the highest
+    /// scores.
+    ZMPop(Vec<Bytes>, bool, usize),
+
+    /// `ZUNION`, `ZINTER`, `ZDIFF` and their `STORE` variants.
+    ZSetAlgebra(ZSetAlgebraCommand),
+
+    /// `ZRANGESTORE dst src min max ...`, the range being that of `ZRANGE`.
+    ZRangeStore(Bytes, ZRangeCommand),
+
+    /// `ZINTERCARD numkeys key [key ...] [LIMIT limit]`, a limit of 0 meaning none.
+    ZInterCard(Vec<Bytes>, usize),
+
+    Scan(ScanCommand),
+
+    HScan(Bytes, ScanCommand),
+
+    SScan(Bytes, ScanCommand),
+
+    ZScan(Bytes, ScanCommand),
+
+    Type(Bytes),
+
+    ConfigGet(Vec<String>),
+
+    Keys(Bytes),
+
+    Info(Option<String>),
+
+    REPLCONF(String, String),
+
+    PSYNC(String, String),
+
+    /// `HELLO [protover [AUTH username password] [SETNAME clientname]]`
+    Hello(
+        Option<ProtocolVersion>,
+        Option<(String, String)>,
+        Option<String>,
+    ),
+
+    Command(CommandIntrospection),
+
+    Client(ClientCommand),
+
+    Select(i64),
+
+    SwapDb(i64, i64),
+
+    Move(Bytes, i64),
+
+    /// `true` when asked to free the memory in the background.
+    FlushDb(bool),
+
+    FlushAll(bool),
+
+    DbSize,
+
+    Save,
+
+    Debug(DebugCommand),
+}
+
+/// Subcommands of `CLIENT`.
+#[derive(Debug)]
+pub enum ClientCommand {
+    Id,
+
+    /// `None` clears the name.
+    SetName(Option<String>),
+
+    GetName,
+
+    /// Optional `TYPE` and `ID` filters.
+    List(Option<ClientKind>, Vec<u64>),
+
+    Info,
+
+    Kill(KillFilter),
+
+    Reply(ReplyMode),
+
+    /// `CLIENT UNBLOCK id [TIMEOUT|ERROR]`, `true` to fail the blocked command with an error
+    /// instead of answering as if it timed out.
+    Unblock(u64, bool),
+}
+
+/// Subcommands of `DEBUG`.
+#[derive(Debug)]
+pub enum DebugCommand {
+    /// `SET-ACTIVE-EXPIRE 0|1` turns the background expire cycle off or back on.
+    SetActiveExpire(bool),
+
+    /// `FREEZE-TIME [unix-time-milliseconds]` stops the clock expirations are checked
+    /// against, at the given time or where it is now.
+    FreezeTime(Option<DateTime<Utc>>),
+
+    UnfreezeTime,
+
+    /// `ADVANCE-TIME milliseconds` moves frozen time forward.
+    AdvanceTime(u64),
+}
+
+/// Which clients `CLIENT KILL` closes; every filter that is set has to match.
+#[derive(Debug, Default)]
+pub struct KillFilter {
+    pub id: Option<u64>,
+
+    pub addr: Option<String>,
+
+    pub laddr: Option<String>,
+
+    pub user: Option<String>,
+
+    pub kind: Option<ClientKind>,
+
+    pub skip_me: bool,
+
+    /// The old `CLIENT KILL addr:port` form, answered with OK or an error instead of a count.
+    pub legacy: bool,
+}
+
+/// An expiration given on the command line. Relative ones are resolved against the clock
+/// when the command runs, not when it is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiration {
+    /// `EX`/`PX`, in milliseconds from now.
+    In(i64),
+
+    /// `EXAT`/`PXAT`, as a Unix time in milliseconds.
+    At(i64),
+}
+
+impl Expiration {
+    /// The point in time this expiration refers to, or `None` when it can not be represented.
+    pub fn deadline(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let ms = match self {
+            Self::In(ms) => now.timestamp_millis().checked_add(*ms)?,
+
+            Self::At(ms) => *ms,
+        };
+
+        DateTime::from_timestamp_millis(ms)
+    }
+}
+
+/// What a command does to the expiration of the key it writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TtlUpdate {
+    /// `PERSIST`
+    Persist,
+
+    Expire(Expiration),
+}
+
+/// The `NX`, `XX`, `GT` and `LT` flags of the `EXPIRE` family.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExpireCondition {
+    /// Only when the key has no expiration.
+    pub nx: bool,
+
+    /// Only when the key has an expiration.
+    pub xx: bool,
+
+    /// Only when the new expiration is later than the current one.
+    pub gt: bool,
+
+    /// Only when the new expiration is earlier than the current one.
+    pub lt: bool,
+}
+
+impl ExpireCondition {
+    /// Whether `deadline` may replace `current`; a key without expiration counts as
+    /// expiring never, so `GT` refuses it and `LT` accepts it.
+    pub fn allows(&self, current: Option<DateTime<Utc>>, deadline: DateTime<Utc>) -> bool {
+        match current {
+            None => !self.xx && !self.gt,
+
+            Some(current) => {
+                !self.nx && (!self.gt || deadline > current) && (!self.lt || deadline < current)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    Seconds,
+
+    Milliseconds,
+}
+
+/// `SET` only writes when the key is missing (`NX`) or when it exists (`XX`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    Nx,
+
+    Xx,
+}
+
+#[derive(Debug)]
+pub struct SetCommand {
+    pub key: Bytes,
+
+    pub value: Bytes,
+
+    pub condition: Option<SetCondition>,
+
+    /// `GET`: reply with the old value instead of `OK`.
+    pub get: bool,
+
+    /// `KEEPTTL`
+    pub keep_ttl: bool,
+
+    pub expiration: Option<Expiration>,
+}
+
+/// `HSETEX`, which sets fields together with their expiration.
+#[derive(Debug)]
+pub struct HSetExCommand {
+    pub key: Bytes,
+
+    pub pairs: Vec<(Bytes, Bytes)>,
+
+    /// `FNX`: only when none of the fields exist; `FXX`: only when all of them do.
+    pub condition: Option<SetCondition>,
+
+    /// `KEEPTTL`
+    pub keep_ttl: bool,
+
+    pub expiration: Option<Expiration>,
+}
+
+/// `COPY source destination [DB destination-db] [REPLACE]`
+#[derive(Debug)]
+pub struct CopyCommand {
+    pub source: Bytes,
+
+    pub destination: Bytes,
+
+    /// Target database; the client's own when `None`.
+    pub db: Option<i64>,
+
+    pub replace: bool,
+}
+
+/// Which end of a list a command works on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+
+    Right,
+}
+
+impl ListEnd {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Left => "LEFT",
+
+            Self::Right => "RIGHT",
+        }
+    }
+}
+
+/// How `SINTER`, `SUNION`, `SDIFF` and their `STORE` variants combine sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    Inter,
+
+    Union,
+
+    Diff,
+}
+
+impl SetOperation {
+    /// Name of the variant of the command that stores its result.
+    pub fn store_command(&self) -> &'static str {
+        match self {
+            Self::Inter => "SINTERSTORE",
+
+            Self::Union => "SUNIONSTORE",
+
+            Self::Diff => "SDIFFSTORE",
+        }
+    }
+}
+
+/// `ZADD key [NX | XX] [GT | LT] [CH] [INCR] score member [score member ...]`
+#[derive(Debug)]
+pub struct ZAddCommand {
+    pub key: Bytes,
+
+    pub pairs: Vec<(f64, Bytes)>,
+
+    /// Only add new members.
+    pub nx: bool,
+
+    /// Only update existing members.
+    pub xx: bool,
+
+    /// Only update scores to greater ones, new members are still added.
+    pub gt: bool,
+
+    /// Only update scores to lesser ones.
+    pub lt: bool,
+
+    /// `CH`, counting updated members along with new ones in the reply.
+    pub changed: bool,
+
+    /// Add the score to the existing one and reply with the result, like `ZINCRBY`.
+    pub incr: bool,
+}
+
+/// Which members a `ZRANGE` style command selects.
+#[derive(Debug, Clone)]
+pub enum ZRange {
+    /// Start and stop ranks, negative ones counting from the end.
+    Rank(i64, i64),
+
+    Score(ScoreBound, ScoreBound),
+
+    Lex(LexBound, LexBound),
+}
+
+#[derive(Debug)]
+pub struct ZRangeCommand {
+    pub key: Bytes,
+
+    pub range: ZRange,
+
+    /// Go from the highest score down.
+    pub rev: bool,
+
+    /// `LIMIT offset count`; a negative count means no limit.
+    pub limit: Option<(i64, i64)>,
+
+    pub with_scores: bool,
+}
+
+/// `ZUNION numkeys key [key ...] [WEIGHTS weight ...] [AGGREGATE SUM | MIN | MAX]
+/// [WITHSCORES]`, `ZINTER`, `ZDIFF`, which takes neither weights nor an aggregate, and
+/// their `STORE` variants, which take a destination instead of `WITHSCORES`.
+#[derive(Debug)]
+pub struct ZSetAlgebraCommand {
+    pub operation: SetOperation,
+
+    pub destination: Option<Bytes>,
+
+    pub keys: Vec<Bytes>,
+
+    /// What the scores of each key are multiplied by, 1 unless given.
+    pub weights: Vec<f64>,
+
+    pub aggregate: Aggregate,
+
+    pub with_scores: bool,
+}
+
+/// How `ZUNION` and `ZINTER` combine the scores a member has in several sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum,
+
+    Min,
+
+    Max,
+}
+
+impl Aggregate {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sum => "SUM",
+
+            Self::Min => "MIN",
+
+            Self::Max => "MAX",
+        }
+    }
+
+    /// Combines two scores, a sum of opposite infinities giving 0.
+    pub fn apply(&self, a: f64, b: f64) -> f64 {
+        match self {
+            Self::Sum => {
+                let sum = a + b;
+
+                if sum.is_nan() {
+                    0.0
+                } else {
+                    sum
+                }
+            }
+
+            Self::Min => a.min(b),
+
+            Self::Max => a.max(b),
+        }
+    }
+}
+
+/// What a blocked client does once one of its keys has something to pop.
+#[derive(Debug, Clone)]
+pub enum BlockingOp {
+    /// `BLPOP` and `BRPOP`
+    Pop(ListEnd),
+
+    /// `BLMOVE` and `BRPOPLPUSH`: the destination, the end to pop from and the end to push
+    /// to.
+    Move(Bytes, ListEnd, ListEnd),
+
+    /// `BLMPOP`, up to a count of elements.
+    MPop(ListEnd, usize),
+
+    ZPopMin,
+
+    ZPopMax,
+}
+
+impl BlockingOp {
+    /// Type of the values the operation pops from, as `TYPE` names it.
+    pub fn value_type(&self) -> &'static str {
+        match self {
+            Self::Pop(_) | Self::Move(..) | Self::MPop(..) => "list",
+
+            Self::ZPopMin | Self::ZPopMax => "zset",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LPosOptions {
+    /// Which match to start from, negative ones counting from the tail.
+    pub rank: i64,
+
+    /// How many matches to return, all of them for 0. `None` replies with a single index
+    /// instead of an array.
+    pub count: Option<usize>,
+
+    /// Compare at most this many elements, 0 for no limit.
+    pub max_len: usize,
+}
+
+/// Options shared by `SCAN`, `HSCAN`, `SSCAN` and `ZSCAN`.
+#[derive(Debug)]
+pub struct ScanCommand {
+    pub cursor: u64,
+
+    /// `MATCH`
+    pub pattern: Option<Bytes>,
+
+    /// `COUNT`, how much work to do in one call rather than an exact number of elements.
+    pub count: usize,
+
+    /// `TYPE`, only for `SCAN`.
+    pub kind: Option<String>,
+
+    /// `NOVALUES`, only for `HSCAN`.
+    pub no_values: bool,
+}
+
+/// Subcommands of `COMMAND`, answered straight from the command table.
+#[derive(Debug)]
+pub enum CommandIntrospection {
+    All,
+
+    Count,
+
+    /// Command names to describe; empty means every command.
+    Info(Vec<String>),
+
+    Docs(Vec<String>),
+
+    List(Option<ListFilter>),
+
+    /// A full command line whose key arguments should be extracted.
+    GetKeys(Vec<Bytes>),
+}
+
+#[derive(Debug)]
+pub enum ListFilter {
+    /// No modules can be loaded, so this filter never matches anything.
+    #[allow(dead_code)]
+    Module(String),
+
+    AclCategory(String),
+
+    Pattern(String),
+}
+
+impl Commands {
+    /// Flattens a client request into its arguments, command name included.
+    fn decode_arguments(value: RespDataTypes) -> Result<Vec<Bytes>, CommandError> {
+        let RespDataTypes::Array(items) = value else {
+            return Err(CommandError::from(
+                "ERR Protocol error: expected a command array",
+            ));
+        };
+
+        items
+            .into_iter()
+            .map(|item| match item {
+                RespDataTypes::BulkString(bytes) => Ok(bytes),
+
+                RespDataTypes::SimpleString(string) => Ok(Bytes::from(string)),
+
+                RespDataTypes::Integer(int) => Ok(Bytes::from(int.to_string())),
+
+                _ => Err(CommandError::from(
+                    "ERR Protocol error: expected command arguments to be strings",
+                )),
+            })
+            .collect()
+    }
+}
+
+impl Commands {
+    /// Parses a client request, also returning the table entry that handles it.
+    pub fn parse(value: RespDataTypes) -> Result<(&'static CommandSpec, Self), CommandError> {
+        let args = Self::decode_arguments(value)?;
+
+        let spec = table::resolve(&args)?;
+
+        Ok((spec, (spec.parse)(&args)?))
+    }
+}
+
+/// Lossy text view of an argument that is a keyword or a name rather than user data.
+pub fn text(arg: &[u8]) -> String {
+    String::from_utf8_lossy(arg).into_owned()
+}
+
+/// Parses an argument as a signed 64-bit integer, failing with the standard Redis error.
+pub fn integer(arg: &[u8]) -> Result<i64, CommandError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|text| text.parse::<i64>().ok())
+        .ok_or(CommandError::NotInteger)
+}
+
+/// Parses the count of `HRANDFIELD`, `SRANDMEMBER` or `ZRANDMEMBER`, where a negative
+/// count allows repetitions. Like Redis, counts so negative that the reply could never be
+/// built are refused.
+pub fn random_count(arg: &[u8]) -> Result<i64, CommandError> {
+    let count = integer(arg)?;
+
+    if count < -(i64::MAX / 2) {
+        return Err(CommandError::from("ERR value is out of range"));
+    }
+
+    Ok(count)
+}
+
+/// Parses an argument as a double, rejecting `nan` like Redis does.
+pub fn float(arg: &[u8]) -> Result<f64, CommandError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|text| text.parse::<f64>().ok())
+        .filter(|value| !value.is_nan())
+        .ok_or_else(|| CommandError::from("ERR value is not a valid float"))
+}
+
+/// Parses the argument of an `EX`, `PX`, `EXAT` or `PXAT` option of `command`.
+pub fn expiration(unit: &str, arg: &[u8], command: &str) -> Result<Expiration, CommandError> {
+    let value = integer(arg)?;
+
+    let invalid_expire = || CommandError::InvalidExpireTime(command.to_string());
+
+    if value <= 0 {
+        return Err(invalid_expire());
+    }
+
+    match unit {
+        "EX" => value.checked_mul(1000).map(Expiration::In),
+
+        "PX" => Some(Expiration::In(value)),
+
+        "EXAT" => value.checked_mul(1000).map(Expiration::At),
+
+        "PXAT" => Some(Expiration::At(value)),
+
+        _ => return Err(CommandError::Syntax),
+    }
+    .ok_or_else(invalid_expire)
+}
+
+/// Upper-cased text of an argument, for matching keywords like `EX` or `NX`.
+pub fn keyword(arg: &[u8]) -> String {
+    String::from_utf8_lossy(arg).to_uppercase()
+}
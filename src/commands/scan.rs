@@ -0,0 +1,89 @@
+use bytes::Bytes;
+
+use super::{integer, keyword, text, CommandError, Commands, ScanCommand};
+
+/// Elements a scan looks at per call when no `COUNT` is given.
+const DEFAULT_SCAN_COUNT: usize = 10;
+
+/// Type names `SCAN TYPE` can filter on.
+const SCAN_TYPES: &[&str] = &["string", "list", "set", "zset", "hash", "stream"];
+
+pub fn parse_scan(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Scan(scan_command(args, 1)?))
+}
+
+pub fn parse_hscan(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::HScan(args[1].clone(), scan_command(args, 2)?))
+}
+
+pub fn parse_sscan(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SScan(args[1].clone(), scan_command(args, 2)?))
+}
+
+pub fn parse_zscan(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZScan(args[1].clone(), scan_command(args, 2)?))
+}
+
+/// Parses the cursor at `cursor` and the options after it. `TYPE` is only accepted by
+/// `SCAN` and `NOVALUES` only by `HSCAN`.
+fn scan_command(args: &[Bytes], cursor: usize) -> Result<ScanCommand, CommandError> {
+    let command = keyword(&args[0]);
+
+    let mut scan = ScanCommand {
+        cursor: text(&args[cursor])
+            .parse::<u64>()
+            .map_err(|_| CommandError::from("ERR invalid cursor"))?,
+        pattern: None,
+        count: DEFAULT_SCAN_COUNT,
+        kind: None,
+        no_values: false,
+    };
+
+    let mut i = cursor + 1;
+
+    while i < args.len() {
+        let option = keyword(&args[i]);
+
+        if option == "NOVALUES" && command == "HSCAN" {
+            scan.no_values = true;
+
+            i += 1;
+
+            continue;
+        }
+
+        let Some(value) = args.get(i + 1) else {
+            return Err(CommandError::Syntax);
+        };
+
+        match option.as_str() {
+            "MATCH" => scan.pattern = Some(value.clone()),
+
+            "COUNT" => {
+                scan.count = usize::try_from(integer(value)?)
+                    .ok()
+                    .filter(|count| *count >= 1)
+                    .ok_or(CommandError::Syntax)?;
+            }
+
+            "TYPE" if command == "SCAN" => {
+                let kind = text(value).to_lowercase();
+
+                if !SCAN_TYPES.contains(&kind.as_str()) {
+                    return Err(CommandError::Custom(format!(
+                        "ERR unknown type name '{}'",
+                        text(value)
+                    )));
+                }
+
+                scan.kind = Some(kind);
+            }
+
+            _ => return Err(CommandError::Syntax),
+        }
+
+        i += 2;
+    }
+
+    Ok(scan)
+}
@@ -0,0 +1,204 @@
+use bytes::Bytes;
+use chrono::DateTime;
+
+use crate::resp::ProtocolVersion;
+
+use super::connection::check_client_name;
+use super::{
+    integer, keyword, text, CommandError, CommandIntrospection, Commands, DebugCommand, ListFilter,
+};
+
+pub fn parse_ping(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Ping(args.get(1).cloned()))
+}
+
+pub fn parse_echo(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Echo(args[1].clone()))
+}
+
+pub fn parse_config_get(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ConfigGet(
+        args[2..].iter().map(|arg| text(arg)).collect(),
+    ))
+}
+
+pub fn parse_info(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Info(args.get(1).map(|arg| text(arg))))
+}
+
+pub fn parse_replconf(args: &[Bytes]) -> Result<Commands, CommandError> {
+    if args.len() != 3 {
+        return Err(CommandError::Syntax);
+    }
+
+    Ok(Commands::REPLCONF(text(&args[1]), text(&args[2])))
+}
+
+pub fn parse_psync(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::PSYNC(text(&args[1]), text(&args[2])))
+}
+
+pub fn parse_hello(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let Some(version) = args.get(1) else {
+        return Ok(Commands::Hello(None, None, None));
+    };
+
+    let version = text(version).parse::<i64>().map_err(|_| {
+        CommandError::from("ERR Protocol version is not an integer or out of range")
+    })?;
+
+    let protocol = ProtocolVersion::try_from(version)?;
+
+    let mut auth = None;
+    let mut name = None;
+
+    let mut i = 2;
+
+    while i < args.len() {
+        match keyword(&args[i]).as_str() {
+            "AUTH" if i + 2 < args.len() => {
+                auth = Some((text(&args[i + 1]), text(&args[i + 2])));
+
+                i += 3;
+            }
+
+            "SETNAME" if i + 1 < args.len() => {
+                check_client_name(&args[i + 1])?;
+
+                name = Some(text(&args[i + 1]));
+
+                i += 2;
+            }
+
+            _ => {
+                return Err(CommandError::Custom(format!(
+                    "ERR Syntax error in HELLO option '{}'",
+                    text(&args[i])
+                )))
+            }
+        }
+    }
+
+    Ok(Commands::Hello(Some(protocol), auth, name))
+}
+
+pub fn parse_command(_args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Command(CommandIntrospection::All))
+}
+
+pub fn parse_command_count(_args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Command(CommandIntrospection::Count))
+}
+
+pub fn parse_command_info(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Command(CommandIntrospection::Info(
+        args[2..].iter().map(|arg| text(arg)).collect(),
+    )))
+}
+
+pub fn parse_command_docs(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Command(CommandIntrospection::Docs(
+        args[2..].iter().map(|arg| text(arg)).collect(),
+    )))
+}
+
+pub fn parse_command_list(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let filter = match args.len() {
+        2 => None,
+
+        5 if keyword(&args[2]) == "FILTERBY" => {
+            let value = text(&args[4]);
+
+            match keyword(&args[3]).as_str() {
+                "MODULE" => Some(ListFilter::Module(value)),
+
+                "ACLCAT" => Some(ListFilter::AclCategory(value)),
+
+                "PATTERN" => Some(ListFilter::Pattern(value)),
+
+                _ => return Err(CommandError::Syntax),
+            }
+        }
+
+        _ => return Err(CommandError::Syntax),
+    };
+
+    Ok(Commands::Command(CommandIntrospection::List(filter)))
+}
+
+pub fn parse_command_getkeys(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Command(CommandIntrospection::GetKeys(
+        args[2..].to_vec(),
+    )))
+}
+
+pub fn parse_swapdb(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let first = integer(&args[1]).map_err(|_| CommandError::from("ERR invalid first DB index"))?;
+
+    let second =
+        integer(&args[2]).map_err(|_| CommandError::from("ERR invalid second DB index"))?;
+
+    Ok(Commands::SwapDb(first, second))
+}
+
+/// The optional `ASYNC`/`SYNC` argument of `FLUSHDB` and `FLUSHALL`.
+fn flush_mode(args: &[Bytes]) -> Result<bool, CommandError> {
+    match args.get(1).map(|arg| keyword(arg)).as_deref() {
+        None | Some("SYNC") if args.len() <= 2 => Ok(false),
+
+        Some("ASYNC") if args.len() == 2 => Ok(true),
+
+        _ => Err(CommandError::Syntax),
+    }
+}
+
+pub fn parse_flushdb(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::FlushDb(flush_mode(args)?))
+}
+
+pub fn parse_flushall(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::FlushAll(flush_mode(args)?))
+}
+
+pub fn parse_dbsize(_args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::DbSize)
+}
+
+pub fn parse_save(_args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Save)
+}
+
+pub fn parse_debug(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let subcommand = keyword(&args[1]);
+
+    let command = match (subcommand.as_str(), args.len()) {
+        ("SET-ACTIVE-EXPIRE", 3) => DebugCommand::SetActiveExpire(integer(&args[2])? != 0),
+
+        ("FREEZE-TIME", 2) => DebugCommand::FreezeTime(None),
+
+        ("FREEZE-TIME", 3) => {
+            let at = DateTime::from_timestamp_millis(integer(&args[2])?)
+                .ok_or(CommandError::NotInteger)?;
+
+            DebugCommand::FreezeTime(Some(at))
+        }
+
+        ("UNFREEZE-TIME", 2) => DebugCommand::UnfreezeTime,
+
+        ("ADVANCE-TIME", 3) => {
+            let milliseconds =
+                u64::try_from(integer(&args[2])?).map_err(|_| CommandError::NotInteger)?;
+
+            DebugCommand::AdvanceTime(milliseconds)
+        }
+
+        _ => {
+            return Err(CommandError::Custom(format!(
+                "ERR Unknown subcommand or wrong number of arguments for '{}'. Try DEBUG HELP.",
+                text(&args[1])
+            )))
+        }
+    };
+
+    Ok(Commands::Debug(command))
+}
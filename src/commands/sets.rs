@@ -0,0 +1,157 @@
+use bytes::Bytes;
+
+use super::{integer, keyword, random_count, CommandError, Commands, SetOperation};
+
+pub fn parse_sadd(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SAdd(args[1].clone(), args[2..].to_vec()))
+}
+
+pub fn parse_srem(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SRem(args[1].clone(), args[2..].to_vec()))
+}
+
+pub fn parse_sismember(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SIsMember(args[1].clone(), args[2].clone()))
+}
+
+pub fn parse_smismember(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SMIsMember(args[1].clone(), args[2..].to_vec()))
+}
+
+pub fn parse_smembers(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SMembers(args[1].clone()))
+}
+
+pub fn parse_scard(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SCard(args[1].clone()))
+}
+
+pub fn parse_spop(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let count = match args.len() {
+        2 => None,
+
+        3 => Some(
+            integer(&args[2])?
+                .try_into()
+                .map_err(|_| CommandError::from("ERR value is out of range, must be positive"))?,
+        ),
+
+        _ => return Err(CommandError::Syntax),
+    };
+
+    Ok(Commands::SPop(args[1].clone(), count))
+}
+
+pub fn parse_srandmember(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let count = match args.len() {
+        2 => None,
+
+        3 => Some(random_count(&args[2])?),
+
+        _ => return Err(CommandError::Syntax),
+    };
+
+    Ok(Commands::SRandMember(args[1].clone(), count))
+}
+
+pub fn parse_smove(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SMove(
+        args[1].clone(),
+        args[2].clone(),
+        args[3].clone(),
+    ))
+}
+
+pub fn parse_sinter(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SetAlgebra(
+        SetOperation::Inter,
+        None,
+        args[1..].to_vec(),
+    ))
+}
+
+pub fn parse_sunion(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SetAlgebra(
+        SetOperation::Union,
+        None,
+        args[1..].to_vec(),
+    ))
+}
+
+pub fn parse_sdiff(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SetAlgebra(
+        SetOperation::Diff,
+        None,
+        args[1..].to_vec(),
+    ))
+}
+
+pub fn parse_sinterstore(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SetAlgebra(
+        SetOperation::Inter,
+        Some(args[1].clone()),
+        args[2..].to_vec(),
+    ))
+}
+
+pub fn parse_sunionstore(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SetAlgebra(
+        SetOperation::Union,
+        Some(args[1].clone()),
+        args[2..].to_vec(),
+    ))
+}
+
+pub fn parse_sdiffstore(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::SetAlgebra(
+        SetOperation::Diff,
+        Some(args[1].clone()),
+        args[2..].to_vec(),
+    ))
+}
+
+/// `SINTERCARD numkeys key [key ...] [LIMIT limit]`
+pub fn parse_sintercard(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let numkeys = integer(&args[1])
+        .ok()
+        .and_then(|numkeys| usize::try_from(numkeys).ok())
+        .filter(|numkeys| *numkeys > 0)
+        .ok_or_else(|| CommandError::from("ERR numkeys should be greater than 0"))?;
+
+    if numkeys > args.len() - 2 {
+        return Err(CommandError::from(
+            "ERR Number of keys can't be greater than number of args",
+        ));
+    }
+
+    let mut limit = 0;
+
+    let mut i = numkeys + 2;
+
+    while i < args.len() {
+        match args.get(i + 1) {
+            Some(value) if keyword(&args[i]) == "LIMIT" => {
+                limit = integer(value)?
+                    .try_into()
+                    .map_err(|_| CommandError::from("ERR LIMIT can't be negative"))?;
+            }
+
+            _ => return Err(CommandError::Syntax),
+        }
+
+        i += 2;
+    }
+
+    Ok(Commands::SInterCard(args[2..numkeys + 2].to_vec(), limit))
+}
+
+/// Keys of `SINTERCARD`, which come after their count.
+pub fn sintercard_keys(args: &[Bytes]) -> Vec<usize> {
+    let numkeys = args
+        .get(1)
+        .and_then(|numkeys| integer(numkeys).ok())
+        .and_then(|numkeys| usize::try_from(numkeys).ok())
+        .unwrap_or(0);
+
+    (2..args.len()).take(numkeys).collect()
+}
@@ -0,0 +1,611 @@
+use bytes::Bytes;
+
+use crate::database::value::{LexBound, ScoreBound};
+
+use crate::resp::format_double;
+
+use super::lists::lmpop_keys;
+use super::sets::parse_sintercard;
+use super::{
+    float, integer, keyword, random_count, text, Aggregate, CommandError, Commands, SetOperation,
+    ZAddCommand, ZRange, ZRangeCommand, ZSetAlgebraCommand,
+};
+
+/// `ZADD key [NX | XX] [GT | LT] [CH] [INCR] score member [score member ...]`
+pub fn parse_zadd(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let mut command = ZAddCommand {
+        key: args[1].clone(),
+        pairs: Vec::new(),
+        nx: false,
+        xx: false,
+        gt: false,
+        lt: false,
+        changed: false,
+        incr: false,
+    };
+
+    let mut i = 2;
+
+    while i < args.len() {
+        match keyword(&args[i]).as_str() {
+            "NX" => command.nx = true,
+
+            "XX" => command.xx = true,
+
+            "GT" => command.gt = true,
+
+            "LT" => command.lt = true,
+
+            "CH" => command.changed = true,
+
+            "INCR" => command.incr = true,
+
+            _ => break,
+        }
+
+        i += 1;
+    }
+
+    let elements = &args[i..];
+
+    if elements.is_empty() || !elements.len().is_multiple_of(2) {
+        return Err(CommandError::Syntax);
+    }
+
+    if command.nx && command.xx {
+        return Err(CommandError::from(
+            "ERR XX and NX options at the same time are not compatible",
+        ));
+    }
+
+    if [command.nx, command.gt, command.lt]
+        .iter()
+        .filter(|option| **option)
+        .count()
+        > 1
+    {
+        return Err(CommandError::from(
+            "ERR GT, LT, and/or NX options at the same time are not compatible",
+        ));
+    }
+
+    if command.incr && elements.len() > 2 {
+        return Err(CommandError::from(
+            "ERR INCR option supports a single increment-element pair",
+        ));
+    }
+
+    command.pairs = elements
+        .chunks(2)
+        .map(|pair| Ok((float(&pair[0])?, pair[1].clone())))
+        .collect::<Result<_, CommandError>>()?;
+
+    Ok(Commands::ZAdd(command))
+}
+
+pub fn parse_zrem(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZRem(args[1].clone(), args[2..].to_vec()))
+}
+
+pub fn parse_zscore(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZScore(args[1].clone(), args[2].clone()))
+}
+
+pub fn parse_zmscore(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZMScore(args[1].clone(), args[2..].to_vec()))
+}
+
+/// `ZINCRBY key increment member`, which does what `ZADD key INCR increment member` does.
+pub fn parse_zincrby(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZAdd(ZAddCommand {
+        key: args[1].clone(),
+        pairs: vec![(float(&args[2])?, args[3].clone())],
+        nx: false,
+        xx: false,
+        gt: false,
+        lt: false,
+        changed: false,
+        incr: true,
+    }))
+}
+
+pub fn parse_zcard(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZCard(args[1].clone()))
+}
+
+pub fn parse_zcount(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZCount(
+        args[1].clone(),
+        ZRange::Score(score_bound(&args[2])?, score_bound(&args[3])?),
+    ))
+}
+
+pub fn parse_zlexcount(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZCount(
+        args[1].clone(),
+        ZRange::Lex(lex_bound(&args[2])?, lex_bound(&args[3])?),
+    ))
+}
+
+pub fn parse_zrank(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_rank(args, false)
+}
+
+pub fn parse_zrevrank(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_rank(args, true)
+}
+
+/// `ZRANK key member [WITHSCORE]` and `ZREVRANK`.
+fn parse_rank(args: &[Bytes], rev: bool) -> Result<Commands, CommandError> {
+    let with_score = match args.get(3) {
+        Some(option) if args.len() == 4 && keyword(option) == "WITHSCORE" => true,
+
+        Some(_) => return Err(CommandError::Syntax),
+
+        None => false,
+    };
+
+    Ok(Commands::ZRank(
+        args[1].clone(),
+        args[2].clone(),
+        rev,
+        with_score,
+    ))
+}
+
+/// What the start and stop arguments of a `ZRANGE` style command are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RangeBy {
+    Rank,
+
+    Score,
+
+    Lex,
+}
+
+pub fn parse_zrange(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZRange(parse_range(args, None)?))
+}
+
+pub fn parse_zrevrange(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZRange(parse_range(
+        args,
+        Some((RangeBy::Rank, true)),
+    )?))
+}
+
+pub fn parse_zrangebyscore(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZRange(parse_range(
+        args,
+        Some((RangeBy::Score, false)),
+    )?))
+}
+
+pub fn parse_zrevrangebyscore(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZRange(parse_range(
+        args,
+        Some((RangeBy::Score, true)),
+    )?))
+}
+
+pub fn parse_zrangebylex(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZRange(parse_range(
+        args,
+        Some((RangeBy::Lex, false)),
+    )?))
+}
+
+pub fn parse_zrevrangebylex(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZRange(parse_range(
+        args,
+        Some((RangeBy::Lex, true)),
+    )?))
+}
+
+/// `ZRANGE key start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES]`, and
+/// its older forms, for which `fixed` says what the range is made of and whether it is
+/// reversed. Reversed score and lex ranges take their maximum first.
+fn parse_range(
+    args: &[Bytes],
+    fixed: Option<(RangeBy, bool)>,
+) -> Result<ZRangeCommand, CommandError> {
+    let (mut by, mut rev) = fixed.unwrap_or((RangeBy::Rank, false));
+
+    let mut limit = None;
+
+    let mut with_scores = false;
+
+    let mut i = 4;
+
+    while i < args.len() {
+        match keyword(&args[i]).as_str() {
+            "WITHSCORES" => with_scores = true,
+
+            "LIMIT" if i + 2 < args.len() => {
+                limit = Some((integer(&args[i + 1])?, integer(&args[i + 2])?));
+
+                i += 2;
+            }
+
+            "BYSCORE" if fixed.is_none() => by = RangeBy::Score,
+
+            "BYLEX" if fixed.is_none() => by = RangeBy::Lex,
+
+            "REV" if fixed.is_none() => rev = true,
+
+            _ => return Err(CommandError::Syntax),
+        }
+
+        i += 1;
+    }
+
+    if limit.is_some() && by == RangeBy::Rank {
+        return Err(CommandError::from(
+            "ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX",
+        ));
+    }
+
+    if with_scores && by == RangeBy::Lex {
+        return Err(CommandError::from(
+            "ERR syntax error, WITHSCORES not supported in combination with BYLEX",
+        ));
+    }
+
+    let (start, stop) = if rev && by != RangeBy::Rank {
+        (&args[3], &args[2])
+    } else {
+        (&args[2], &args[3])
+    };
+
+    let range = match by {
+        RangeBy::Rank => ZRange::Rank(integer(start)?, integer(stop)?),
+
+        RangeBy::Score => ZRange::Score(score_bound(start)?, score_bound(stop)?),
+
+        RangeBy::Lex => ZRange::Lex(lex_bound(start)?, lex_bound(stop)?),
+    };
+
+    Ok(ZRangeCommand {
+        key: args[1].clone(),
+        range,
+        rev,
+        limit,
+        with_scores,
+    })
+}
+
+impl ZRangeCommand {
+    /// Arguments that follow the key in a `ZRANGE` selecting the same members.
+    pub fn range_arguments(&self) -> Vec<Bytes> {
+        let (mut args, by) = match &self.range {
+            ZRange::Rank(start, stop) => (
+                vec![start.to_string().into(), stop.to_string().into()],
+                None,
+            ),
+
+            ZRange::Score(min, max) => (vec![score_arg(min), score_arg(max)], Some("BYSCORE")),
+
+            ZRange::Lex(min, max) => (vec![lex_arg(min), lex_arg(max)], Some("BYLEX")),
+        };
+
+        if self.rev && by.is_some() {
+            args.swap(0, 1);
+        }
+
+        args.extend(by.map(Bytes::from));
+
+        if self.rev {
+            args.push("REV".into());
+        }
+
+        if let Some((offset, count)) = self.limit {
+            args.push("LIMIT".into());
+            args.push(offset.to_string().into());
+            args.push(count.to_string().into());
+        }
+
+        args
+    }
+}
+
+/// `ZRANGESTORE dst src min max [BYSCORE | BYLEX] [REV] [LIMIT offset count]`
+pub fn parse_zrangestore(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let command = parse_range(&args[1..], None)?;
+
+    if command.with_scores {
+        return Err(CommandError::Syntax);
+    }
+
+    Ok(Commands::ZRangeStore(args[1].clone(), command))
+}
+
+pub fn parse_zremrangebyrank(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZRemRange(
+        args[1].clone(),
+        ZRange::Rank(integer(&args[2])?, integer(&args[3])?),
+    ))
+}
+
+pub fn parse_zremrangebyscore(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZRemRange(
+        args[1].clone(),
+        ZRange::Score(score_bound(&args[2])?, score_bound(&args[3])?),
+    ))
+}
+
+pub fn parse_zremrangebylex(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::ZRemRange(
+        args[1].clone(),
+        ZRange::Lex(lex_bound(&args[2])?, lex_bound(&args[3])?),
+    ))
+}
+
+pub fn parse_zpopmin(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_pop(args, false)
+}
+
+pub fn parse_zpopmax(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_pop(args, true)
+}
+
+/// `ZPOPMIN key [count]` and `ZPOPMAX`.
+fn parse_pop(args: &[Bytes], max: bool) -> Result<Commands, CommandError> {
+    if args.len() > 3 {
+        return Err(CommandError::Syntax);
+    }
+
+    let count = args
+        .get(2)
+        .map(|count| {
+            usize::try_from(integer(count)?)
+                .map_err(|_| CommandError::from("ERR value is out of range, must be positive"))
+        })
+        .transpose()?;
+
+    Ok(Commands::ZPop(args[1].clone(), max, count))
+}
+
+pub fn parse_zrandmember(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let count = args.get(2).map(|count| random_count(count)).transpose()?;
+
+    let with_scores = match args.get(3) {
+        Some(option) if args.len() == 4 && keyword(option) == "WITHSCORES" => true,
+
+        Some(_) => return Err(CommandError::Syntax),
+
+        None => false,
+    };
+
+    Ok(Commands::ZRandMember(args[1].clone(), count, with_scores))
+}
+
+/// `ZMPOP numkeys key [key ...] MIN|MAX [COUNT count]`
+pub fn parse_zmpop(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let numkeys = integer(&args[1])
+        .ok()
+        .and_then(|numkeys| usize::try_from(numkeys).ok())
+        .filter(|numkeys| *numkeys > 0)
+        .ok_or_else(|| CommandError::from("ERR numkeys should be greater than 0"))?;
+
+    let end_idx = numkeys.saturating_add(2);
+
+    if end_idx >= args.len() {
+        return Err(CommandError::Syntax);
+    }
+
+    let max = match keyword(&args[end_idx]).as_str() {
+        "MIN" => false,
+
+        "MAX" => true,
+
+        _ => return Err(CommandError::Syntax),
+    };
+
+    let mut count = None;
+
+    let mut i = end_idx + 1;
+
+    while i < args.len() {
+        match args.get(i + 1) {
+            Some(value) if count.is_none() && keyword(&args[i]) == "COUNT" => {
+                count = Some(
+                    integer(value)
+                        .ok()
+                        .and_then(|count| usize::try_from(count).ok())
+                        .filter(|count| *count > 0)
+                        .ok_or_else(|| CommandError::from("ERR count should be greater than 0"))?,
+                );
+            }
+
+            _ => return Err(CommandError::Syntax),
+        }
+
+        i += 2;
+    }
+
+    Ok(Commands::ZMPop(
+        args[2..end_idx].to_vec(),
+        max,
+        count.unwrap_or(1),
+    ))
+}
+
+pub fn parse_zunion(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_algebra(args, SetOperation::Union, false)
+}
+
+pub fn parse_zinter(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_algebra(args, SetOperation::Inter, false)
+}
+
+pub fn parse_zdiff(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_algebra(args, SetOperation::Diff, false)
+}
+
+pub fn parse_zunionstore(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_algebra(args, SetOperation::Union, true)
+}
+
+pub fn parse_zinterstore(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_algebra(args, SetOperation::Inter, true)
+}
+
+pub fn parse_zdiffstore(args: &[Bytes]) -> Result<Commands, CommandError> {
+    parse_algebra(args, SetOperation::Diff, true)
+}
+
+/// The `ZUNION` family, whose `STORE` variants take a destination before the number of
+/// keys.
+fn parse_algebra(
+    args: &[Bytes],
+    operation: SetOperation,
+    store: bool,
+) -> Result<Commands, CommandError> {
+    let (destination, numkeys_idx) = match store {
+        true => (Some(args[1].clone()), 2),
+
+        false => (None, 1),
+    };
+
+    let numkeys = integer(&args[numkeys_idx])?;
+
+    if numkeys < 1 {
+        return Err(CommandError::Custom(format!(
+            "ERR at least 1 input key is needed for '{}' command",
+            text(&args[0]).to_lowercase()
+        )));
+    }
+
+    let first_key = numkeys_idx + 1;
+
+    let numkeys = usize::try_from(numkeys)
+        .ok()
+        .filter(|numkeys| *numkeys <= args.len() - first_key)
+        .ok_or(CommandError::Syntax)?;
+
+    let mut command = ZSetAlgebraCommand {
+        operation,
+        destination,
+        keys: args[first_key..first_key + numkeys].to_vec(),
+        weights: vec![1.0; numkeys],
+        aggregate: Aggregate::Sum,
+        with_scores: false,
+    };
+
+    let combines_scores = operation != SetOperation::Diff;
+
+    let mut i = first_key + numkeys;
+
+    while i < args.len() {
+        match keyword(&args[i]).as_str() {
+            "WEIGHTS" if combines_scores && args.len() - i > numkeys => {
+                for (weight, arg) in command.weights.iter_mut().zip(&args[i + 1..]) {
+                    *weight = float(arg)
+                        .map_err(|_| CommandError::from("ERR weight value is not a float"))?;
+                }
+
+                i += numkeys;
+            }
+
+            "AGGREGATE" if combines_scores && i + 1 < args.len() => {
+                command.aggregate = match keyword(&args[i + 1]).as_str() {
+                    "SUM" => Aggregate::Sum,
+
+                    "MIN" => Aggregate::Min,
+
+                    "MAX" => Aggregate::Max,
+
+                    _ => return Err(CommandError::Syntax),
+                };
+
+                i += 1;
+            }
+
+            "WITHSCORES" if !store => command.with_scores = true,
+
+            _ => return Err(CommandError::Syntax),
+        }
+
+        i += 1;
+    }
+
+    Ok(Commands::ZSetAlgebra(command))
+}
+
+/// `ZINTERCARD numkeys key [key ...] [LIMIT limit]`, which is parsed like `SINTERCARD`.
+pub fn parse_zintercard(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let Commands::SInterCard(keys, limit) = parse_sintercard(args)? else {
+        unreachable!("SINTERCARD parses into Commands::SInterCard");
+    };
+
+    Ok(Commands::ZInterCard(keys, limit))
+}
+
+/// Key positions of commands that give the number of their keys right before them, like
+/// `ZMPOP` or `ZUNION`, laid out like those of `LMPOP`.
+pub fn counted_keys(args: &[Bytes]) -> Vec<usize> {
+    lmpop_keys(args)
+}
+
+/// Key positions of `ZUNIONSTORE` and the other `STORE` variants: the destination, then the
+/// counted keys.
+pub fn store_keys(args: &[Bytes]) -> Vec<usize> {
+    let mut keys = vec![1];
+
+    keys.extend(lmpop_keys(&args[1..]).into_iter().map(|index| index + 1));
+
+    keys
+}
+
+/// A score range end: a float, possibly infinite, after an optional `(` for an exclusive one.
+fn score_bound(arg: &[u8]) -> Result<ScoreBound, CommandError> {
+    let (value, exclusive) = match arg.strip_prefix(b"(") {
+        Some(value) => (value, true),
+
+        None => (arg, false),
+    };
+
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|text| text.parse::<f64>().ok())
+        .filter(|value| !value.is_nan())
+        .map(|value| ScoreBound { value, exclusive })
+        .ok_or_else(|| CommandError::from("ERR min or max is not a float"))
+}
+
+/// A score range end as `score_bound` parses it.
+fn score_arg(bound: &ScoreBound) -> Bytes {
+    let prefix = if bound.exclusive { "(" } else { "" };
+
+    Bytes::from(format!("{prefix}{}", format_double(bound.value)))
+}
+
+/// A lex range end as `lex_bound` parses it.
+fn lex_arg(bound: &LexBound) -> Bytes {
+    match bound {
+        LexBound::Min => Bytes::from_static(b"-"),
+
+        LexBound::Max => Bytes::from_static(b"+"),
+
+        LexBound::Inclusive(member) => [b"[".as_slice(), member].concat().into(),
+
+        LexBound::Exclusive(member) => [b"(".as_slice(), member].concat().into(),
+    }
+}
+
+/// A lex range end: `-`, `+`, or a member after `[` or `(`.
+fn lex_bound(arg: &Bytes) -> Result<LexBound, CommandError> {
+    match arg.first() {
+        Some(b'-') if arg.len() == 1 => Ok(LexBound::Min),
+
+        Some(b'+') if arg.len() == 1 => Ok(LexBound::Max),
+
+        Some(b'[') => Ok(LexBound::Inclusive(arg.slice(1..))),
+
+        Some(b'(') => Ok(LexBound::Exclusive(arg.slice(1..))),
+
+        _ => Err(CommandError::from(
+            "ERR min or max not valid string range item",
+        )),
+    }
+}
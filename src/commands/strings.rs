@@ -0,0 +1,83 @@
+use bytes::Bytes;
+
+use super::{expiration, keyword, CommandError, Commands, SetCommand, SetCondition, TtlUpdate};
+
+pub fn parse_get(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::Get(args[1].clone()))
+}
+
+/// `SET key value [NX | XX] [GET] [EX s | PX ms | EXAT ts | PXAT ms-ts | KEEPTTL]`, with the
+/// options in any order.
+pub fn parse_set(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let mut command = SetCommand {
+        key: args[1].clone(),
+        value: args[2].clone(),
+        condition: None,
+        get: false,
+        keep_ttl: false,
+        expiration: None,
+    };
+
+    let mut i = 3;
+
+    while i < args.len() {
+        let option = keyword(&args[i]);
+
+        match option.as_str() {
+            "NX" | "XX" if command.condition.is_none() => {
+                command.condition = Some(if option == "NX" {
+                    SetCondition::Nx
+                } else {
+                    SetCondition::Xx
+                });
+            }
+
+            "GET" if !command.get => command.get = true,
+
+            "KEEPTTL" if !command.keep_ttl && command.expiration.is_none() => {
+                command.keep_ttl = true
+            }
+
+            "EX" | "PX" | "EXAT" | "PXAT"
+                if i + 1 < args.len() && !command.keep_ttl && command.expiration.is_none() =>
+            {
+                command.expiration = Some(expiration(&option, &args[i + 1], "set")?);
+
+                i += 1;
+            }
+
+            _ => return Err(CommandError::Syntax),
+        }
+
+        i += 1;
+    }
+
+    Ok(Commands::Set(command))
+}
+
+/// `GETEX key [EX s | PX ms | EXAT ts | PXAT ms-ts | PERSIST]`
+pub fn parse_getex(args: &[Bytes]) -> Result<Commands, CommandError> {
+    let update = match args.len() {
+        2 => None,
+
+        3 if keyword(&args[2]) == "PERSIST" => Some(TtlUpdate::Persist),
+
+        4 => {
+            let unit = keyword(&args[2]);
+
+            Some(TtlUpdate::Expire(expiration(&unit, &args[3], "getex")?))
+        }
+
+        _ => return Err(CommandError::Syntax),
+    };
+
+    Ok(Commands::GetEx(args[1].clone(), update))
+}
+
+pub fn parse_getdel(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::GetDel(args[1].clone()))
+}
+
+pub fn parse_getset(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Ok(Commands::GetSet(args[1].clone(), args[2].clone()))
+}
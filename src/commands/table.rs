@@ -0,0 +1,2174 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use bytes::Bytes;
+
+use crate::glob;
+use crate::resp::RespDataTypes;
+
+use super::{
+    blocking, connection, hashes, keys, lists, scan, server, sets, sorted_sets, strings, text,
+};
+use super::{CommandError, CommandIntrospection, Commands, ListFilter};
+
+use CommandFlag::*;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandFlag {
+    Write,
+    ReadOnly,
+    DenyOom,
+    Admin,
+    PubSub,
+    NoScript,
+    Loading,
+    Stale,
+    Fast,
+    Blocking,
+}
+
+impl CommandFlag {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Write => "write",
+            ReadOnly => "readonly",
+            DenyOom => "denyoom",
+            Admin => "admin",
+            PubSub => "pubsub",
+            NoScript => "noscript",
+            Loading => "loading",
+            Stale => "stale",
+            Fast => "fast",
+            Blocking => "blocking",
+        }
+    }
+}
+
+/// Where the keys sit in a command line: the first and last key argument (a negative
+/// `last` counts from the end) and the step between keys. All zero for keyless commands.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyRange {
+    pub first: i64,
+    pub last: i64,
+    pub step: i64,
+}
+
+impl KeyRange {
+    pub const NONE: KeyRange = KeyRange::new(0, 0, 0);
+
+    pub const FIRST: KeyRange = KeyRange::new(1, 1, 1);
+
+    pub const fn new(first: i64, last: i64, step: i64) -> Self {
+        Self { first, last, step }
+    }
+}
+
+pub type ParseFn = fn(&[Bytes]) -> Result<Commands, CommandError>;
+
+/// Returns the argument indexes holding keys, for commands whose keys can't be described
+/// by a [`KeyRange`] (for example when the number of keys is itself an argument).
+pub type MovableKeysFn = fn(&[Bytes]) -> Vec<usize>;
+
+#[derive(Debug)]
+pub struct CommandSpec {
+    /// Lower-case name; subcommands are named `container|subcommand`.
+    pub name: &'static str,
+
+    /// Number of arguments including the command name, or its negated minimum.
+    pub arity: i64,
+
+    pub flags: &'static [CommandFlag],
+
+    pub keys: KeyRange,
+
+    pub movable_keys: Option<MovableKeysFn>,
+
+    /// ACL categories without the leading `@`.
+    pub acl_categories: &'static [&'static str],
+
+    pub group: &'static str,
+
+    pub since: &'static str,
+
+    pub summary: &'static str,
+
+    pub subcommands: &'static [CommandSpec],
+
+    pub parse: ParseFn,
+}
+
+impl CommandSpec {
+    const DEFAULT: CommandSpec = CommandSpec {
+        name: "",
+        arity: -1,
+        flags: &[],
+        keys: KeyRange::NONE,
+        movable_keys: None,
+        acl_categories: &[],
+        group: "generic",
+        since: "1.0.0",
+        summary: "",
+        subcommands: &[],
+        parse: parse_container,
+    };
+
+    fn check_arity(&self, argc: usize) -> Result<(), CommandError> {
+        let argc = argc as i64;
+
+        if (self.arity > 0 && argc != self.arity) || argc < -self.arity {
+            return Err(CommandError::WrongArity(self.name.to_string()));
+        }
+
+        Ok(())
+    }
+
+    pub fn has_flag(&self, flag: CommandFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// Indexes of the key arguments in `args`, which includes the command name.
+    pub fn key_positions(&self, args: &[Bytes]) -> Vec<usize> {
+        if let Some(movable_keys) = self.movable_keys {
+            return movable_keys(args);
+        }
+
+        if self.keys.first == 0 {
+            return Vec::new();
+        }
+
+        let last = if self.keys.last < 0 {
+            args.len() as i64 + self.keys.last
+        } else {
+            self.keys.last
+        };
+
+        (self.keys.first..=last.min(args.len() as i64 - 1))
+            .step_by(self.keys.step.max(1) as usize)
+            .map(|idx| idx as usize)
+            .collect()
+    }
+
+    /// Entry of `COMMAND INFO`.
+    pub fn info(&self) -> RespDataTypes {
+        let mut flags: Vec<RespDataTypes> = self
+            .flags
+            .iter()
+            .map(|flag| RespDataTypes::SimpleString(flag.name().to_string()))
+            .collect();
+
+        if self.movable_keys.is_some() {
+            flags.push(RespDataTypes::SimpleString("movablekeys".to_string()));
+        }
+
+        let acl_categories = self
+            .acl_categories
+            .iter()
+            .map(|category| RespDataTypes::SimpleString(format!("@{category}")))
+            .collect();
+
+        RespDataTypes::Array(vec![
+            RespDataTypes::BulkString(self.name.into()),
+            RespDataTypes::Integer(self.arity),
+            RespDataTypes::Set(flags),
+            RespDataTypes::Integer(self.keys.first),
+            RespDataTypes::Integer(self.keys.last),
+            RespDataTypes::Integer(self.keys.step),
+            RespDataTypes::Set(acl_categories),
+            RespDataTypes::Array(Vec::new()),
+            RespDataTypes::Array(self.key_specs()),
+            RespDataTypes::Array(self.subcommands.iter().map(|sub| sub.info()).collect()),
+        ])
+    }
+
+    fn key_specs(&self) -> Vec<RespDataTypes> {
+        if self.keys.first == 0 {
+            return Vec::new();
+        }
+
+        let access = if self.has_flag(Write) { "RW" } else { "RO" };
+
+        let last_key = if self.keys.last < 0 {
+            self.keys.last
+        } else {
+            self.keys.last - self.keys.first
+        };
+
+        vec![map(vec![
+            (
+                "flags",
+                RespDataTypes::Array(vec![RespDataTypes::SimpleString(access.to_string())]),
+            ),
+            (
+                "begin_search",
+                map(vec![
+                    ("type", RespDataTypes::BulkString("index".into())),
+                    (
+                        "spec",
+                        map(vec![("index", RespDataTypes::Integer(self.keys.first))]),
+                    ),
+                ]),
+            ),
+            (
+                "find_keys",
+                map(vec![
+                    ("type", RespDataTypes::BulkString("range".into())),
+                    (
+                        "spec",
+                        map(vec![
+                            ("lastkey", RespDataTypes::Integer(last_key)),
+                            ("keystep", RespDataTypes::Integer(self.keys.step)),
+                            ("limit", RespDataTypes::Integer(0)),
+                        ]),
+                    ),
+                ]),
+            ),
+        ])]
+    }
+
+    /// Entry of `COMMAND DOCS`.
+    pub fn docs(&self) -> RespDataTypes {
+        let mut fields = vec![
+            ("summary", RespDataTypes::BulkString(self.summary.into())),
+            ("since", RespDataTypes::BulkString(self.since.into())),
+            ("group", RespDataTypes::BulkString(self.group.into())),
+        ];
+
+        if !self.subcommands.is_empty() {
+            fields.push((
+                "subcommands",
+                RespDataTypes::Map(
+                    self.subcommands
+                        .iter()
+                        .map(|sub| (RespDataTypes::BulkString(sub.name.into()), sub.docs()))
+                        .collect(),
+                ),
+            ));
+        }
+
+        map(fields)
+    }
+}
+
+fn map(fields: Vec<(&'static str, RespDataTypes)>) -> RespDataTypes {
+    RespDataTypes::Map(
+        fields
+            .into_iter()
+            .map(|(key, value)| (RespDataTypes::BulkString(key.into()), value))
+            .collect(),
+    )
+}
+
+/// Container commands are only reached without a subcommand when their arity allows it.
+fn parse_container(args: &[Bytes]) -> Result<Commands, CommandError> {
+    Err(CommandError::WrongArity(text(&args[0]).to_lowercase()))
+}
+
+pub static COMMAND_TABLE: &[CommandSpec] = &[
+    CommandSpec {
+        name: "ping",
+        arity: -1,
+        flags: &[Fast],
+        acl_categories: &["fast", "connection"],
+        group: "connection",
+        summary: "Returns the server's liveliness response.",
+        parse: server::parse_ping,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "echo",
+        arity: 2,
+        flags: &[Fast],
+        acl_categories: &["fast", "connection"],
+        group: "connection",
+        summary: "Returns the given string.",
+        parse: server::parse_echo,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hello",
+        arity: -1,
+        flags: &[NoScript, Loading, Stale, Fast],
+        acl_categories: &["fast", "connection"],
+        group: "connection",
+        since: "6.0.0",
+        summary: "Handshakes with the Redis server.",
+        parse: server::parse_hello,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "client",
+        arity: -2,
+        acl_categories: &["slow"],
+        group: "connection",
+        since: "2.4.0",
+        summary: "A container for client connection commands.",
+        subcommands: &[
+            CommandSpec {
+                name: "client|getname",
+                arity: 2,
+                flags: &[NoScript, Loading, Stale],
+                acl_categories: &["slow", "connection"],
+                group: "connection",
+                since: "2.6.9",
+                summary: "Returns the name of the connection.",
+                parse: connection::parse_client_getname,
+                ..CommandSpec::DEFAULT
+            },
+            CommandSpec {
+                name: "client|id",
+                arity: 2,
+                flags: &[NoScript, Loading, Stale],
+                acl_categories: &["slow", "connection"],
+                group: "connection",
+                since: "5.0.0",
+                summary: "Returns the unique client ID of the connection.",
+                parse: connection::parse_client_id,
+                ..CommandSpec::DEFAULT
+            },
+            CommandSpec {
+                name: "client|info",
+                arity: 2,
+                flags: &[NoScript, Loading, Stale],
+                acl_categories: &["slow", "connection"],
+                group: "connection",
+                since: "6.2.0",
+                summary: "Returns information about the connection.",
+                parse: connection::parse_client_info,
+                ..CommandSpec::DEFAULT
+            },
+            CommandSpec {
+                name: "client|kill",
+                arity: -3,
+                flags: &[Admin, NoScript, Loading, Stale],
+                acl_categories: &["admin", "slow", "dangerous", "connection"],
+                group: "connection",
+                since: "2.4.0",
+                summary: "Terminates open connections.",
+                parse: connection::parse_client_kill,
+                ..CommandSpec::DEFAULT
+            },
+            CommandSpec {
+                name: "client|list",
+                arity: -2,
+                flags: &[Admin, NoScript, Loading, Stale],
+                acl_categories: &["admin", "slow", "dangerous", "connection"],
+                group: "connection",
+                since: "2.4.0",
+                summary: "Lists open connections.",
+                parse: connection::parse_client_list,
+                ..CommandSpec::DEFAULT
+            },
+            CommandSpec {
+                name: "client|reply",
+                arity: 3,
+                flags: &[NoScript, Loading, Stale],
+                acl_categories: &["slow", "connection"],
+                group: "connection",
+                since: "3.2.0",
+                summary: "Instructs the server whether to reply to commands.",
+                parse: connection::parse_client_reply,
+                ..CommandSpec::DEFAULT
+            },
+            CommandSpec {
+                name: "client|setname",
+                arity: 3,
+                flags: &[NoScript, Loading, Stale],
+                acl_categories: &["slow", "connection"],
+                group: "connection",
+                since: "2.6.9",
+                summary: "Sets the connection name.",
+                parse: connection::parse_client_setname,
+                ..CommandSpec::DEFAULT
+            },
+            CommandSpec {
+                name: "client|unblock",
+                arity: -3,
+                flags: &[Admin, NoScript, Loading, Stale],
+                acl_categories: &["admin", "slow", "dangerous", "connection"],
+                group: "connection",
+                since: "5.0.0",
+                summary: "Unblocks a client blocked by a blocking command from a different connection.",
+                parse: connection::parse_client_unblock,
+                ..CommandSpec::DEFAULT
+            },
+        ],
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "get",
+        arity: 2,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "string", "fast"],
+        group: "string",
+        summary: "Returns the string value of a key.",
+        parse: strings::parse_get,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "set",
+        arity: -3,
+        flags: &[Write, DenyOom],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "string", "slow"],
+        group: "string",
+        summary: "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist.",
+        parse: strings::parse_set,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "getex",
+        arity: -2,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "string", "fast"],
+        group: "string",
+        since: "6.2.0",
+        summary: "Returns the string value of a key after setting its expiration time.",
+        parse: strings::parse_getex,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "getdel",
+        arity: 2,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "string", "fast"],
+        group: "string",
+        since: "6.2.0",
+        summary: "Returns the string value of a key after deleting the key.",
+        parse: strings::parse_getdel,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "getset",
+        arity: 3,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "string", "fast"],
+        group: "string",
+        summary: "Returns the previous string value of a key after setting it to a new value.",
+        parse: strings::parse_getset,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "keys",
+        arity: 2,
+        flags: &[ReadOnly],
+        acl_categories: &["keyspace", "read", "slow", "dangerous"],
+        summary: "Returns all key names that match a pattern.",
+        parse: keys::parse_keys,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "lpush",
+        arity: -3,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "list", "fast"],
+        group: "list",
+        summary: "Prepends one or more elements to a list. Creates the key if it doesn't exist.",
+        parse: lists::parse_lpush,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "rpush",
+        arity: -3,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "list", "fast"],
+        group: "list",
+        summary: "Appends one or more elements to a list. Creates the key if it doesn't exist.",
+        parse: lists::parse_rpush,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "lpushx",
+        arity: -3,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "list", "fast"],
+        group: "list",
+        since: "2.2.0",
+        summary: "Prepends one or more elements to a list only when the list exists.",
+        parse: lists::parse_lpushx,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "rpushx",
+        arity: -3,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "list", "fast"],
+        group: "list",
+        since: "2.2.0",
+        summary: "Appends an element to a list only when the list exists.",
+        parse: lists::parse_rpushx,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "lpop",
+        arity: -2,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "list", "fast"],
+        group: "list",
+        summary: "Returns the first elements in a list after removing it. Deletes the list if the last element was popped.",
+        parse: lists::parse_lpop,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "rpop",
+        arity: -2,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "list", "fast"],
+        group: "list",
+        summary: "Returns and removes the last elements of a list. Deletes the list if the last element was popped.",
+        parse: lists::parse_rpop,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "lrange",
+        arity: 4,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "list", "slow"],
+        group: "list",
+        summary: "Returns a range of elements from a list.",
+        parse: lists::parse_lrange,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "lindex",
+        arity: 3,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "list", "slow"],
+        group: "list",
+        summary: "Returns an element from a list by its index.",
+        parse: lists::parse_lindex,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "lset",
+        arity: 4,
+        flags: &[Write, DenyOom],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "list", "slow"],
+        group: "list",
+        summary: "Sets the value of an element in a list by its index.",
+        parse: lists::parse_lset,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "llen",
+        arity: 2,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "list", "fast"],
+        group: "list",
+        summary: "Returns the length of a list.",
+        parse: lists::parse_llen,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "lrem",
+        arity: 4,
+        flags: &[Write],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "list", "slow"],
+        group: "list",
+        summary: "Removes elements from a list. Deletes the list if the last element was removed.",
+        parse: lists::parse_lrem,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "ltrim",
+        arity: 4,
+        flags: &[Write],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "list", "slow"],
+        group: "list",
+        summary: "Removes elements from both ends a list. Deletes the list if all elements were trimmed.",
+        parse: lists::parse_ltrim,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "linsert",
+        arity: 5,
+        flags: &[Write, DenyOom],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "list", "slow"],
+        group: "list",
+        since: "2.2.0",
+        summary: "Inserts an element before or after another element in a list.",
+        parse: lists::parse_linsert,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "lpos",
+        arity: -3,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "list", "slow"],
+        group: "list",
+        since: "6.0.6",
+        summary: "Returns the index of matching elements in a list.",
+        parse: lists::parse_lpos,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "lmove",
+        arity: 5,
+        flags: &[Write, DenyOom],
+        keys: KeyRange::new(1, 2, 1),
+        acl_categories: &["write", "list", "slow"],
+        group: "list",
+        since: "6.2.0",
+        summary: "Returns an element after popping it from one list and pushing it to another. Deletes the list if the last element was moved.",
+        parse: lists::parse_lmove,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "rpoplpush",
+        arity: 3,
+        flags: &[Write, DenyOom],
+        keys: KeyRange::new(1, 2, 1),
+        acl_categories: &["write", "list", "slow"],
+        group: "list",
+        since: "1.2.0",
+        summary: "Returns the last element of a list after removing and pushing it to another list. Deletes the list if the last element was popped.",
+        parse: lists::parse_rpoplpush,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "lmpop",
+        arity: -4,
+        flags: &[Write],
+        movable_keys: Some(lists::lmpop_keys),
+        acl_categories: &["write", "list", "slow"],
+        group: "list",
+        since: "7.0.0",
+        summary: "Returns multiple elements from a list after removing them. Deletes the list if the last element was popped.",
+        parse: lists::parse_lmpop,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "blpop",
+        arity: -3,
+        flags: &[Write, NoScript, Blocking],
+        keys: KeyRange::new(1, -2, 1),
+        acl_categories: &["write", "list", "slow", "blocking"],
+        group: "list",
+        since: "2.0.0",
+        summary: "Removes and returns the first element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+        parse: blocking::parse_blpop,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "brpop",
+        arity: -3,
+        flags: &[Write, NoScript, Blocking],
+        keys: KeyRange::new(1, -2, 1),
+        acl_categories: &["write", "list", "slow", "blocking"],
+        group: "list",
+        since: "2.0.0",
+        summary: "Removes and returns the last element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+        parse: blocking::parse_brpop,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "blmove",
+        arity: 6,
+        flags: &[Write, DenyOom, NoScript, Blocking],
+        keys: KeyRange::new(1, 2, 1),
+        acl_categories: &["write", "list", "slow", "blocking"],
+        group: "list",
+        since: "6.2.0",
+        summary: "Pops an element from a list, pushes it to another list and returns it. Blocks until an element is available otherwise. Deletes the list if the last element was moved.",
+        parse: blocking::parse_blmove,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "brpoplpush",
+        arity: 4,
+        flags: &[Write, DenyOom, NoScript, Blocking],
+        keys: KeyRange::new(1, 2, 1),
+        acl_categories: &["write", "list", "slow", "blocking"],
+        group: "list",
+        since: "2.2.0",
+        summary: "Pops an element from a list, pushes it to another list and returns it. Block until an element is available otherwise. Deletes the list if the last element was popped.",
+        parse: blocking::parse_brpoplpush,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "blmpop",
+        arity: -5,
+        flags: &[Write, Blocking],
+        movable_keys: Some(blocking::blmpop_keys),
+        acl_categories: &["write", "list", "slow", "blocking"],
+        group: "list",
+        since: "7.0.0",
+        summary: "Pops the first element from one of multiple lists. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+        parse: blocking::parse_blmpop,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "bzpopmin",
+        arity: -3,
+        flags: &[Write, NoScript, Fast, Blocking],
+        keys: KeyRange::new(1, -2, 1),
+        acl_categories: &["write", "sortedset", "fast", "blocking"],
+        group: "sorted_set",
+        since: "5.0.0",
+        summary: "Removes and returns the member with the lowest score from one or more sorted sets. Blocks until a member is available otherwise. Deletes the sorted set if the last element was popped.",
+        parse: blocking::parse_bzpopmin,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "bzpopmax",
+        arity: -3,
+        flags: &[Write, NoScript, Fast, Blocking],
+        keys: KeyRange::new(1, -2, 1),
+        acl_categories: &["write", "sortedset", "fast", "blocking"],
+        group: "sorted_set",
+        since: "5.0.0",
+        summary: "Removes and returns the member with the highest score from one or more sorted sets. Blocks until a member available otherwise. Deletes the sorted set if the last element was popped.",
+        parse: blocking::parse_bzpopmax,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hset",
+        arity: -4,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "hash", "fast"],
+        group: "hash",
+        since: "2.0.0",
+        summary: "Creates or modifies the value of a field in a hash.",
+        parse: hashes::parse_hset,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hsetnx",
+        arity: 4,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "hash", "fast"],
+        group: "hash",
+        since: "2.0.0",
+        summary: "Sets the value of a field in a hash only when the field doesn't exist.",
+        parse: hashes::parse_hsetnx,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hget",
+        arity: 3,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "fast"],
+        group: "hash",
+        since: "2.0.0",
+        summary: "Returns the value of a field in a hash.",
+        parse: hashes::parse_hget,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hmget",
+        arity: -3,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "fast"],
+        group: "hash",
+        since: "2.0.0",
+        summary: "Returns the values of all fields in a hash.",
+        parse: hashes::parse_hmget,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hdel",
+        arity: -3,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "hash", "fast"],
+        group: "hash",
+        since: "2.0.0",
+        summary: "Deletes one or more fields and their values from a hash. Deletes the hash if no fields remain.",
+        parse: hashes::parse_hdel,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hexists",
+        arity: 3,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "fast"],
+        group: "hash",
+        since: "2.0.0",
+        summary: "Determines whether a field exists in a hash.",
+        parse: hashes::parse_hexists,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hlen",
+        arity: 2,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "fast"],
+        group: "hash",
+        since: "2.0.0",
+        summary: "Returns the number of fields in a hash.",
+        parse: hashes::parse_hlen,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hstrlen",
+        arity: 3,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "fast"],
+        group: "hash",
+        since: "3.2.0",
+        summary: "Returns the length of the value of a field.",
+        parse: hashes::parse_hstrlen,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hkeys",
+        arity: 2,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "slow"],
+        group: "hash",
+        since: "2.0.0",
+        summary: "Returns all fields in a hash.",
+        parse: hashes::parse_hkeys,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hvals",
+        arity: 2,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "slow"],
+        group: "hash",
+        since: "2.0.0",
+        summary: "Returns all values in a hash.",
+        parse: hashes::parse_hvals,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hgetall",
+        arity: 2,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "slow"],
+        group: "hash",
+        since: "2.0.0",
+        summary: "Returns all fields and values in a hash.",
+        parse: hashes::parse_hgetall,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hincrby",
+        arity: 4,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "hash", "fast"],
+        group: "hash",
+        since: "2.0.0",
+        summary: "Increments the integer value of a field in a hash by a number. Uses 0 as initial value if the field doesn't exist.",
+        parse: hashes::parse_hincrby,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hincrbyfloat",
+        arity: 4,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "hash", "fast"],
+        group: "hash",
+        since: "2.6.0",
+        summary: "Increments the floating point value of a field by a number. Uses 0 as initial value if the field doesn't exist.",
+        parse: hashes::parse_hincrbyfloat,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hrandfield",
+        arity: -2,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "slow"],
+        group: "hash",
+        since: "6.2.0",
+        summary: "Returns one or more random fields from a hash.",
+        parse: hashes::parse_hrandfield,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hexpire",
+        arity: -6,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "hash", "fast"],
+        group: "hash",
+        since: "7.4.0",
+        summary: "Set expiry for hash field using relative time to expire (seconds).",
+        parse: hashes::parse_hexpire,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hpexpire",
+        arity: -6,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "hash", "fast"],
+        group: "hash",
+        since: "7.4.0",
+        summary: "Set expiry for hash field using relative time to expire (milliseconds).",
+        parse: hashes::parse_hpexpire,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hexpireat",
+        arity: -6,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "hash", "fast"],
+        group: "hash",
+        since: "7.4.0",
+        summary: "Set expiry for hash field using an absolute Unix timestamp (seconds).",
+        parse: hashes::parse_hexpireat,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hpexpireat",
+        arity: -6,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "hash", "fast"],
+        group: "hash",
+        since: "7.4.0",
+        summary: "Set expiry for hash field using an absolute Unix timestamp (milliseconds).",
+        parse: hashes::parse_hpexpireat,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "httl",
+        arity: -5,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "fast"],
+        group: "hash",
+        since: "7.4.0",
+        summary: "Returns the TTL in seconds of a hash field.",
+        parse: hashes::parse_httl,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hpttl",
+        arity: -5,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "fast"],
+        group: "hash",
+        since: "7.4.0",
+        summary: "Returns the TTL in milliseconds of a hash field.",
+        parse: hashes::parse_hpttl,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hexpiretime",
+        arity: -5,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "fast"],
+        group: "hash",
+        since: "7.4.0",
+        summary: "Returns the expiration time of a hash field as a Unix timestamp, in seconds.",
+        parse: hashes::parse_hexpiretime,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hpexpiretime",
+        arity: -5,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "fast"],
+        group: "hash",
+        since: "7.4.0",
+        summary: "Returns the expiration time of a hash field as a Unix timestamp, in msec.",
+        parse: hashes::parse_hpexpiretime,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hpersist",
+        arity: -5,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "hash", "fast"],
+        group: "hash",
+        since: "7.4.0",
+        summary: "Removes the expiration time for each specified field.",
+        parse: hashes::parse_hpersist,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hgetex",
+        arity: -5,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "hash", "fast"],
+        group: "hash",
+        since: "8.0.0",
+        summary: "Get the value of one or more fields of a given hash key, and optionally set their expiration.",
+        parse: hashes::parse_hgetex,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hsetex",
+        arity: -6,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "hash", "fast"],
+        group: "hash",
+        since: "8.0.0",
+        summary: "Set the value of one or more fields of a given hash key, and optionally set their expiration.",
+        parse: hashes::parse_hsetex,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "sadd",
+        arity: -3,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "set", "fast"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Adds one or more members to a set. Creates the key if it doesn't exist.",
+        parse: sets::parse_sadd,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "scard",
+        arity: 2,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "set", "fast"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Returns the number of members in a set.",
+        parse: sets::parse_scard,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "sdiff",
+        arity: -2,
+        flags: &[ReadOnly],
+        keys: KeyRange::new(1, -1, 1),
+        acl_categories: &["read", "set", "slow"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Returns the difference of multiple sets.",
+        parse: sets::parse_sdiff,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "sdiffstore",
+        arity: -3,
+        flags: &[Write, DenyOom],
+        keys: KeyRange::new(1, -1, 1),
+        acl_categories: &["write", "set", "slow"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Stores the difference of multiple sets in a key.",
+        parse: sets::parse_sdiffstore,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "sinter",
+        arity: -2,
+        flags: &[ReadOnly],
+        keys: KeyRange::new(1, -1, 1),
+        acl_categories: &["read", "set", "slow"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Returns the intersect of multiple sets.",
+        parse: sets::parse_sinter,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "sintercard",
+        arity: -3,
+        flags: &[ReadOnly],
+        movable_keys: Some(sets::sintercard_keys),
+        acl_categories: &["read", "set", "slow"],
+        group: "set",
+        since: "7.0.0",
+        summary: "Returns the number of members of the intersect of multiple sets.",
+        parse: sets::parse_sintercard,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "sinterstore",
+        arity: -3,
+        flags: &[Write, DenyOom],
+        keys: KeyRange::new(1, -1, 1),
+        acl_categories: &["write", "set", "slow"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Stores the intersect of multiple sets in a key.",
+        parse: sets::parse_sinterstore,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "sismember",
+        arity: 3,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "set", "fast"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Determines whether a member belongs to a set.",
+        parse: sets::parse_sismember,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "smembers",
+        arity: 2,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "set", "slow"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Returns all members of a set.",
+        parse: sets::parse_smembers,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "smismember",
+        arity: -3,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "set", "fast"],
+        group: "set",
+        since: "6.2.0",
+        summary: "Determines whether multiple members belong to a set.",
+        parse: sets::parse_smismember,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "smove",
+        arity: 4,
+        flags: &[Write, Fast],
+        keys: KeyRange::new(1, 2, 1),
+        acl_categories: &["write", "set", "fast"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Moves a member from one set to another.",
+        parse: sets::parse_smove,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "spop",
+        arity: -2,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "set", "fast"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Returns one or more random members from a set after removing them. Deletes the set if the last member was popped.",
+        parse: sets::parse_spop,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "srandmember",
+        arity: -2,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "set", "slow"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Get one or multiple random members from a set.",
+        parse: sets::parse_srandmember,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "srem",
+        arity: -3,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "set", "fast"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Removes one or more members from a set. Deletes the set if the last member was removed.",
+        parse: sets::parse_srem,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "sunion",
+        arity: -2,
+        flags: &[ReadOnly],
+        keys: KeyRange::new(1, -1, 1),
+        acl_categories: &["read", "set", "slow"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Returns the union of multiple sets.",
+        parse: sets::parse_sunion,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "sunionstore",
+        arity: -3,
+        flags: &[Write, DenyOom],
+        keys: KeyRange::new(1, -1, 1),
+        acl_categories: &["write", "set", "slow"],
+        group: "set",
+        since: "1.0.0",
+        summary: "Stores the union of multiple sets in a key.",
+        parse: sets::parse_sunionstore,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zadd",
+        arity: -4,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "sortedset", "fast"],
+        group: "sorted_set",
+        since: "1.2.0",
+        summary: "Adds one or more members to a sorted set, or updates their scores. Creates the key if it doesn't exist.",
+        parse: sorted_sets::parse_zadd,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zcard",
+        arity: 2,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "fast"],
+        group: "sorted_set",
+        since: "1.2.0",
+        summary: "Returns the number of members in a sorted set.",
+        parse: sorted_sets::parse_zcard,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zcount",
+        arity: 4,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "fast"],
+        group: "sorted_set",
+        since: "2.0.0",
+        summary: "Returns the count of members in a sorted set that have scores within a range.",
+        parse: sorted_sets::parse_zcount,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zdiff",
+        arity: -3,
+        flags: &[ReadOnly],
+        movable_keys: Some(sorted_sets::counted_keys),
+        acl_categories: &["read", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "6.2.0",
+        summary: "Returns the difference between multiple sorted sets.",
+        parse: sorted_sets::parse_zdiff,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zdiffstore",
+        arity: -4,
+        flags: &[Write, DenyOom],
+        movable_keys: Some(sorted_sets::store_keys),
+        acl_categories: &["write", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "6.2.0",
+        summary: "Stores the difference of multiple sorted sets in a key.",
+        parse: sorted_sets::parse_zdiffstore,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zincrby",
+        arity: 4,
+        flags: &[Write, DenyOom, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "sortedset", "fast"],
+        group: "sorted_set",
+        since: "1.2.0",
+        summary: "Increments the score of a member in a sorted set.",
+        parse: sorted_sets::parse_zincrby,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zinter",
+        arity: -3,
+        flags: &[ReadOnly],
+        movable_keys: Some(sorted_sets::counted_keys),
+        acl_categories: &["read", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "6.2.0",
+        summary: "Returns the intersect of multiple sorted sets.",
+        parse: sorted_sets::parse_zinter,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zintercard",
+        arity: -3,
+        flags: &[ReadOnly],
+        movable_keys: Some(sorted_sets::counted_keys),
+        acl_categories: &["read", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "7.0.0",
+        summary: "Returns the number of members of the intersect of multiple sorted sets.",
+        parse: sorted_sets::parse_zintercard,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zinterstore",
+        arity: -4,
+        flags: &[Write, DenyOom],
+        movable_keys: Some(sorted_sets::store_keys),
+        acl_categories: &["write", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "2.0.0",
+        summary: "Stores the intersect of multiple sorted sets in a key.",
+        parse: sorted_sets::parse_zinterstore,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zlexcount",
+        arity: 4,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "fast"],
+        group: "sorted_set",
+        since: "2.8.9",
+        summary: "Returns the number of members in a sorted set within a lexicographical range.",
+        parse: sorted_sets::parse_zlexcount,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zmpop",
+        arity: -4,
+        flags: &[Write],
+        movable_keys: Some(sorted_sets::counted_keys),
+        acl_categories: &["write", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "7.0.0",
+        summary: "Returns the highest- or lowest-scoring members from one or more sorted sets after removing them. Deletes the sorted set if the last member was popped.",
+        parse: sorted_sets::parse_zmpop,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zmscore",
+        arity: -3,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "fast"],
+        group: "sorted_set",
+        since: "6.2.0",
+        summary: "Returns the score of one or more members in a sorted set.",
+        parse: sorted_sets::parse_zmscore,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zpopmax",
+        arity: -2,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "sortedset", "fast"],
+        group: "sorted_set",
+        since: "5.0.0",
+        summary: "Returns the highest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped.",
+        parse: sorted_sets::parse_zpopmax,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zpopmin",
+        arity: -2,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "sortedset", "fast"],
+        group: "sorted_set",
+        since: "5.0.0",
+        summary: "Returns the lowest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped.",
+        parse: sorted_sets::parse_zpopmin,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zrandmember",
+        arity: -2,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "6.2.0",
+        summary: "Returns one or more random members from a sorted set.",
+        parse: sorted_sets::parse_zrandmember,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zrange",
+        arity: -4,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "1.2.0",
+        summary: "Returns members in a sorted set within a range of indexes.",
+        parse: sorted_sets::parse_zrange,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zrangebylex",
+        arity: -4,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "2.8.9",
+        summary: "Returns members in a sorted set within a lexicographical range.",
+        parse: sorted_sets::parse_zrangebylex,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zrangebyscore",
+        arity: -4,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "1.0.5",
+        summary: "Returns members in a sorted set within a range of scores.",
+        parse: sorted_sets::parse_zrangebyscore,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zrangestore",
+        arity: -5,
+        flags: &[Write, DenyOom],
+        keys: KeyRange::new(1, 2, 1),
+        acl_categories: &["write", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "6.2.0",
+        summary: "Stores a range of members from sorted set in a key.",
+        parse: sorted_sets::parse_zrangestore,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zrank",
+        arity: -3,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "fast"],
+        group: "sorted_set",
+        since: "2.0.0",
+        summary: "Returns the index of a member in a sorted set ordered by ascending scores.",
+        parse: sorted_sets::parse_zrank,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zrem",
+        arity: -3,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "sortedset", "fast"],
+        group: "sorted_set",
+        since: "1.2.0",
+        summary: "Removes one or more members from a sorted set. Deletes the sorted set if all members were removed.",
+        parse: sorted_sets::parse_zrem,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zremrangebylex",
+        arity: 4,
+        flags: &[Write],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "2.8.9",
+        summary: "Removes members in a sorted set within a lexicographical range. Deletes the sorted set if all members were removed.",
+        parse: sorted_sets::parse_zremrangebylex,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zremrangebyrank",
+        arity: 4,
+        flags: &[Write],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "2.0.0",
+        summary: "Removes members in a sorted set within a range of indexes. Deletes the sorted set if all members were removed.",
+        parse: sorted_sets::parse_zremrangebyrank,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zremrangebyscore",
+        arity: 4,
+        flags: &[Write],
+        keys: KeyRange::FIRST,
+        acl_categories: &["write", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "1.2.0",
+        summary: "Removes members in a sorted set within a range of scores. Deletes the sorted set if all members were removed.",
+        parse: sorted_sets::parse_zremrangebyscore,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zrevrange",
+        arity: -4,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "1.2.0",
+        summary: "Returns members in a sorted set within a range of indexes in reverse order.",
+        parse: sorted_sets::parse_zrevrange,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zrevrangebylex",
+        arity: -4,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "2.8.9",
+        summary: "Returns members in a sorted set within a lexicographical range in reverse order.",
+        parse: sorted_sets::parse_zrevrangebylex,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zrevrangebyscore",
+        arity: -4,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "2.2.0",
+        summary: "Returns members in a sorted set within a range of scores in reverse order.",
+        parse: sorted_sets::parse_zrevrangebyscore,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zrevrank",
+        arity: -3,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "fast"],
+        group: "sorted_set",
+        since: "2.0.0",
+        summary: "Returns the index of a member in a sorted set ordered by descending scores.",
+        parse: sorted_sets::parse_zrevrank,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zscore",
+        arity: 3,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "fast"],
+        group: "sorted_set",
+        since: "1.2.0",
+        summary: "Returns the score of a member in a sorted set.",
+        parse: sorted_sets::parse_zscore,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zunion",
+        arity: -3,
+        flags: &[ReadOnly],
+        movable_keys: Some(sorted_sets::counted_keys),
+        acl_categories: &["read", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "6.2.0",
+        summary: "Returns the union of multiple sorted sets.",
+        parse: sorted_sets::parse_zunion,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zunionstore",
+        arity: -4,
+        flags: &[Write, DenyOom],
+        movable_keys: Some(sorted_sets::store_keys),
+        acl_categories: &["write", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "2.0.0",
+        summary: "Stores the union of multiple sorted sets in a key.",
+        parse: sorted_sets::parse_zunionstore,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "scan",
+        arity: -2,
+        flags: &[ReadOnly],
+        acl_categories: &["keyspace", "read", "slow"],
+        since: "2.8.0",
+        summary: "Iterates over the key names in the database.",
+        parse: scan::parse_scan,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "hscan",
+        arity: -3,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "hash", "slow"],
+        group: "hash",
+        since: "2.8.0",
+        summary: "Iterates over fields and values of a hash.",
+        parse: scan::parse_hscan,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "sscan",
+        arity: -3,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "set", "slow"],
+        group: "set",
+        since: "2.8.0",
+        summary: "Iterates over members of a set.",
+        parse: scan::parse_sscan,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "zscan",
+        arity: -3,
+        flags: &[ReadOnly],
+        keys: KeyRange::FIRST,
+        acl_categories: &["read", "sortedset", "slow"],
+        group: "sorted_set",
+        since: "2.8.0",
+        summary: "Iterates over members and scores of a sorted set.",
+        parse: scan::parse_zscan,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "del",
+        arity: -2,
+        flags: &[Write],
+        keys: KeyRange::new(1, -1, 1),
+        acl_categories: &["keyspace", "write", "slow"],
+        summary: "Deletes one or more keys.",
+        parse: keys::parse_del,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "exists",
+        arity: -2,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::new(1, -1, 1),
+        acl_categories: &["keyspace", "read", "fast"],
+        summary: "Determines whether one or more keys exist.",
+        parse: keys::parse_exists,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "touch",
+        arity: -2,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::new(1, -1, 1),
+        acl_categories: &["keyspace", "read", "fast"],
+        since: "3.2.1",
+        summary: "Returns the number of existing keys out of those specified after updating the time they were last accessed.",
+        parse: keys::parse_touch,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "rename",
+        arity: 3,
+        flags: &[Write],
+        keys: KeyRange::new(1, 2, 1),
+        acl_categories: &["keyspace", "write", "slow"],
+        summary: "Renames a key and overwrites the destination.",
+        parse: keys::parse_rename,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "renamenx",
+        arity: 3,
+        flags: &[Write, Fast],
+        keys: KeyRange::new(1, 2, 1),
+        acl_categories: &["keyspace", "write", "fast"],
+        summary: "Renames a key only when the target key name doesn't exist.",
+        parse: keys::parse_renamenx,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "copy",
+        arity: -3,
+        flags: &[Write, DenyOom],
+        keys: KeyRange::new(1, 2, 1),
+        acl_categories: &["keyspace", "write", "slow"],
+        since: "6.2.0",
+        summary: "Copies the value of a key to a new key.",
+        parse: keys::parse_copy,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "randomkey",
+        arity: 1,
+        flags: &[ReadOnly],
+        acl_categories: &["keyspace", "read", "slow"],
+        summary: "Returns a random key name from the database.",
+        parse: keys::parse_randomkey,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "type",
+        arity: 2,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["keyspace", "read", "fast"],
+        summary: "Determines the type of value stored at a key.",
+        parse: keys::parse_type,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "move",
+        arity: 3,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["keyspace", "write", "fast"],
+        summary: "Moves a key to another database.",
+        parse: keys::parse_move,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "expire",
+        arity: -3,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["keyspace", "write", "fast"],
+        since: "1.0.0",
+        summary: "Sets the expiration time of a key in seconds.",
+        parse: keys::parse_expire,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "pexpire",
+        arity: -3,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["keyspace", "write", "fast"],
+        since: "2.6.0",
+        summary: "Sets the expiration time of a key in milliseconds.",
+        parse: keys::parse_pexpire,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "expireat",
+        arity: -3,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["keyspace", "write", "fast"],
+        since: "1.2.0",
+        summary: "Sets the expiration time of a key to a Unix timestamp.",
+        parse: keys::parse_expireat,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "pexpireat",
+        arity: -3,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["keyspace", "write", "fast"],
+        since: "2.6.0",
+        summary: "Sets the expiration time of a key to a Unix milliseconds timestamp.",
+        parse: keys::parse_pexpireat,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "ttl",
+        arity: 2,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["keyspace", "read", "fast"],
+        since: "1.0.0",
+        summary: "Returns the expiration time in seconds of a key.",
+        parse: keys::parse_ttl,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "pttl",
+        arity: 2,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["keyspace", "read", "fast"],
+        since: "2.6.0",
+        summary: "Returns the expiration time in milliseconds of a key.",
+        parse: keys::parse_pttl,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "expiretime",
+        arity: 2,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["keyspace", "read", "fast"],
+        since: "7.0.0",
+        summary: "Returns the expiration time of a key as a Unix timestamp.",
+        parse: keys::parse_expiretime,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "pexpiretime",
+        arity: 2,
+        flags: &[ReadOnly, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["keyspace", "read", "fast"],
+        since: "7.0.0",
+        summary: "Returns the expiration time of a key as a Unix milliseconds timestamp.",
+        parse: keys::parse_pexpiretime,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "persist",
+        arity: 2,
+        flags: &[Write, Fast],
+        keys: KeyRange::FIRST,
+        acl_categories: &["keyspace", "write", "fast"],
+        since: "2.2.0",
+        summary: "Removes the expiration time of a key.",
+        parse: keys::parse_persist,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "select",
+        arity: 2,
+        flags: &[Loading, Stale, Fast],
+        acl_categories: &["fast", "connection"],
+        group: "connection",
+        since: "2.0.0",
+        summary: "Changes the selected database.",
+        parse: connection::parse_select,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "swapdb",
+        arity: 3,
+        flags: &[Write, Fast],
+        acl_categories: &["keyspace", "write", "fast", "dangerous"],
+        group: "server",
+        since: "4.0.0",
+        summary: "Swaps two Redis databases.",
+        parse: server::parse_swapdb,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "flushdb",
+        arity: -1,
+        flags: &[Write],
+        acl_categories: &["keyspace", "write", "slow", "dangerous"],
+        group: "server",
+        summary: "Removes all keys from the current database.",
+        parse: server::parse_flushdb,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "flushall",
+        arity: -1,
+        flags: &[Write],
+        acl_categories: &["keyspace", "write", "slow", "dangerous"],
+        group: "server",
+        summary: "Removes all keys from all databases.",
+        parse: server::parse_flushall,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "dbsize",
+        arity: 1,
+        flags: &[ReadOnly, Fast],
+        acl_categories: &["keyspace", "read", "fast"],
+        group: "server",
+        summary: "Returns the number of keys in the database.",
+        parse: server::parse_dbsize,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "save",
+        arity: 1,
+        flags: &[Admin, NoScript],
+        acl_categories: &["admin", "slow", "dangerous"],
+        group: "server",
+        summary: "Synchronously saves the database(s) to disk.",
+        parse: server::parse_save,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "config",
+        arity: -2,
+        acl_categories: &["slow"],
+        group: "server",
+        since: "2.0.0",
+        summary: "A container for server configuration commands.",
+        subcommands: &[CommandSpec {
+            name: "config|get",
+            arity: -3,
+            flags: &[Admin, NoScript, Loading, Stale],
+            acl_categories: &["admin", "slow", "dangerous"],
+            group: "server",
+            since: "2.0.0",
+            summary: "Returns the effective values of configuration parameters.",
+            parse: server::parse_config_get,
+            ..CommandSpec::DEFAULT
+        }],
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "debug",
+        arity: -2,
+        flags: &[Admin, NoScript, Loading, Stale],
+        acl_categories: &["admin", "slow", "dangerous"],
+        group: "server",
+        summary: "A container for debugging commands.",
+        parse: server::parse_debug,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "info",
+        arity: -1,
+        flags: &[Loading, Stale],
+        acl_categories: &["slow", "dangerous"],
+        group: "server",
+        summary: "Returns information and statistics about the server.",
+        parse: server::parse_info,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "replconf",
+        arity: -1,
+        flags: &[Admin, NoScript, Loading, Stale],
+        acl_categories: &["admin", "slow", "dangerous"],
+        group: "server",
+        since: "3.0.0",
+        summary: "An internal command for configuring the replication stream.",
+        parse: server::parse_replconf,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "psync",
+        arity: -3,
+        flags: &[Admin, NoScript],
+        acl_categories: &["admin", "slow", "dangerous"],
+        group: "server",
+        since: "2.8.0",
+        summary: "An internal command used in replication.",
+        parse: server::parse_psync,
+        ..CommandSpec::DEFAULT
+    },
+    CommandSpec {
+        name: "command",
+        arity: -1,
+        flags: &[Loading, Stale],
+        acl_categories: &["slow", "connection"],
+        group: "server",
+        since: "2.8.13",
+        summary: "Returns detailed information about all commands.",
+        parse: server::parse_command,
+        subcommands: &[
+            CommandSpec {
+                name: "command|count",
+                arity: 2,
+                flags: &[Loading, Stale],
+                acl_categories: &["slow", "connection"],
+                group: "server",
+                since: "2.8.13",
+                summary: "Returns a count of commands.",
+                parse: server::parse_command_count,
+                ..CommandSpec::DEFAULT
+            },
+            CommandSpec {
+                name: "command|docs",
+                arity: -2,
+                flags: &[Loading, Stale],
+                acl_categories: &["slow", "connection"],
+                group: "server",
+                since: "7.0.0",
+                summary: "Returns documentary information about one, multiple or all commands.",
+                parse: server::parse_command_docs,
+                ..CommandSpec::DEFAULT
+            },
+            CommandSpec {
+                name: "command|getkeys",
+                arity: -3,
+                flags: &[Loading, Stale],
+                acl_categories: &["slow", "connection"],
+                group: "server",
+                since: "2.8.13",
+                summary: "Extracts the key names from an arbitrary command.",
+                parse: server::parse_command_getkeys,
+                ..CommandSpec::DEFAULT
+            },
+            CommandSpec {
+                name: "command|info",
+                arity: -2,
+                flags: &[Loading, Stale],
+                acl_categories: &["slow", "connection"],
+                group: "server",
+                since: "2.8.13",
+                summary: "Returns information about one, multiple or all commands.",
+                parse: server::parse_command_info,
+                ..CommandSpec::DEFAULT
+            },
+            CommandSpec {
+                name: "command|list",
+                arity: -2,
+                flags: &[Loading, Stale],
+                acl_categories: &["slow", "connection"],
+                group: "server",
+                since: "7.0.0",
+                summary: "Returns a list of command names.",
+                parse: server::parse_command_list,
+                ..CommandSpec::DEFAULT
+            },
+        ],
+        ..CommandSpec::DEFAULT
+    },
+];
+
+fn index() -> &'static HashMap<&'static str, &'static CommandSpec> {
+    static INDEX: OnceLock<HashMap<&'static str, &'static CommandSpec>> = OnceLock::new();
+
+    INDEX.get_or_init(|| {
+        COMMAND_TABLE
+            .iter()
+            .flat_map(|spec| std::iter::once(spec).chain(spec.subcommands.iter()))
+            .map(|spec| (spec.name, spec))
+            .collect()
+    })
+}
+
+/// Looks a command or a `container|subcommand` up by its case-insensitive name.
+pub fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    index().get(name.to_lowercase().as_str()).copied()
+}
+
+/// Finds the spec that handles `args`, descending into subcommands, and checks its arity.
+pub fn resolve(args: &[Bytes]) -> Result<&'static CommandSpec, CommandError> {
+    let name = args.first().map(|name| text(name)).unwrap_or_default();
+
+    let Some(spec) = lookup(&name) else {
+        let args_preview = args
+            .iter()
+            .skip(1)
+            .map(|arg| format!("'{}' ", text(arg)))
+            .collect::<String>();
+
+        return Err(CommandError::UnknownCommand(name, args_preview));
+    };
+
+    if !spec.subcommands.is_empty() && args.len() >= 2 {
+        let subcommand = text(&args[1]);
+
+        return match lookup(&format!("{}|{subcommand}", spec.name)) {
+            Some(sub_spec) => {
+                sub_spec.check_arity(args.len())?;
+
+                Ok(sub_spec)
+            }
+
+            None => Err(CommandError::UnknownSubcommand(
+                subcommand,
+                spec.name.to_uppercase(),
+            )),
+        };
+    }
+
+    spec.check_arity(args.len())?;
+
+    Ok(spec)
+}
+
+/// Answers the `COMMAND` family straight from the table.
+pub fn introspect(request: CommandIntrospection) -> RespDataTypes {
+    match request {
+        CommandIntrospection::All => {
+            RespDataTypes::Array(COMMAND_TABLE.iter().map(|spec| spec.info()).collect())
+        }
+
+        CommandIntrospection::Count => RespDataTypes::Integer(COMMAND_TABLE.len() as i64),
+
+        CommandIntrospection::Info(names) if names.is_empty() => {
+            introspect(CommandIntrospection::All)
+        }
+
+        CommandIntrospection::Info(names) => RespDataTypes::Array(
+            names
+                .iter()
+                .map(|name| lookup(name).map_or(RespDataTypes::Null, |spec| spec.info()))
+                .collect(),
+        ),
+
+        CommandIntrospection::Docs(names) => {
+            let specs: Vec<&CommandSpec> = if names.is_empty() {
+                COMMAND_TABLE.iter().collect()
+            } else {
+                names.iter().filter_map(|name| lookup(name)).collect()
+            };
+
+            RespDataTypes::Map(
+                specs
+                    .into_iter()
+                    .map(|spec| (RespDataTypes::BulkString(spec.name.into()), spec.docs()))
+                    .collect(),
+            )
+        }
+
+        CommandIntrospection::List(filter) => {
+            let names = COMMAND_TABLE
+                .iter()
+                .flat_map(|spec| std::iter::once(spec).chain(spec.subcommands.iter()))
+                .filter(|spec| match &filter {
+                    None => true,
+
+                    Some(ListFilter::Module(_)) => false,
+
+                    Some(ListFilter::AclCategory(category)) => spec
+                        .acl_categories
+                        .iter()
+                        .any(|c| c.eq_ignore_ascii_case(category.trim_start_matches('@'))),
+
+                    Some(ListFilter::Pattern(pattern)) => {
+                        glob::matches(pattern.as_bytes(), spec.name.as_bytes(), true)
+                    }
+                })
+                .map(|spec| RespDataTypes::BulkString(spec.name.into()))
+                .collect();
+
+            RespDataTypes::Array(names)
+        }
+
+        CommandIntrospection::GetKeys(args) => match get_keys(&args) {
+            Ok(keys) => {
+                RespDataTypes::Array(keys.into_iter().map(RespDataTypes::BulkString).collect())
+            }
+
+            Err(e) => RespDataTypes::SimpleError(e.to_string()),
+        },
+    }
+}
+
+fn get_keys(args: &[Bytes]) -> Result<Vec<Bytes>, CommandError> {
+    let spec = resolve(args).map_err(|e| match e {
+        CommandError::WrongArity(_) => {
+            CommandError::from("ERR Invalid number of arguments specified for command")
+        }
+
+        _ => CommandError::from("ERR Invalid command specified"),
+    })?;
+
+    let positions = spec.key_positions(args);
+
+    if positions.is_empty() {
+        return Err(CommandError::from("ERR The command has no key arguments"));
+    }
+
+    Ok(positions.into_iter().map(|idx| args[idx].clone()).collect())
+}
@@ -1,5 +1,7 @@
 use clap::Parser;
 
+use super::configurations::ProtectedAccess;
+
 #[derive(Debug, Parser)]
 pub struct CmdOptions {
     #[arg(short = 'd', long = "dir", default_value = "/tmp/redis-files")]
@@ -16,6 +18,24 @@ pub struct CmdOptions {
 
     #[arg(short, long = "replicaof", value_parser = valid_replicaof)]
     pub replicatof: Option<String>,
+
+    #[arg(long = "databases", default_value = "16", value_parser = clap::value_parser!(u32).range(1..))]
+    pub databases: u32,
+
+    #[arg(long = "proto-max-bulk-len", default_value = "512mb", value_parser = memory_size)]
+    pub proto_max_bulk_len: u64,
+
+    #[arg(long = "client-query-buffer-limit", default_value = "1gb", value_parser = memory_size)]
+    pub client_query_buffer_limit: u64,
+
+    #[arg(long = "max-multibulk-len", default_value = "1048576")]
+    pub max_multibulk_len: u32,
+
+    #[arg(long = "max-nesting-depth", default_value = "16")]
+    pub max_nesting_depth: u32,
+
+    #[arg(long = "enable-debug-command", default_value = "no", value_enum)]
+    pub enable_debug_command: ProtectedAccess,
 }
 
 fn valid_replicaof(value: &str) -> Result<String, String> {
@@ -27,3 +47,32 @@ fn valid_replicaof(value: &str) -> Result<String, String> {
 
     Ok(str.to_string())
 }
+
+/// Parses sizes the way redis.conf writes them: `1024`, `100kb`, `512mb`, `1gb`, or the
+/// decimal `k`/`m`/`g` variants.
+fn memory_size(value: &str) -> Result<u64, String> {
+    let value = value.trim().to_lowercase();
+
+    let split_at = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+
+    let (number, unit) = value.split_at(split_at);
+
+    let multiplier: u64 = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return Err(format!("Invalid memory unit in '{value}'")),
+    };
+
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| format!("Invalid memory size '{value}'"))
+}
@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 
-use crate::state::replication_state::Role;
+use clap::ValueEnum;
+
+use crate::{resp::ProtocolLimits, state::replication_state::Role};
 
 use super::cmd_options::CmdOptions;
 
@@ -17,6 +19,47 @@ pub struct Configuration {
     pub master_address: Option<String>,
 
     pub replication_role: Role,
+
+    /// Number of logical databases, selectable as `0..databases`.
+    pub databases: u32,
+
+    pub protocol_limits: ProtocolLimits,
+
+    pub enable_debug_command: ProtectedAccess,
+}
+
+/// Who may run a command that is dangerous to expose, like `DEBUG`: nobody, everybody, or
+/// only clients connected from the loopback interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProtectedAccess {
+    No,
+    Yes,
+    Local,
+}
+
+impl ProtectedAccess {
+    pub fn name(self) -> &'static str {
+        match self {
+            ProtectedAccess::No => "no",
+
+            ProtectedAccess::Yes => "yes",
+
+            ProtectedAccess::Local => "local",
+        }
+    }
+
+    /// Whether a client connected from `addr` may run the command.
+    pub fn allows(self, addr: &str) -> bool {
+        match self {
+            ProtectedAccess::No => false,
+
+            ProtectedAccess::Yes => true,
+
+            ProtectedAccess::Local => addr
+                .parse::<std::net::SocketAddr>()
+                .is_ok_and(|addr| addr.ip().is_loopback()),
+        }
+    }
 }
 
 impl Configuration {
@@ -28,6 +71,18 @@ impl Configuration {
         PathBuf::from(format!("{}/{}", self.dir, self.filename))
     }
 
+    /// Every parameter [`Configuration::get`] knows, in the order `CONFIG GET` lists them.
+    pub const PARAMETERS: &'static [&'static str] = &[
+        "port",
+        "host",
+        "dir",
+        "filename",
+        "databases",
+        "proto-max-bulk-len",
+        "client-query-buffer-limit",
+        "enable-debug-command",
+    ];
+
     pub fn get(&self, attr: &str) -> Option<String> {
         match attr {
             "port" => Some(self.port.clone()),
@@ -38,10 +93,22 @@ impl Configuration {
 
             "filename" => Some(self.filename.clone()),
 
+            "databases" => Some(self.databases.to_string()),
+
+            "proto-max-bulk-len" => Some(self.protocol_limits.max_bulk_len.to_string()),
+
+            "client-query-buffer-limit" => Some(self.protocol_limits.max_query_buffer.to_string()),
+
+            "enable-debug-command" => Some(self.enable_debug_command.name().to_string()),
+
             _ => None,
         }
     }
 
+    pub fn get_protocol_limits(&self) -> ProtocolLimits {
+        self.protocol_limits
+    }
+
     pub fn get_master_address(&self) -> &Option<String> {
         &self.master_address
     }
@@ -56,6 +123,14 @@ impl From<CmdOptions> for Configuration {
             filename: value.filename,
             master_address: value.replicatof.clone(),
             replication_role: value.replicatof.map_or(Role::Master, |_| Role::Slave),
+            databases: value.databases,
+            protocol_limits: ProtocolLimits {
+                max_bulk_len: value.proto_max_bulk_len.min(i64::MAX as u64) as i64,
+                max_multibulk_len: value.max_multibulk_len as i64,
+                max_nesting_depth: value.max_nesting_depth as usize,
+                max_query_buffer: value.client_query_buffer_limit as usize,
+            },
+            enable_debug_command: value.enable_debug_command,
         }
     }
 }
@@ -0,0 +1,427 @@
+use std::collections::{BTreeSet, HashMap};
+use std::time::Instant;
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use rand::seq::IndexedRandom;
+use rand::Rng;
+
+use super::scan::ScanOrder;
+use super::value::{Hash, Record, RedisValue};
+
+/// Keys sampled from the expires index in one round of the active expire cycle.
+const ACTIVE_EXPIRE_KEYS_PER_LOOP: usize = 20;
+
+/// Another round is sampled while more than this percentage of a round had expired.
+const ACTIVE_EXPIRE_ACCEPTABLE_STALE: usize = 10;
+
+/// What one run of the active expire cycle did to a keyspace.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExpireCycleStats {
+    pub sampled: usize,
+
+    pub expired: usize,
+}
+
+/// The keys of one database. Lookups take the current time and treat expired keys as
+/// missing, removing them on the way.
+#[derive(Debug, Default)]
+pub struct Keyspace {
+    entries: HashMap<Bytes, Record>,
+
+    /// Every key, for `RANDOMKEY` to sample.
+    keys: KeySample,
+
+    /// Keys that have an expiration, for the active expire cycle to sample.
+    expiring: KeySample,
+
+    /// Every key, in the order `SCAN` walks them.
+    scan_order: ScanOrder,
+
+    /// Keys removed because they expired, since the last [`Keyspace::take_expired_count`].
+    expired_count: u64,
+
+    /// Keys removed because they expired, until the next [`Keyspace::take_expired_keys`].
+    expired_keys: Vec<Bytes>,
+
+    /// Hashes with expiring fields, by the earliest expiration among them. Entries go stale
+    /// as fields change and are dropped or refreshed when their time comes.
+    field_deadlines: BTreeSet<(DateTime<Utc>, Bytes)>,
+
+    /// Hash fields removed because they expired, by key, until the next
+    /// [`Keyspace::take_expired_fields`].
+    expired_fields: Vec<(Bytes, Vec<Bytes>)>,
+}
+
+impl Keyspace {
+    pub fn get(&mut self, key: &[u8], now: DateTime<Utc>) -> Option<&Record> {
+        self.expire_if_needed(key, now);
+
+        self.entries.get(key)
+    }
+
+    /// Mutable access to the value only; expirations change through
+    /// [`Keyspace::set_expires_at`] so that the expires index stays in sync.
+    pub fn get_value_mut(&mut self, key: &[u8], now: DateTime<Utc>) -> Option<&mut RedisValue> {
+        self.expire_if_needed(key, now);
+
+        self.entries.get_mut(key).map(|record| &mut record.value)
+    }
+
+    pub fn contains(&mut self, key: &[u8], now: DateTime<Utc>) -> bool {
+        self.get(key, now).is_some()
+    }
+
+    /// Stores `record` under `key`, returning whatever was there, expired or not.
+    pub fn insert(&mut self, key: Bytes, record: Record) -> Option<Record> {
+        if record.expires_at.is_some() {
+            self.expiring.insert(&key);
+        } else {
+            self.expiring.remove(&key);
+        }
+
+        let previous = self.entries.insert(key.clone(), record);
+
+        if previous.is_none() {
+            self.keys.insert(&key);
+
+            self.scan_order.insert(key.clone());
+        }
+
+        self.track_field_expirations(&key);
+
+        previous
+    }
+
+    /// Makes the active expire cycle aware of the expiring fields of the hash at `key`; to be
+    /// called whenever one of its fields is given an expiration.
+    pub fn track_field_expirations(&mut self, key: &Bytes) {
+        let next = self
+            .entries
+            .get(key)
+            .and_then(|record| record.value.as_hash().ok())
+            .and_then(Hash::next_expiration);
+
+        if let Some(next) = next {
+            self.field_deadlines.insert((next, key.clone()));
+        }
+    }
+
+    /// Changes the expiration of an existing key; false when there is no such key.
+    pub fn set_expires_at(&mut self, key: &[u8], expires_at: Option<DateTime<Utc>>) -> bool {
+        let Some((key, record)) = self.entries.get_key_value(key) else {
+            return false;
+        };
+
+        let key = key.clone();
+
+        if record.expires_at.is_none() && expires_at.is_some() {
+            self.expiring.insert(&key);
+        } else if expires_at.is_none() {
+            self.expiring.remove(&key);
+        }
+
+        if let Some(record) = self.entries.get_mut(&key) {
+            record.expires_at = expires_at;
+        }
+
+        true
+    }
+
+    /// Removes `key`, returning its record unless it had already expired.
+    pub fn remove(&mut self, key: &[u8], now: DateTime<Utc>) -> Option<Record> {
+        self.remove_entry(key, now).map(|(_, record)| record)
+    }
+
+    /// Like [`Keyspace::remove`], also handing back the stored key.
+    pub fn remove_entry(&mut self, key: &[u8], now: DateTime<Utc>) -> Option<(Bytes, Record)> {
+        self.expire_if_needed(key, now);
+
+        self.detach(key)
+    }
+
+    /// Moves `key` to `target` with its expiration. Nothing happens, and false is returned,
+    /// when the key does not exist here or already exists in `target`.
+    pub fn move_to(&mut self, target: &mut Keyspace, key: &[u8], now: DateTime<Utc>) -> bool {
+        if target.contains(key, now) {
+            return false;
+        }
+
+        match self.remove_entry(key, now) {
+            Some((key, record)) => {
+                target.insert(key, record);
+
+                true
+            }
+
+            None => false,
+        }
+    }
+
+    /// Copies `source` to `destination` in `target`, or in this keyspace when there is no
+    /// `target`, with its expiration. False when the source does not exist, or the
+    /// destination does and `replace` is not set.
+    pub fn copy_to(
+        &mut self,
+        target: Option<&mut Keyspace>,
+        source: &[u8],
+        destination: Bytes,
+        replace: bool,
+        now: DateTime<Utc>,
+    ) -> bool {
+        let Some(record) = self.get(source, now).cloned() else {
+            return false;
+        };
+
+        let target = target.unwrap_or(self);
+
+        if !replace && target.contains(&destination, now) {
+            return false;
+        }
+
+        target.insert(destination, record);
+
+        true
+    }
+
+    /// Number of keys, counting the expired ones that were not removed yet, like Redis does.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Iterates the keys that did not expire yet.
+    pub fn live(&self, now: DateTime<Utc>) -> impl Iterator<Item = (&Bytes, &Record)> {
+        self.entries
+            .iter()
+            .filter(move |(_, record)| !record.is_expired(now))
+    }
+
+    /// A random key that did not expire, removing the expired ones it comes across.
+    pub fn random_key(&mut self, now: DateTime<Utc>) -> Option<Bytes> {
+        let mut rng = rand::rng();
+
+        while let Some(key) = self.keys.random(&mut rng).cloned() {
+            if !self.expire_if_needed(&key, now) {
+                return Some(key);
+            }
+        }
+
+        None
+    }
+
+    /// One step of `SCAN`: the live keys among the next `count` ones in scan order from
+    /// `cursor`, and the cursor to continue from, 0 once every key was visited. Keys sharing
+    /// a hash are always returned together.
+    pub fn scan(&mut self, cursor: u64, count: usize, now: DateTime<Utc>) -> (u64, Vec<Bytes>) {
+        let (next, visited) = self.scan_order.step(cursor, count);
+
+        let visited: Vec<Bytes> = visited.into_iter().cloned().collect();
+
+        let keys = visited
+            .into_iter()
+            .filter(|key| !self.expire_if_needed(key, now))
+            .collect();
+
+        (next, keys)
+    }
+
+    /// Moves every key out, leaving the expiration statistics in place.
+    pub fn clear(&mut self) -> Keyspace {
+        Keyspace {
+            entries: std::mem::take(&mut self.entries),
+            keys: std::mem::take(&mut self.keys),
+            expiring: std::mem::take(&mut self.expiring),
+            scan_order: std::mem::take(&mut self.scan_order),
+            expired_count: 0,
+            expired_keys: Vec::new(),
+            field_deadlines: std::mem::take(&mut self.field_deadlines),
+            expired_fields: Vec::new(),
+        }
+    }
+
+    pub fn take_expired_count(&mut self) -> u64 {
+        std::mem::take(&mut self.expired_count)
+    }
+
+    pub fn take_expired_keys(&mut self) -> Vec<Bytes> {
+        std::mem::take(&mut self.expired_keys)
+    }
+
+    pub fn take_expired_fields(&mut self) -> Vec<(Bytes, Vec<Bytes>)> {
+        std::mem::take(&mut self.expired_fields)
+    }
+
+    /// Redis's `activeExpireCycle` for one database: samples keys that have an expiration
+    /// and deletes the expired ones, going on while a round found more than
+    /// [`ACTIVE_EXPIRE_ACCEPTABLE_STALE`] percent of them expired and `time_limit` allows.
+    pub fn active_expire(&mut self, now: DateTime<Utc>, time_limit: Instant) -> ExpireCycleStats {
+        let mut stats = ExpireCycleStats::default();
+
+        let mut rng = rand::rng();
+
+        loop {
+            if self.expiring.is_empty() {
+                break;
+            }
+
+            let samples = ACTIVE_EXPIRE_KEYS_PER_LOOP.min(self.expiring.len());
+
+            let mut expired = 0;
+
+            for _ in 0..samples {
+                let Some(key) = self.expiring.random(&mut rng).cloned() else {
+                    break;
+                };
+
+                if self.expire_if_needed(&key, now) {
+                    expired += 1;
+                }
+            }
+
+            stats.sampled += samples;
+            stats.expired += expired;
+
+            if expired * 100 <= samples * ACTIVE_EXPIRE_ACCEPTABLE_STALE
+                || Instant::now() >= time_limit
+            {
+                break;
+            }
+        }
+
+        self.active_expire_fields(now, time_limit);
+
+        stats
+    }
+
+    /// Removes the expired fields of every hash whose earliest field expiration has passed,
+    /// for as long as `time_limit` allows.
+    fn active_expire_fields(&mut self, now: DateTime<Utc>, time_limit: Instant) {
+        while let Some((next, _)) = self.field_deadlines.first() {
+            if *next > now || Instant::now() >= time_limit {
+                break;
+            }
+
+            let (_, key) = self.field_deadlines.pop_first().expect("checked above");
+
+            if !self.expire_if_needed(&key, now) {
+                self.track_field_expirations(&key);
+            }
+        }
+    }
+
+    /// Deletes `key` when it has expired, or when it is a hash whose fields all did,
+    /// reporting whether it did. The expired fields of a hash are removed on the way.
+    fn expire_if_needed(&mut self, key: &[u8], now: DateTime<Utc>) -> bool {
+        let expired = self
+            .entries
+            .get(key)
+            .is_some_and(|record| record.is_expired(now));
+
+        if expired {
+            if let Some((key, _)) = self.detach(key) {
+                self.expired_keys.push(key);
+            }
+
+            self.expired_count += 1;
+
+            return true;
+        }
+
+        self.expire_fields(key, now)
+    }
+
+    /// Removes the expired fields of the hash at `key`, and the key along with its last
+    /// field, reporting whether the key went away.
+    fn expire_fields(&mut self, key: &[u8], now: DateTime<Utc>) -> bool {
+        let Some((key, record)) = self.entries.get_key_value(key) else {
+            return false;
+        };
+
+        let due = record
+            .value
+            .as_hash()
+            .ok()
+            .and_then(Hash::next_expiration)
+            .is_some_and(|next| next <= now);
+
+        if !due {
+            return false;
+        }
+
+        let key = key.clone();
+
+        let Some(RedisValue::Hash(hash)) = self.entries.get_mut(&key).map(|r| &mut r.value) else {
+            return false;
+        };
+
+        let fields = hash.remove_expired(now);
+
+        let emptied = hash.is_empty();
+
+        self.expired_fields.push((key.clone(), fields));
+
+        if emptied {
+            self.detach(&key);
+        } else {
+            self.track_field_expirations(&key);
+        }
+
+        emptied
+    }
+
+    /// Removes `key` from the entries and from every index.
+    fn detach(&mut self, key: &[u8]) -> Option<(Bytes, Record)> {
+        let (key, record) = self.entries.remove_entry(key)?;
+
+        self.keys.remove(&key);
+
+        self.expiring.remove(&key);
+
+        self.scan_order.remove(&key);
+
+        Some((key, record))
+    }
+}
+
+/// Keys in a vector, so that random ones can be sampled, along with the position of each to
+/// remove it without a scan.
+#[derive(Debug, Default)]
+struct KeySample {
+    keys: Vec<Bytes>,
+
+    positions: HashMap<Bytes, usize>,
+}
+
+impl KeySample {
+    fn insert(&mut self, key: &Bytes) {
+        if !self.positions.contains_key(key) {
+            self.positions.insert(key.clone(), self.keys.len());
+
+            self.keys.push(key.clone());
+        }
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        let Some(position) = self.positions.remove(key) else {
+            return;
+        };
+
+        self.keys.swap_remove(position);
+
+        if let Some(moved) = self.keys.get(position) {
+            self.positions.insert(moved.clone(), position);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    fn random(&self, rng: &mut impl Rng) -> Option<&Bytes> {
+        self.keys.choose(rng)
+    }
+}
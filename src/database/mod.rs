@@ -0,0 +1,149 @@
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tokio::sync::{Mutex, MutexGuard};
+
+pub mod keyspace;
+pub mod scan;
+pub mod skiplist;
+pub mod value;
+
+use crate::clock::Clock;
+use crate::glob;
+use keyspace::Keyspace;
+use value::{Record, RedisValue};
+
+#[derive(Debug)]
+pub struct Database {
+    id: u32,
+
+    keyspace: Mutex<Keyspace>,
+
+    /// Decides which keys have expired.
+    clock: Arc<dyn Clock>,
+}
+
+impl Database {
+    pub fn new(id: u32, clock: Arc<dyn Clock>) -> Self {
+        Database {
+            id,
+            keyspace: Mutex::new(Keyspace::default()),
+            clock,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Current time as far as expirations in this database are concerned.
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Locks the keyspace, for commands that read and write it as one atomic step.
+    pub async fn lock(&self) -> MutexGuard<'_, Keyspace> {
+        self.keyspace.lock().await
+    }
+
+    pub async fn insert(&self, key: Bytes, value: RedisValue, expire_time: Option<DateTime<Utc>>) {
+        self.lock()
+            .await
+            .insert(key, Record::new(value, expire_time));
+    }
+
+    /// Type name of the value under `key`, or `none` when the key does not exist.
+    pub async fn type_of(&self, key: &[u8]) -> &'static str {
+        match self.lock().await.get(key, self.now()) {
+            Some(record) => record.value.type_name(),
+
+            None => "none",
+        }
+    }
+
+    pub async fn keys(&self) -> Vec<Bytes> {
+        let now = self.now();
+
+        self.lock()
+            .await
+            .live(now)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Keys matching a glob-style `pattern`.
+    pub async fn keys_matching(&self, pattern: &[u8]) -> Vec<Bytes> {
+        let now = self.now();
+
+        self.lock()
+            .await
+            .live(now)
+            .filter(|(key, _)| glob::matches(pattern, key, false))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// One step of `SCAN`, keeping the keys that match `pattern` and hold a value of type
+    /// `kind`. Returns the next cursor, 0 once done.
+    pub async fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+        kind: Option<&str>,
+    ) -> (u64, Vec<Bytes>) {
+        let now = self.now();
+
+        let mut keyspace = self.lock().await;
+
+        let (next, keys) = keyspace.scan(cursor, count, now);
+
+        let keys = keys
+            .into_iter()
+            .filter(|key| pattern.is_none_or(|pattern| glob::matches(pattern, key, false)))
+            .filter(|key| {
+                kind.is_none_or(|kind| {
+                    keyspace
+                        .get(key, now)
+                        .is_some_and(|record| record.value.type_name() == kind)
+                })
+            })
+            .collect();
+
+        (next, keys)
+    }
+
+    pub async fn len(&self) -> usize {
+        self.lock().await.len()
+    }
+
+    /// Copies every live key, for writing a snapshot.
+    pub async fn entries(&self) -> Vec<(Bytes, Record)> {
+        let now = self.now();
+
+        self.lock()
+            .await
+            .live(now)
+            .map(|(key, record)| (key.clone(), record.clone()))
+            .collect()
+    }
+
+    /// Locks two different databases in id order, so that concurrent multi-database commands
+    /// can not deadlock. The guards are returned in argument order.
+    pub async fn lock_pair<'a>(
+        first: &'a Database,
+        second: &'a Database,
+    ) -> (MutexGuard<'a, Keyspace>, MutexGuard<'a, Keyspace>) {
+        if first.id < second.id {
+            let first_guard = first.lock().await;
+            let second_guard = second.lock().await;
+
+            (first_guard, second_guard)
+        } else {
+            let second_guard = second.lock().await;
+            let first_guard = first.lock().await;
+
+            (first_guard, second_guard)
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeSet;
+use std::hash::BuildHasher;
+use std::sync::OnceLock;
+
+use bytes::Bytes;
+
+/// Position of an element in scan order. Scans walk elements by increasing hash and their
+/// cursor is the hash to resume from, so that an element present for the whole scan is
+/// returned no matter how the collection grows or shrinks in between calls.
+pub fn scan_hash(element: &[u8]) -> u64 {
+    // seeded once per process: cursors only need to stay valid while the server runs
+    static HASHER: OnceLock<RandomState> = OnceLock::new();
+
+    HASHER.get_or_init(RandomState::new).hash_one(element)
+}
+
+/// Elements in scan order, kept by a collection next to its elements so that a scan step
+/// only visits the elements it returns.
+#[derive(Debug, Clone, Default)]
+pub struct ScanOrder {
+    elements: BTreeSet<(u64, Bytes)>,
+}
+
+impl ScanOrder {
+    pub fn insert(&mut self, element: Bytes) {
+        self.elements.insert((scan_hash(&element), element));
+    }
+
+    pub fn remove(&mut self, element: &Bytes) {
+        self.elements.remove(&(scan_hash(element), element.clone()));
+    }
+
+    /// One step of a scan: the next `count` elements in scan order from `cursor`, and the
+    /// cursor to continue from, 0 once every element was visited. Elements sharing a hash
+    /// are always returned together.
+    pub fn step(&self, cursor: u64, count: usize) -> (u64, Vec<&Bytes>) {
+        let mut visited: Vec<(u64, &Bytes)> = Vec::with_capacity(count.min(self.elements.len()));
+
+        let mut next = 0;
+
+        for (hash, element) in self.elements.range((cursor, Bytes::new())..) {
+            if visited.len() >= count && visited.last().is_some_and(|(last, _)| last != hash) {
+                next = *hash;
+
+                break;
+            }
+
+            visited.push((*hash, element));
+        }
+
+        (
+            next,
+            visited.into_iter().map(|(_, element)| element).collect(),
+        )
+    }
+}
@@ -0,0 +1,675 @@
+//! The ordered index of a sorted set: Redis's skiplist, whose links also count how many
+//! elements they skip, so that ranks are found in O(log N) like scores are.
+
+use bytes::Bytes;
+use rand::Rng;
+
+/// Levels a node can have, plenty for 2^64 elements.
+const MAX_LEVEL: usize = 32;
+
+/// Chance of a node also being linked on the next level up.
+const LEVEL_PROBABILITY: f64 = 0.25;
+
+/// Index of the header node, which holds no element and links to the first one on every
+/// level.
+const HEAD: usize = 0;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Link {
+    forward: Option<usize>,
+
+    /// Elements from this node to the one `forward` points to, that one included.
+    span: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    member: Bytes,
+
+    score: f64,
+
+    backward: Option<usize>,
+
+    links: Vec<Link>,
+}
+
+impl Node {
+    /// Whether this node comes before the element `(score, member)`: by score, then by the
+    /// bytes of the member.
+    fn precedes(&self, score: f64, member: &[u8]) -> bool {
+        self.score < score || (self.score == score && self.member.as_ref() < member)
+    }
+
+    fn is(&self, score: f64, member: &[u8]) -> bool {
+        self.score == score && self.member.as_ref() == member
+    }
+}
+
+/// Elements ordered by score then member. Nodes live in a vector and link to each other by
+/// index; the slots of removed nodes are reused.
+#[derive(Debug, Clone)]
+pub struct SkipList {
+    nodes: Vec<Node>,
+
+    free: Vec<usize>,
+
+    tail: Option<usize>,
+
+    len: usize,
+
+    /// Levels in use by at least one node.
+    level: usize,
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        let head = Node {
+            member: Bytes::new(),
+            score: 0.0,
+            backward: None,
+            links: vec![Link::default(); MAX_LEVEL],
+        };
+
+        Self {
+            nodes: vec![head],
+            free: Vec::new(),
+            tail: None,
+            len: 0,
+            level: 1,
+        }
+    }
+}
+
+impl SkipList {
+    /// Adds an element, which must not be in the list yet.
+    pub fn insert(&mut self, score: f64, member: Bytes) {
+        let mut update = [HEAD; MAX_LEVEL];
+
+        // rank of the node in `update` on each level
+        let mut rank = [0usize; MAX_LEVEL];
+
+        let mut x = HEAD;
+
+        for i in (0..self.level).rev() {
+            rank[i] = if i == self.level - 1 { 0 } else { rank[i + 1] };
+
+            while let Some(next) = self.nodes[x].links[i].forward {
+                if !self.nodes[next].precedes(score, &member) {
+                    break;
+                }
+
+                rank[i] += self.nodes[x].links[i].span;
+
+                x = next;
+            }
+
+            update[i] = x;
+        }
+
+        let level = random_level();
+
+        if level > self.level {
+            for i in self.level..level {
+                rank[i] = 0;
+                update[i] = HEAD;
+                self.nodes[HEAD].links[i].span = self.len;
+            }
+
+            self.level = level;
+        }
+
+        let new = self.allocate(Node {
+            member,
+            score,
+            backward: (update[0] != HEAD).then_some(update[0]),
+            links: vec![Link::default(); level],
+        });
+
+        for i in 0..level {
+            let previous = self.nodes[update[i]].links[i];
+
+            let skipped = rank[0] - rank[i];
+
+            self.nodes[new].links[i] = Link {
+                forward: previous.forward,
+                span: previous.span - skipped,
+            };
+
+            self.nodes[update[i]].links[i] = Link {
+                forward: Some(new),
+                span: skipped + 1,
+            };
+        }
+
+        // the levels above the new node now skip one more element
+        for (i, previous) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[*previous].links[i].span += 1;
+        }
+
+        match self.nodes[new].links[0].forward {
+            Some(next) => self.nodes[next].backward = Some(new),
+
+            None => self.tail = Some(new),
+        }
+
+        self.len += 1;
+    }
+
+    /// Removes an element, reporting whether it was there.
+    pub fn remove(&mut self, score: f64, member: &[u8]) -> bool {
+        let mut update = [HEAD; MAX_LEVEL];
+
+        let mut x = HEAD;
+
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].links[i].forward {
+                if !self.nodes[next].precedes(score, member) {
+                    break;
+                }
+
+                x = next;
+            }
+
+            update[i] = x;
+        }
+
+        match self.nodes[x].links[0].forward {
+            Some(target) if self.nodes[target].is(score, member) => {
+                self.unlink(target, &update);
+
+                true
+            }
+
+            _ => false,
+        }
+    }
+
+    /// Zero based position of an element from the lowest one.
+    pub fn rank(&self, score: f64, member: &[u8]) -> Option<usize> {
+        let mut rank = 0;
+
+        let mut x = HEAD;
+
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].links[i].forward {
+                let node = &self.nodes[next];
+
+                if !node.precedes(score, member) && !node.is(score, member) {
+                    break;
+                }
+
+                rank += self.nodes[x].links[i].span;
+
+                x = next;
+            }
+
+            if x != HEAD && self.nodes[x].is(score, member) {
+                return Some(rank - 1);
+            }
+        }
+
+        None
+    }
+
+    /// Elements from the one at zero based `rank` on, towards the highest one, or towards
+    /// the lowest one with `rev`, in which case ranks count from the highest one too.
+    pub fn iter_from_rank(&self, rank: usize, rev: bool) -> Iter<'_> {
+        let rank = match rev {
+            true => self.len.checked_sub(rank + 1),
+
+            false => Some(rank),
+        };
+
+        Iter {
+            list: self,
+            next: rank.and_then(|rank| self.node_at(rank)),
+            rev,
+        }
+    }
+
+    /// Elements from the first one that `before` does not hold for, as long as `within`
+    /// holds; `before` must hold for the elements of a prefix of the list, and `within` for
+    /// a longer one. With `rev` the same elements come from the highest one.
+    pub fn range<'a>(
+        &'a self,
+        before: impl Fn(f64, &[u8]) -> bool + 'a,
+        within: impl Fn(f64, &[u8]) -> bool + 'a,
+        rev: bool,
+    ) -> impl Iterator<Item = (&'a Bytes, f64)> + 'a {
+        let start = if rev {
+            self.last_where(&within)
+        } else {
+            self.first_where(|score, member| !before(score, member))
+        };
+
+        Iter {
+            list: self,
+            next: start,
+            rev,
+        }
+        .take_while(move |(member, score)| match rev {
+            true => !before(*score, member),
+
+            false => within(*score, member),
+        })
+    }
+
+    pub fn first(&self) -> Option<(&Bytes, f64)> {
+        self.nodes[HEAD].links[0]
+            .forward
+            .map(|first| self.element(first))
+    }
+
+    pub fn last(&self) -> Option<(&Bytes, f64)> {
+        self.tail.map(|last| self.element(last))
+    }
+
+    fn element(&self, node: usize) -> (&Bytes, f64) {
+        (&self.nodes[node].member, self.nodes[node].score)
+    }
+
+    /// The node at zero based `rank`.
+    fn node_at(&self, rank: usize) -> Option<usize> {
+        let target = rank + 1;
+
+        let mut traversed = 0;
+
+        let mut x = HEAD;
+
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].links[i].forward {
+                if traversed + self.nodes[x].links[i].span > target {
+                    break;
+                }
+
+                traversed += self.nodes[x].links[i].span;
+
+                x = next;
+            }
+
+            if traversed == target {
+                return Some(x);
+            }
+        }
+
+        None
+    }
+
+    /// The first node `matches` holds for, given that it holds for a suffix of the list.
+    fn first_where(&self, matches: impl Fn(f64, &[u8]) -> bool) -> Option<usize> {
+        let mut x = HEAD;
+
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].links[i].forward {
+                let node = &self.nodes[next];
+
+                if matches(node.score, &node.member) {
+                    break;
+                }
+
+                x = next;
+            }
+        }
+
+        self.nodes[x].links[0].forward
+    }
+
+    /// The last node `matches` holds for, given that it holds for a prefix of the list.
+    fn last_where(&self, matches: impl Fn(f64, &[u8]) -> bool) -> Option<usize> {
+        let mut x = HEAD;
+
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].links[i].forward {
+                let node = &self.nodes[next];
+
+                if !matches(node.score, &node.member) {
+                    break;
+                }
+
+                x = next;
+            }
+        }
+
+        (x != HEAD).then_some(x)
+    }
+
+    /// Takes `target` out of every level, `update` holding the node before it on each.
+    fn unlink(&mut self, target: usize, update: &[usize; MAX_LEVEL]) {
+        for (i, previous) in update.iter().enumerate().take(self.level) {
+            let link = self.nodes[*previous].links[i];
+
+            self.nodes[*previous].links[i] = if link.forward == Some(target) {
+                let removed = self.nodes[target].links[i];
+
+                Link {
+                    forward: removed.forward,
+                    span: link.span + removed.span - 1,
+                }
+            } else {
+                Link {
+                    forward: link.forward,
+                    span: link.span - 1,
+                }
+            };
+        }
+
+        let backward = self.nodes[target].backward;
+
+        match self.nodes[target].links[0].forward {
+            Some(next) => self.nodes[next].backward = backward,
+
+            None => self.tail = backward,
+        }
+
+        while self.level > 1 && self.nodes[HEAD].links[self.level - 1].forward.is_none() {
+            self.level -= 1;
+        }
+
+        self.len -= 1;
+
+        let node = &mut self.nodes[target];
+
+        node.member = Bytes::new();
+        node.links = Vec::new();
+
+        self.free.push(target);
+    }
+
+    fn allocate(&mut self, node: Node) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+
+                index
+            }
+
+            None => {
+                self.nodes.push(node);
+
+                self.nodes.len() - 1
+            }
+        }
+    }
+}
+
+/// Walks the list one element at a time, forwards or backwards.
+pub struct Iter<'a> {
+    list: &'a SkipList,
+
+    next: Option<usize>,
+
+    rev: bool,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a Bytes, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next?;
+
+        let node = &self.list.nodes[current];
+
+        self.next = match self.rev {
+            true => node.backward,
+
+            false => node.links[0].forward,
+        };
+
+        Some((&node.member, node.score))
+    }
+}
+
+/// A level between 1 and [`MAX_LEVEL`], each one less likely than the one below.
+fn random_level() -> usize {
+    let mut rng = rand::rng();
+
+    let mut level = 1;
+
+    while level < MAX_LEVEL && rng.random::<f64>() < LEVEL_PROBABILITY {
+        level += 1;
+    }
+
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks every link, span and backward pointer of `list` against the elements it
+    /// should hold, in order.
+    fn assert_consistent(list: &SkipList, expected: &[(f64, Bytes)]) {
+        assert_eq!(list.len, expected.len());
+
+        // one based position of each node on the bottom level, the header being 0
+        let mut position = vec![None; list.nodes.len()];
+        position[HEAD] = Some(0);
+
+        let mut x = HEAD;
+
+        for (idx, (score, member)) in expected.iter().enumerate() {
+            let next = list.nodes[x].links[0].forward.expect("list ends too early");
+
+            assert!(list.nodes[next].is(*score, member));
+            assert_eq!(list.nodes[next].backward, (x != HEAD).then_some(x));
+
+            position[next] = Some(idx + 1);
+
+            x = next;
+        }
+
+        assert_eq!(list.nodes[x].links[0].forward, None);
+        assert_eq!(list.tail, (x != HEAD).then_some(x));
+
+        for i in 0..list.level {
+            let mut x = HEAD;
+
+            loop {
+                let link = list.nodes[x].links[i];
+
+                let from = position[x].unwrap();
+
+                let to = match link.forward {
+                    Some(next) => position[next].expect("link to a removed node"),
+
+                    None => list.len,
+                };
+
+                assert_eq!(link.span, to - from, "span on level {i}");
+
+                match link.forward {
+                    Some(next) => x = next,
+
+                    None => break,
+                }
+            }
+        }
+
+        if list.level > 1 {
+            assert!(list.nodes[HEAD].links[list.level - 1].forward.is_some());
+        }
+
+        for (idx, (score, member)) in expected.iter().enumerate() {
+            assert_eq!(list.rank(*score, member), Some(idx));
+
+            let (at_rank, at_score) = list.iter_from_rank(idx, false).next().unwrap();
+
+            assert_eq!((at_rank, at_score), (member, *score));
+
+            let (at_rev_rank, _) = list
+                .iter_from_rank(expected.len() - 1 - idx, true)
+                .next()
+                .unwrap();
+
+            assert_eq!(at_rev_rank, member);
+        }
+
+        assert!(list.iter_from_rank(expected.len(), false).next().is_none());
+        assert!(list.iter_from_rank(expected.len(), true).next().is_none());
+
+        let backwards: Vec<_> = list.iter_from_rank(0, true).map(|(m, _)| m).collect();
+
+        assert!(backwards
+            .into_iter()
+            .eq(expected.iter().rev().map(|(_, m)| m)));
+    }
+
+    fn sorted(mut elements: Vec<(f64, Bytes)>) -> Vec<(f64, Bytes)> {
+        elements.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+
+        elements
+    }
+
+    fn element(score: f64, member: &str) -> (f64, Bytes) {
+        (score, Bytes::copy_from_slice(member.as_bytes()))
+    }
+
+    #[test]
+    fn insert_orders_by_score_then_member() {
+        let mut list = SkipList::default();
+
+        let elements = vec![
+            element(3.0, "c"),
+            element(1.0, "b"),
+            element(2.0, "z"),
+            element(1.0, "a"),
+            element(-1.5, "n"),
+            element(2.0, "y"),
+        ];
+
+        for (score, member) in &elements {
+            list.insert(*score, member.clone());
+        }
+
+        assert_consistent(&list, &sorted(elements));
+        assert_eq!(list.first(), Some((&Bytes::from("n"), -1.5)));
+        assert_eq!(list.last(), Some((&Bytes::from("c"), 3.0)));
+        assert_eq!(list.rank(2.0, b"x"), None);
+    }
+
+    #[test]
+    fn remove_fixes_spans_and_tail() {
+        let mut list = SkipList::default();
+
+        let mut elements: Vec<_> = (0..100)
+            .map(|i| element(i as f64, &i.to_string()))
+            .collect();
+
+        for (score, member) in &elements {
+            list.insert(*score, member.clone());
+        }
+
+        for idx in [99, 0, 50, 10] {
+            let (score, member) = elements.remove(idx.min(elements.len() - 1));
+
+            assert!(list.remove(score, &member));
+            assert!(!list.remove(score, &member));
+
+            assert_consistent(&list, &elements);
+        }
+
+        for (score, member) in elements.drain(..) {
+            assert!(list.remove(score, &member));
+        }
+
+        assert_consistent(&list, &[]);
+        assert_eq!(list.level, 1);
+        assert_eq!(list.first(), None);
+    }
+
+    #[test]
+    fn score_updates_move_elements() {
+        let mut list = SkipList::default();
+
+        let mut elements: Vec<_> = (0..20)
+            .map(|i| element(i as f64, &format!("m{i}")))
+            .collect();
+
+        for (score, member) in &elements {
+            list.insert(*score, member.clone());
+        }
+
+        // what `ZADD` and `ZINCRBY` do to an existing member
+        let (score, member) = elements.remove(0);
+
+        list.remove(score, &member);
+        list.insert(100.0, member.clone());
+
+        elements.push((100.0, member));
+
+        let (score, member) = elements.remove(10);
+
+        list.remove(score, &member);
+        list.insert(-100.0, member.clone());
+
+        elements.insert(0, (-100.0, member));
+
+        assert_consistent(&list, &elements);
+    }
+
+    #[test]
+    fn random_operations_keep_spans_consistent() {
+        let mut rng = rand::rng();
+
+        let mut list = SkipList::default();
+
+        let mut expected: Vec<(f64, Bytes)> = Vec::new();
+
+        for round in 0..2000 {
+            let member = Bytes::from(format!("m{}", rng.random_range(0..200)));
+
+            let score = rng.random_range(0..20) as f64;
+
+            let current = expected.iter().position(|(_, m)| *m == member);
+
+            match current {
+                Some(idx) if rng.random_bool(0.5) => {
+                    let (score, member) = expected.remove(idx);
+
+                    assert!(list.remove(score, &member));
+                }
+
+                Some(idx) => {
+                    let (previous, member) = expected.remove(idx);
+
+                    list.remove(previous, &member);
+                    list.insert(score, member.clone());
+
+                    expected.push((score, member));
+                }
+
+                None => {
+                    list.insert(score, member.clone());
+
+                    expected.push((score, member));
+                }
+            }
+
+            expected = sorted(expected);
+
+            if round % 50 == 0 {
+                assert_consistent(&list, &expected);
+            }
+        }
+
+        assert_consistent(&list, &expected);
+    }
+
+    #[test]
+    fn range_walks_between_bounds() {
+        let mut list = SkipList::default();
+
+        for i in 0..10 {
+            list.insert(i as f64, Bytes::from(i.to_string()));
+        }
+
+        let scores = |rev| {
+            list.range(|score, _| score < 3.0, |score, _| score <= 6.0, rev)
+                .map(|(_, score)| score)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(scores(false), vec![3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(scores(true), vec![6.0, 5.0, 4.0, 3.0]);
+    }
+}
@@ -0,0 +1,617 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use super::scan::ScanOrder;
+use super::skiplist::SkipList;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DatabaseError {
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+}
+
+/// A value stored under a key, together with its expiration.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub value: RedisValue,
+
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Record {
+    pub fn new(value: RedisValue, expires_at: Option<DateTime<Utc>>) -> Self {
+        Self { value, expires_at }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub enum RedisValue {
+    String(Bytes),
+
+    List(VecDeque<Bytes>),
+
+    Hash(Hash),
+
+    Set(Set),
+
+    ZSet(SortedSet),
+
+    Stream(Stream),
+}
+
+impl RedisValue {
+    /// Name reported by the `TYPE` command.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::String(_) => "string",
+
+            Self::List(_) => "list",
+
+            Self::Hash(_) => "hash",
+
+            Self::Set(_) => "set",
+
+            Self::ZSet(_) => "zset",
+
+            Self::Stream(_) => "stream",
+        }
+    }
+
+    pub fn as_string(&self) -> Result<&Bytes, DatabaseError> {
+        match self {
+            Self::String(value) => Ok(value),
+
+            _ => Err(DatabaseError::WrongType),
+        }
+    }
+
+    pub fn as_list(&self) -> Result<&VecDeque<Bytes>, DatabaseError> {
+        match self {
+            Self::List(list) => Ok(list),
+
+            _ => Err(DatabaseError::WrongType),
+        }
+    }
+
+    pub fn as_list_mut(&mut self) -> Result<&mut VecDeque<Bytes>, DatabaseError> {
+        match self {
+            Self::List(list) => Ok(list),
+
+            _ => Err(DatabaseError::WrongType),
+        }
+    }
+
+    pub fn as_hash(&self) -> Result<&Hash, DatabaseError> {
+        match self {
+            Self::Hash(hash) => Ok(hash),
+
+            _ => Err(DatabaseError::WrongType),
+        }
+    }
+
+    pub fn as_hash_mut(&mut self) -> Result<&mut Hash, DatabaseError> {
+        match self {
+            Self::Hash(hash) => Ok(hash),
+
+            _ => Err(DatabaseError::WrongType),
+        }
+    }
+
+    pub fn as_set(&self) -> Result<&Set, DatabaseError> {
+        match self {
+            Self::Set(set) => Ok(set),
+
+            _ => Err(DatabaseError::WrongType),
+        }
+    }
+
+    pub fn as_set_mut(&mut self) -> Result<&mut Set, DatabaseError> {
+        match self {
+            Self::Set(set) => Ok(set),
+
+            _ => Err(DatabaseError::WrongType),
+        }
+    }
+
+    pub fn as_zset(&self) -> Result<&SortedSet, DatabaseError> {
+        match self {
+            Self::ZSet(zset) => Ok(zset),
+
+            _ => Err(DatabaseError::WrongType),
+        }
+    }
+
+    pub fn as_zset_mut(&mut self) -> Result<&mut SortedSet, DatabaseError> {
+        match self {
+            Self::ZSet(zset) => Ok(zset),
+
+            _ => Err(DatabaseError::WrongType),
+        }
+    }
+}
+
+/// Latest expiration a hash field can be given, as a Unix time in milliseconds.
+pub const MAX_FIELD_EXPIRE_MS: i64 = ((1 << 48) - 1) >> 2;
+
+/// Fields of a hash, each of which may expire on its own.
+#[derive(Debug, Clone, Default)]
+pub struct Hash {
+    fields: HashMap<Bytes, Bytes>,
+
+    /// Expiration of every field that has one.
+    expires: HashMap<Bytes, DateTime<Utc>>,
+
+    /// The same expirations ordered by time, so that the expired fields are found quickly.
+    deadlines: BTreeSet<(DateTime<Utc>, Bytes)>,
+
+    scan_order: ScanOrder,
+}
+
+impl Hash {
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn get(&self, field: &[u8]) -> Option<&Bytes> {
+        self.fields.get(field)
+    }
+
+    pub fn contains_key(&self, field: &[u8]) -> bool {
+        self.fields.contains_key(field)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, &Bytes)> {
+        self.fields.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Bytes> {
+        self.fields.keys()
+    }
+
+    /// One step of `HSCAN`: the fields from `cursor` on with their values, and the cursor to
+    /// continue from.
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&Bytes, &Bytes)>) {
+        let (next, fields) = self.scan_order.step(cursor, count);
+
+        let fields = fields
+            .into_iter()
+            .filter_map(|field| self.fields.get_key_value(field))
+            .collect();
+
+        (next, fields)
+    }
+
+    /// Sets a field like `HSET` does, dropping any expiration it had.
+    pub fn insert(&mut self, field: Bytes, value: Bytes) -> Option<Bytes> {
+        self.set_expires_at(&field, None);
+
+        self.insert_keep_ttl(field, value)
+    }
+
+    /// Sets a field, keeping its expiration like `HINCRBY` does.
+    pub fn insert_keep_ttl(&mut self, field: Bytes, value: Bytes) -> Option<Bytes> {
+        let previous = self.fields.insert(field.clone(), value);
+
+        if previous.is_none() {
+            self.scan_order.insert(field);
+        }
+
+        previous
+    }
+
+    pub fn remove(&mut self, field: &[u8]) -> Option<Bytes> {
+        self.set_expires_at(field, None);
+
+        let (field, value) = self.fields.remove_entry(field)?;
+
+        self.scan_order.remove(&field);
+
+        Some(value)
+    }
+
+    pub fn expires_at(&self, field: &[u8]) -> Option<DateTime<Utc>> {
+        self.expires.get(field).copied()
+    }
+
+    /// Changes the expiration of an existing field; false when there is no such field.
+    pub fn set_expires_at(&mut self, field: &[u8], expires_at: Option<DateTime<Utc>>) -> bool {
+        let Some((field, _)) = self.fields.get_key_value(field) else {
+            return false;
+        };
+
+        let field = field.clone();
+
+        if let Some(previous) = self.expires.remove(&field) {
+            self.deadlines.remove(&(previous, field.clone()));
+        }
+
+        if let Some(expires_at) = expires_at {
+            self.expires.insert(field.clone(), expires_at);
+            self.deadlines.insert((expires_at, field));
+        }
+
+        true
+    }
+
+    /// The earliest expiration among the fields, `None` when none of them expires.
+    pub fn next_expiration(&self) -> Option<DateTime<Utc>> {
+        self.deadlines.first().map(|(expires_at, _)| *expires_at)
+    }
+
+    /// Removes the fields that expired by `now`, returning their names.
+    pub fn remove_expired(&mut self, now: DateTime<Utc>) -> Vec<Bytes> {
+        let mut removed = Vec::new();
+
+        while let Some((expires_at, _)) = self.deadlines.first() {
+            if *expires_at > now {
+                break;
+            }
+
+            let (_, field) = self.deadlines.pop_first().expect("checked above");
+
+            self.expires.remove(&field);
+            self.fields.remove(&field);
+            self.scan_order.remove(&field);
+
+            removed.push(field);
+        }
+
+        removed
+    }
+}
+
+impl FromIterator<(Bytes, Bytes)> for Hash {
+    fn from_iter<I: IntoIterator<Item = (Bytes, Bytes)>>(iter: I) -> Self {
+        let mut hash = Self::default();
+
+        for (field, value) in iter {
+            hash.insert_keep_ttl(field, value);
+        }
+
+        hash
+    }
+}
+
+/// Members of a set.
+#[derive(Debug, Clone, Default)]
+pub struct Set {
+    members: HashSet<Bytes>,
+
+    scan_order: ScanOrder,
+}
+
+impl Set {
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn contains(&self, member: &[u8]) -> bool {
+        self.members.contains(member)
+    }
+
+    /// Members in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &Bytes> {
+        self.members.iter()
+    }
+
+    /// One step of `SSCAN`: the members from `cursor` on, and the cursor to continue from.
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<&Bytes>) {
+        self.scan_order.step(cursor, count)
+    }
+
+    /// Adds a member, reporting whether it is new.
+    pub fn insert(&mut self, member: Bytes) -> bool {
+        let added = self.members.insert(member.clone());
+
+        if added {
+            self.scan_order.insert(member);
+        }
+
+        added
+    }
+
+    /// Removes a member, reporting whether it was there.
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        let Some(member) = self.members.take(member) else {
+            return false;
+        };
+
+        self.scan_order.remove(&member);
+
+        true
+    }
+}
+
+impl FromIterator<Bytes> for Set {
+    fn from_iter<I: IntoIterator<Item = Bytes>>(iter: I) -> Self {
+        let mut set = Self::default();
+
+        for member in iter {
+            set.insert(member);
+        }
+
+        set
+    }
+}
+
+/// Members of a sorted set, indexed twice: by member for their score, and by score in a
+/// skiplist for ranks and ranges.
+#[derive(Debug, Clone, Default)]
+pub struct SortedSet {
+    scores: HashMap<Bytes, f64>,
+
+    list: SkipList,
+
+    scan_order: ScanOrder,
+}
+
+impl SortedSet {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Members with their scores, in no particular order.
+    pub fn scores(&self) -> impl Iterator<Item = (&Bytes, f64)> {
+        self.scores.iter().map(|(member, score)| (member, *score))
+    }
+
+    /// Members with their scores from the lowest one.
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, f64)> {
+        self.list.iter_from_rank(0, false)
+    }
+
+    /// One step of `ZSCAN`: the members from `cursor` on with their scores, and the cursor
+    /// to continue from.
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&Bytes, f64)>) {
+        let (next, members) = self.scan_order.step(cursor, count);
+
+        let members = members
+            .into_iter()
+            .filter_map(|member| {
+                let score = self.score(member)?;
+
+                Some((member, score))
+            })
+            .collect();
+
+        (next, members)
+    }
+
+    /// Adds a member or changes its score, returning the score it had.
+    pub fn insert(&mut self, member: Bytes, score: f64) -> Option<f64> {
+        let previous = self.scores.insert(member.clone(), score);
+
+        match previous {
+            Some(previous) if previous == score => {}
+
+            Some(previous) => {
+                self.list.remove(previous, &member);
+                self.list.insert(score, member);
+            }
+
+            None => {
+                self.scan_order.insert(member.clone());
+                self.list.insert(score, member);
+            }
+        }
+
+        previous
+    }
+
+    /// Removes a member, returning its score.
+    pub fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let (member, score) = self.scores.remove_entry(member)?;
+
+        self.list.remove(score, &member);
+        self.scan_order.remove(&member);
+
+        Some(score)
+    }
+
+    /// Zero based rank of a member from the lowest score, or from the highest one with `rev`,
+    /// along with its score.
+    pub fn rank(&self, member: &[u8], rev: bool) -> Option<(usize, f64)> {
+        let score = self.score(member)?;
+
+        let rank = self.list.rank(score, member)?;
+
+        Some((if rev { self.len() - 1 - rank } else { rank }, score))
+    }
+
+    /// Members from zero based `rank` on, counting from the highest score with `rev`.
+    pub fn starting_at(&self, rank: usize, rev: bool) -> impl Iterator<Item = (&Bytes, f64)> {
+        self.list.iter_from_rank(rank, rev)
+    }
+
+    /// Members with a score between `min` and `max`, from the highest one with `rev`.
+    pub fn by_score(
+        &self,
+        min: ScoreBound,
+        max: ScoreBound,
+        rev: bool,
+    ) -> impl Iterator<Item = (&Bytes, f64)> {
+        self.list.range(
+            move |score, _| min.below(score),
+            move |score, _| max.reaches(score),
+            rev,
+        )
+    }
+
+    /// Members between `min` and `max` by their bytes, from the last one with `rev`. Only
+    /// meaningful when every member has the same score.
+    pub fn by_lex<'a>(
+        &'a self,
+        min: &'a LexBound,
+        max: &'a LexBound,
+        rev: bool,
+    ) -> impl Iterator<Item = (&'a Bytes, f64)> {
+        self.list.range(
+            move |_, member| min.below(member),
+            move |_, member| max.reaches(member),
+            rev,
+        )
+    }
+
+    /// How many members have a score between `min` and `max`.
+    pub fn count_by_score(&self, min: ScoreBound, max: ScoreBound) -> usize {
+        let first = self.by_score(min, max, false).next();
+
+        let last = self.by_score(min, max, true).next();
+
+        self.count_between(first, last)
+    }
+
+    /// How many members are between `min` and `max` by their bytes.
+    pub fn count_by_lex(&self, min: &LexBound, max: &LexBound) -> usize {
+        let first = self.by_lex(min, max, false).next();
+
+        let last = self.by_lex(min, max, true).next();
+
+        self.count_between(first, last)
+    }
+
+    /// Members from `first` to `last` included, found from their ranks.
+    fn count_between(&self, first: Option<(&Bytes, f64)>, last: Option<(&Bytes, f64)>) -> usize {
+        let (Some((first, first_score)), Some((last, last_score))) = (first, last) else {
+            return 0;
+        };
+
+        match (
+            self.list.rank(first_score, first),
+            self.list.rank(last_score, last),
+        ) {
+            (Some(first), Some(last)) if first <= last => last - first + 1,
+
+            _ => 0,
+        }
+    }
+
+    /// Removes the member with the lowest score, or the highest one with `max`. Members with
+    /// the same score are ordered by their bytes.
+    pub fn pop(&mut self, max: bool) -> Option<(Bytes, f64)> {
+        let (member, score) = if max {
+            self.list.last()
+        } else {
+            self.list.first()
+        }?;
+
+        let member = member.clone();
+
+        self.remove(&member);
+
+        Some((member, score))
+    }
+}
+
+impl FromIterator<(Bytes, f64)> for SortedSet {
+    fn from_iter<I: IntoIterator<Item = (Bytes, f64)>>(iter: I) -> Self {
+        let mut zset = Self::default();
+
+        for (member, score) in iter {
+            zset.insert(member, score);
+        }
+
+        zset
+    }
+}
+
+/// One end of a score range, `(` in front of the score making it exclusive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub value: f64,
+
+    pub exclusive: bool,
+}
+
+impl ScoreBound {
+    /// Whether `score` comes before a range starting at this bound.
+    fn below(&self, score: f64) -> bool {
+        score < self.value || (self.exclusive && score == self.value)
+    }
+
+    /// Whether `score` does not go past a range ending at this bound.
+    fn reaches(&self, score: f64) -> bool {
+        score < self.value || (!self.exclusive && score == self.value)
+    }
+}
+
+/// One end of a range of members compared by their bytes: `-` and `+`, or a member after
+/// `[` or `(` for an inclusive or exclusive one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    Min,
+
+    Max,
+
+    Inclusive(Bytes),
+
+    Exclusive(Bytes),
+}
+
+impl LexBound {
+    /// Whether `member` comes before a range starting at this bound.
+    fn below(&self, member: &[u8]) -> bool {
+        match self {
+            Self::Min => false,
+
+            Self::Max => true,
+
+            Self::Inclusive(bound) => member < bound.as_ref(),
+
+            Self::Exclusive(bound) => member <= bound.as_ref(),
+        }
+    }
+
+    /// Whether `member` does not go past a range ending at this bound.
+    fn reaches(&self, member: &[u8]) -> bool {
+        match self {
+            Self::Min => false,
+
+            Self::Max => true,
+
+            Self::Inclusive(bound) => member <= bound.as_ref(),
+
+            Self::Exclusive(bound) => member < bound.as_ref(),
+        }
+    }
+}
+
+/// Stream entry ID, `<milliseconds>-<sequence>`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StreamId {
+    pub ms: u64,
+
+    pub seq: u64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, Vec<(Bytes, Bytes)>>,
+
+    last_id: StreamId,
+}
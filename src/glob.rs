@@ -0,0 +1,200 @@
+//! Glob-style patterns as Redis understands them in `KEYS`, `SCAN MATCH`, `CONFIG GET` and
+//! friends: `*`, `?`, `[abc]`, `[^abc]`, `[a-z]` and `\` to escape the next character.
+
+/// Whether the whole of `string` matches `pattern`, optionally ignoring ASCII case.
+///
+/// Only `*` can match more than one way, so instead of recursing like Redis does, a mismatch
+/// resumes from the last `*` with one more character swallowed by it. That keeps patterns
+/// such as `*a*a*a*b` linear in practice instead of exponential.
+pub fn matches(pattern: &[u8], string: &[u8], nocase: bool) -> bool {
+    let mut p = 0;
+    let mut s = 0;
+
+    // pattern position right after the last `*` and the string position it resumes at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while s < string.len() {
+        if pattern.get(p) == Some(&b'*') {
+            while pattern.get(p) == Some(&b'*') {
+                p += 1;
+            }
+
+            if p == pattern.len() {
+                return true;
+            }
+
+            backtrack = Some((p, s));
+
+            continue;
+        }
+
+        if let Some(next) = match_single(pattern, p, string[s], nocase) {
+            p = next;
+            s += 1;
+
+            continue;
+        }
+
+        match backtrack {
+            Some((star_p, star_s)) => {
+                p = star_p;
+                s = star_s + 1;
+
+                backtrack = Some((star_p, s));
+            }
+
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Matches `c` against the pattern token at `p`, which is not a `*`, returning where the
+/// next token starts.
+fn match_single(pattern: &[u8], p: usize, c: u8, nocase: bool) -> Option<usize> {
+    let (matched, next) = match *pattern.get(p)? {
+        b'?' => (true, p + 1),
+
+        // a trailing backslash stands for itself
+        b'\\' if p + 1 < pattern.len() => (same(pattern[p + 1], c, nocase), p + 2),
+
+        b'[' => match_class(pattern, p + 1, c, nocase),
+
+        literal => (same(literal, c, nocase), p + 1),
+    };
+
+    matched.then_some(next)
+}
+
+/// Matches `c` against the class whose body starts at `p`. A class missing its `]` runs to
+/// the end of the pattern, like in Redis.
+fn match_class(pattern: &[u8], mut p: usize, c: u8, nocase: bool) -> (bool, usize) {
+    let negated = pattern.get(p) == Some(&b'^');
+
+    if negated {
+        p += 1;
+    }
+
+    let mut matched = false;
+
+    loop {
+        match pattern.get(p) {
+            None => break,
+
+            Some(b']') => {
+                p += 1;
+
+                break;
+            }
+
+            // escaped characters are compared exactly, even without case
+            Some(b'\\') if p + 1 < pattern.len() => {
+                p += 1;
+
+                matched |= pattern[p] == c;
+            }
+
+            Some(&start) if p + 2 < pattern.len() && pattern[p + 1] == b'-' => {
+                let mut start = start;
+                let mut end = pattern[p + 2];
+                let mut c = c;
+
+                if start > end {
+                    std::mem::swap(&mut start, &mut end);
+                }
+
+                if nocase {
+                    start = start.to_ascii_lowercase();
+                    end = end.to_ascii_lowercase();
+                    c = c.to_ascii_lowercase();
+                }
+
+                matched |= start <= c && c <= end;
+
+                p += 2;
+            }
+
+            Some(&literal) => matched |= same(literal, c, nocase),
+        }
+
+        p += 1;
+    }
+
+    (matched != negated, p)
+}
+
+fn same(a: u8, b: u8, nocase: bool) -> bool {
+    if nocase {
+        a.eq_ignore_ascii_case(&b)
+    } else {
+        a == b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(matches(b"*", b"", false));
+        assert!(matches(b"", b"", false));
+        assert!(!matches(b"", b"a", false));
+        assert!(matches(b"h?llo", b"hello", false));
+        assert!(!matches(b"h?llo", b"hllo", false));
+        assert!(matches(b"h*llo", b"hllo", false));
+        assert!(matches(b"h*llo", b"heeeello", false));
+        assert!(matches(b"a**", b"a", false));
+        assert!(matches(b"*a*b", b"xaxxaxb", false));
+        assert!(!matches(b"*a*b", b"xaxxaxbx", false));
+    }
+
+    #[test]
+    fn backtracking_stays_linear() {
+        let string = vec![b'a'; 10_000];
+
+        assert!(!matches(b"*a*a*a*a*a*a*b", &string, false));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(matches(br"a\*b", b"a*b", false));
+        assert!(!matches(br"a\*b", b"axb", false));
+        assert!(matches(br"\?", b"?", false));
+        assert!(!matches(br"\?", b"a", false));
+        assert!(matches(br"\[a]", b"[a]", false));
+        assert!(matches(b"a\\", b"a\\", false));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(matches(b"h[ae]llo", b"hallo", false));
+        assert!(!matches(b"h[ae]llo", b"hillo", false));
+        assert!(matches(b"h[^e]llo", b"hallo", false));
+        assert!(!matches(b"h[^e]llo", b"hello", false));
+        assert!(matches(br"[\]]", b"]", false));
+        assert!(matches(br"[\^x]", b"^", false));
+        assert!(matches(b"[abc", b"b", false));
+    }
+
+    #[test]
+    fn ranges() {
+        assert!(matches(b"h[a-c]llo", b"hbllo", false));
+        assert!(!matches(b"h[a-c]llo", b"hdllo", false));
+        assert!(matches(b"[z-a]", b"m", false));
+        assert!(matches(b"[^0-9]", b"x", false));
+        assert!(!matches(b"[^0-9]", b"5", false));
+        assert!(matches(b"[-a]", b"-", false));
+    }
+
+    #[test]
+    fn nocase() {
+        assert!(matches(b"HELLO*", b"hello world", true));
+        assert!(!matches(b"HELLO*", b"hello world", false));
+        assert!(matches(b"[A-Z]", b"q", true));
+        assert!(!matches(b"[A-Z]", b"q", false));
+        assert!(matches(b"[Q]", b"q", true));
+        assert!(matches(br"\Q", b"q", true));
+    }
+}
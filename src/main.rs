@@ -1,8 +1,12 @@
 use anyhow::Ok;
 use clap::Parser;
 
+mod client;
+mod clock;
+mod commands;
 mod configs;
 mod database;
+mod glob;
 mod persistence;
 mod redis_server;
 mod redis_service;
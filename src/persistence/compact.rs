@@ -0,0 +1,417 @@
+//! Decoders for the compact encodings Redis stores small collections in: zipmaps and
+//! ziplists, found in older RDB files, listpacks, which replaced them in Redis 7, and
+//! intsets for sets of integers.
+
+use anyhow::{bail, ensure, Context};
+use bytes::Bytes;
+
+/// Field and value pairs of a zipmap: a one byte count, then each field and value preceded
+/// by their length, values also by a count of unused bytes after them, and a `0xFF`
+/// terminator.
+pub fn zipmap_entries(data: &[u8]) -> anyhow::Result<Vec<(Bytes, Bytes)>> {
+    let mut idx = 1;
+
+    let mut entries = Vec::new();
+
+    loop {
+        let (field, next) = match zipmap_string(data, idx, false)? {
+            Some(entry) => entry,
+
+            None => break,
+        };
+
+        let (value, next) =
+            zipmap_string(data, next, true)?.with_context(|| "Zipmap field without a value")?;
+
+        entries.push((field, value));
+
+        idx = next;
+    }
+
+    Ok(entries)
+}
+
+/// The zipmap string at `idx` and where the next one starts, `None` at the terminator.
+fn zipmap_string(data: &[u8], idx: usize, value: bool) -> anyhow::Result<Option<(Bytes, usize)>> {
+    let first = *data.get(idx).with_context(|| "Unterminated zipmap")?;
+
+    let (len, mut idx) = match first {
+        0xFF => return Ok(None),
+
+        // a four byte length follows
+        0xFE => {
+            let len = bytes_at(data, idx + 1, 4)?;
+
+            (
+                u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize,
+                idx + 5,
+            )
+        }
+
+        len => (len as usize, idx + 1),
+    };
+
+    let mut free = 0;
+
+    if value {
+        free = *data.get(idx).with_context(|| "Truncated zipmap entry")? as usize;
+
+        idx += 1;
+    }
+
+    let string = Bytes::copy_from_slice(bytes_at(data, idx, len)?);
+
+    Ok(Some((string, idx + len + free)))
+}
+
+/// Entries of a ziplist: a header with its size, tail offset and entry count, then entries
+/// made of the previous entry's length, an encoding and the data, and a `0xFF` terminator.
+pub fn ziplist_entries(data: &[u8]) -> anyhow::Result<Vec<Bytes>> {
+    ensure!(data.len() >= 11, "Truncated ziplist header");
+
+    let mut idx = 10;
+
+    let mut entries = Vec::with_capacity(u16::from_le_bytes([data[8], data[9]]) as usize);
+
+    loop {
+        let first = *data.get(idx).with_context(|| "Unterminated ziplist")?;
+
+        if first == 0xFF {
+            break;
+        }
+
+        // length of the previous entry, one byte or 0xFE and four more
+        idx += if first < 0xFE { 1 } else { 5 };
+
+        let encoding = *data.get(idx).with_context(|| "Truncated ziplist entry")?;
+
+        let (entry, len) = match encoding >> 6 {
+            0b00 => string_at(data, idx + 1, (encoding & 0x3F) as usize)?,
+
+            0b01 => {
+                let low = *data
+                    .get(idx + 1)
+                    .with_context(|| "Truncated ziplist entry")?;
+
+                let len = ((encoding & 0x3F) as usize) << 8 | low as usize;
+
+                let (entry, used) = string_at(data, idx + 2, len)?;
+
+                (entry, used + 1)
+            }
+
+            0b10 => {
+                let len = bytes_at(data, idx + 1, 4)?;
+
+                let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+
+                let (entry, used) = string_at(data, idx + 5, len)?;
+
+                (entry, used + 4)
+            }
+
+            _ => {
+                let (value, width) = match encoding {
+                    0xC0 => (signed_le(bytes_at(data, idx + 1, 2)?), 2),
+
+                    0xD0 => (signed_le(bytes_at(data, idx + 1, 4)?), 4),
+
+                    0xE0 => (signed_le(bytes_at(data, idx + 1, 8)?), 8),
+
+                    0xF0 => (signed_le(bytes_at(data, idx + 1, 3)?), 3),
+
+                    0xFE => (signed_le(bytes_at(data, idx + 1, 1)?), 1),
+
+                    // 4 bit immediate between 0 and 12, stored plus one
+                    0xF1..=0xFD => ((encoding & 0x0F) as i64 - 1, 0),
+
+                    _ => bail!("Unknown ziplist encoding {encoding:#04x}"),
+                };
+
+                (Bytes::from(value.to_string()), width + 1)
+            }
+        };
+
+        entries.push(entry);
+
+        idx += len;
+    }
+
+    Ok(entries)
+}
+
+/// Entries of a listpack: a header with its size and entry count, then entries made of an
+/// encoding, the data and the length of both, and a `0xFF` terminator.
+pub fn listpack_entries(data: &[u8]) -> anyhow::Result<Vec<Bytes>> {
+    ensure!(data.len() >= 7, "Truncated listpack header");
+
+    let mut idx = 6;
+
+    let mut entries = Vec::with_capacity(u16::from_le_bytes([data[4], data[5]]) as usize);
+
+    loop {
+        let encoding = *data.get(idx).with_context(|| "Unterminated listpack")?;
+
+        if encoding == 0xFF {
+            break;
+        }
+
+        let (entry, len) = if encoding & 0x80 == 0 {
+            // 7 bit unsigned integer
+            (Bytes::from((encoding & 0x7F).to_string()), 1)
+        } else if encoding & 0xC0 == 0x80 {
+            string_at(data, idx + 1, (encoding & 0x3F) as usize)?
+        } else if encoding & 0xE0 == 0xC0 {
+            let low = *data
+                .get(idx + 1)
+                .with_context(|| "Truncated listpack entry")?;
+
+            // 13 bit two's complement integer
+            let value = (((encoding & 0x1F) as i64) << 8 | low as i64) << 51 >> 51;
+
+            (Bytes::from(value.to_string()), 2)
+        } else if encoding & 0xF0 == 0xE0 {
+            let low = *data
+                .get(idx + 1)
+                .with_context(|| "Truncated listpack entry")?;
+
+            let len = ((encoding & 0x0F) as usize) << 8 | low as usize;
+
+            let (entry, used) = string_at(data, idx + 2, len)?;
+
+            (entry, used + 1)
+        } else {
+            match encoding {
+                0xF0 => {
+                    let len = bytes_at(data, idx + 1, 4)?;
+
+                    let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+
+                    let (entry, used) = string_at(data, idx + 5, len)?;
+
+                    (entry, used + 4)
+                }
+
+                0xF1..=0xF4 => {
+                    let width = match encoding {
+                        0xF1 => 2,
+
+                        0xF2 => 3,
+
+                        0xF3 => 4,
+
+                        _ => 8,
+                    };
+
+                    let value = signed_le(bytes_at(data, idx + 1, width)?);
+
+                    (Bytes::from(value.to_string()), width + 1)
+                }
+
+                _ => bail!("Unknown listpack encoding {encoding:#04x}"),
+            }
+        };
+
+        entries.push(entry);
+
+        idx += len + backlen_size(len);
+    }
+
+    Ok(entries)
+}
+
+/// Members of an intset: the width of every integer (2, 4 or 8 bytes), their count, both
+/// as 32 bit little endian integers, then the sorted integers themselves.
+pub fn intset_entries(data: &[u8]) -> anyhow::Result<Vec<Bytes>> {
+    let header = bytes_at(data, 0, 8)?;
+
+    let width = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+
+    let count = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+
+    ensure!(
+        matches!(width, 2 | 4 | 8),
+        "Unknown intset encoding {width}"
+    );
+
+    let contents = bytes_at(data, 8, width * count)?;
+
+    Ok(contents
+        .chunks(width)
+        .map(|value| Bytes::from(signed_le(value).to_string()))
+        .collect())
+}
+
+/// Bytes taken by the length a listpack entry of `len` bytes is followed by, seven bits
+/// per byte.
+fn backlen_size(len: usize) -> usize {
+    match len {
+        0..=127 => 1,
+
+        128..=16382 => 2,
+
+        16383..=2097150 => 3,
+
+        2097151..=268435454 => 4,
+
+        _ => 5,
+    }
+}
+
+/// A `len` bytes long string starting at `start`, with the bytes used to encode it counted
+/// from the encoding byte before `start`.
+fn string_at(data: &[u8], start: usize, len: usize) -> anyhow::Result<(Bytes, usize)> {
+    let value = bytes_at(data, start, len)?;
+
+    Ok((Bytes::copy_from_slice(value), len + 1))
+}
+
+fn bytes_at(data: &[u8], start: usize, len: usize) -> anyhow::Result<&[u8]> {
+    data.get(start..start + len)
+        .with_context(|| "Entry runs past the end of its container")
+}
+
+/// A little endian two's complement integer of up to eight bytes.
+fn signed_le(bytes: &[u8]) -> i64 {
+    let unsigned = bytes
+        .iter()
+        .rev()
+        .fold(0u64, |value, byte| (value << 8) | *byte as u64);
+
+    let shift = 64 - 8 * bytes.len() as u32;
+
+    ((unsigned << shift) as i64) >> shift
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(entries: Vec<Bytes>) -> Vec<String> {
+        entries
+            .iter()
+            .map(|entry| String::from_utf8_lossy(entry).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn listpack_decodes_every_encoding() {
+        let long = vec![b'x'; 200];
+
+        let mut data = vec![0, 0, 0, 0, 7, 0];
+        data.extend([0x81, b'a', 0x02]);
+        data.extend([0x05, 0x01]);
+        data.extend([0xDF, 0xFF, 0x02]);
+        data.extend([0xF1, 0xE8, 0x03, 0x03]);
+        data.extend([0xF2, 0x90, 0xEE, 0xFE, 0x04]);
+        data.extend([0xE0, 200]);
+        data.extend(&long);
+        data.extend([0x4A, 0x01]);
+        data.extend([0xF0, 3, 0, 0, 0, b'b', b'i', b'g', 0x08]);
+        data.push(0xFF);
+
+        assert_eq!(
+            strings(listpack_entries(&data).unwrap()),
+            vec![
+                "a".to_string(),
+                "5".to_string(),
+                "-1".to_string(),
+                "1000".to_string(),
+                "-70000".to_string(),
+                String::from_utf8(long).unwrap(),
+                "big".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn listpack_rejects_bad_input() {
+        assert!(listpack_entries(&[0, 0, 0, 0, 1, 0, 0x81, b'a', 0x02]).is_err());
+        assert!(listpack_entries(&[0, 0, 0, 0, 1, 0, 0x85, b'a', 0x02, 0xFF]).is_err());
+        assert!(listpack_entries(&[0, 0, 0, 0, 1, 0, 0xF5, 0xFF]).is_err());
+        assert!(listpack_entries(&[0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn ziplist_decodes_every_encoding() {
+        let long = vec![b'y'; 300];
+
+        let mut data = vec![0; 8];
+        data.extend([6, 0]);
+        data.extend([0x00, 0x02, b'a', b'b']);
+        data.extend([0x04, 0xFD]);
+        data.extend([0x02, 0xC0, 0xFE, 0xFF]);
+        data.extend([0x04, 0xF0, 0x90, 0xEE, 0xFE]);
+        data.extend([0x05, 0x41, 0x2C]);
+        data.extend(&long);
+        data.extend([0xFE, 0x2F, 0x01, 0, 0, 0x01, b'z']);
+        data.push(0xFF);
+
+        assert_eq!(
+            strings(ziplist_entries(&data).unwrap()),
+            vec![
+                "ab".to_string(),
+                "12".to_string(),
+                "-2".to_string(),
+                "-70000".to_string(),
+                String::from_utf8(long).unwrap(),
+                "z".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn ziplist_rejects_bad_input() {
+        let mut data = vec![0; 8];
+        data.extend([1, 0, 0x00, 0x05, b'a', 0xFF]);
+
+        assert!(ziplist_entries(&data).is_err());
+        assert!(ziplist_entries(&[0; 10]).is_err());
+    }
+
+    #[test]
+    fn intset_decodes_each_width() {
+        let mut data = vec![2, 0, 0, 0, 3, 0, 0, 0];
+        data.extend([0xFF, 0xFF, 0x02, 0x00, 0x2C, 0x01]);
+
+        assert_eq!(
+            strings(intset_entries(&data).unwrap()),
+            vec!["-1", "2", "300"]
+        );
+
+        let mut data = vec![8, 0, 0, 0, 1, 0, 0, 0];
+        data.extend(i64::MIN.to_le_bytes());
+
+        assert_eq!(
+            strings(intset_entries(&data).unwrap()),
+            vec![i64::MIN.to_string()]
+        );
+    }
+
+    #[test]
+    fn intset_rejects_bad_input() {
+        assert!(intset_entries(&[3, 0, 0, 0, 1, 0, 0, 0, 1, 2, 3]).is_err());
+        assert!(intset_entries(&[4, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn zipmap_decodes_pairs() {
+        let mut data = vec![2];
+        data.extend([3, b'f', b'o', b'o', 3, 2, b'b', b'a', b'r', 0, 0]);
+        data.extend([0xFE, 1, 0, 0, 0, b'k', 0, 0]);
+        data.push(0xFF);
+
+        let entries = zipmap_entries(&data).unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                (Bytes::from("foo"), Bytes::from("bar")),
+                (Bytes::from("k"), Bytes::new()),
+            ]
+        );
+
+        assert!(zipmap_entries(&[1, 1, b'a']).is_err());
+        assert!(zipmap_entries(&[1, 1, b'a', 0xFF]).is_err());
+    }
+}
@@ -1,2 +1,3 @@
+mod compact;
 pub mod persistence_interface;
 pub mod rdb;
@@ -1,10 +1,20 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
 
-use crate::database::Database;
+use bytes::Bytes;
 
-#[allow(dead_code)]
-pub trait Persistent: Sync + Send {
-    fn save(&self) -> anyhow::Result<()>;
+use crate::database::{value::Record, Database};
+
+/// Live keys of every database, by database index.
+pub type Snapshot = Vec<(u32, Vec<(Bytes, Record)>)>;
+
+pub trait Persistent: Sync + Send + Debug {
+    fn save(&mut self, snapshot: &Snapshot) -> anyhow::Result<()>;
 
     fn load(&mut self) -> anyhow::Result<HashMap<u32, Arc<Database>>>;
+
+    /// Serializes a snapshot without writing it anywhere, for full resyncs of replicas.
+    fn dump(&self, snapshot: &Snapshot) -> anyhow::Result<Vec<u8>>;
+
+    /// Reads databases back from data produced by [`Persistent::dump`].
+    fn restore(&self, data: &[u8]) -> anyhow::Result<HashMap<u32, Arc<Database>>>;
 }
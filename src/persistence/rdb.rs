@@ -1,17 +1,26 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Display;
-use std::fs::{File, OpenOptions};
+use std::fs::{self, File, OpenOptions};
 use std::io::{BufReader, Read};
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use crate::clock::Clock;
+use crate::database::value::{Hash, RedisValue, Set, SortedSet};
 use crate::database::Database;
 
 use anyhow::{bail, ensure, Context};
+use bytes::Bytes;
 
 use chrono::{DateTime, Utc};
 
-use super::persistence_interface::Persistent;
+use crate::redis_service::REDIS_VERSION;
+
+use super::compact::{intset_entries, listpack_entries, ziplist_entries, zipmap_entries};
+use super::persistence_interface::{Persistent, Snapshot};
+
+/// Key name, raw value, value type, expiration and the index right after the entry.
+type DecodedKey = (Bytes, RedisValue, KeyType, Option<DateTime<Utc>>, usize);
 
 #[derive(Debug)]
 enum KeyType {
@@ -20,12 +29,19 @@ enum KeyType {
     Set,
     SortedSet,
     Hash,
+    SortedSet2,
     Zipmap,
     Ziplist,
     Intset,
     ZHashMap,
     ZSortedSet,
     ListQuickList,
+    HashListpack,
+    ZSetListpack,
+    ListQuickList2,
+    SetListpack,
+    HashMetadata,
+    HashListpackEx,
 }
 
 impl Display for KeyType {
@@ -36,32 +52,48 @@ impl Display for KeyType {
             KeyType::Set => write!(f, "Set"),
             KeyType::SortedSet => write!(f, "SortedSet"),
             KeyType::Hash => write!(f, "Hash"),
+            KeyType::SortedSet2 => write!(f, "SortedSet2"),
             KeyType::Zipmap => write!(f, "Zipmap"),
             KeyType::Ziplist => write!(f, "Ziplist"),
             KeyType::Intset => write!(f, "Intset"),
             KeyType::ZHashMap => write!(f, "ZHashMap"),
             KeyType::ZSortedSet => write!(f, "ZSortedSet"),
             KeyType::ListQuickList => write!(f, "ListQuickList"),
+            KeyType::HashListpack => write!(f, "HashListpack"),
+            KeyType::ZSetListpack => write!(f, "ZSetListpack"),
+            KeyType::ListQuickList2 => write!(f, "ListQuickList2"),
+            KeyType::SetListpack => write!(f, "SetListpack"),
+            KeyType::HashMetadata => write!(f, "HashMetadata"),
+            KeyType::HashListpackEx => write!(f, "HashListpackEx"),
         }
     }
 }
 
-impl From<u8> for KeyType {
-    fn from(value: u8) -> Self {
+impl TryFrom<u8> for KeyType {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> anyhow::Result<Self> {
         match value {
-            0x00 => KeyType::String,
-            0x01 => KeyType::List,
-            0x02 => KeyType::Set,
-            0x03 => KeyType::SortedSet,
-            0x04 => KeyType::Hash,
-            0x09 => KeyType::Zipmap,
-            0x0A => KeyType::Ziplist,
-            0x0B => KeyType::Intset,
-            0x0C => KeyType::ZSortedSet,
-            0x0D => KeyType::ZHashMap,
-            0x0E => KeyType::ListQuickList,
-
-            _ => panic!("Invalid key type"),
+            0x00 => Ok(KeyType::String),
+            0x01 => Ok(KeyType::List),
+            0x02 => Ok(KeyType::Set),
+            0x03 => Ok(KeyType::SortedSet),
+            0x04 => Ok(KeyType::Hash),
+            0x05 => Ok(KeyType::SortedSet2),
+            0x09 => Ok(KeyType::Zipmap),
+            0x0A => Ok(KeyType::Ziplist),
+            0x0B => Ok(KeyType::Intset),
+            0x0C => Ok(KeyType::ZSortedSet),
+            0x0D => Ok(KeyType::ZHashMap),
+            0x0E => Ok(KeyType::ListQuickList),
+            0x10 => Ok(KeyType::HashListpack),
+            0x11 => Ok(KeyType::ZSetListpack),
+            0x12 => Ok(KeyType::ListQuickList2),
+            0x14 => Ok(KeyType::SetListpack),
+            0x18 => Ok(KeyType::HashMetadata),
+            0x19 => Ok(KeyType::HashListpackEx),
+
+            _ => bail!("Unsupported value type {value}"),
         }
     }
 }
@@ -115,13 +147,33 @@ impl TryFrom<&u8> for OperationCode {
     }
 }
 
-#[allow(dead_code, clippy::upper_case_acronyms)]
+/// Quicklist node holding one element too large to be packed.
+const QUICKLIST_NODE_PLAIN: usize = 1;
+
+/// Quicklist node holding a listpack of elements.
+const QUICKLIST_NODE_PACKED: usize = 2;
+
+/// File header: magic string and format version.
+const RDB_MAGIC: &[u8] = b"REDIS0011";
+
+/// Most elements reserved up front for a collection or string whose size comes from the
+/// file, which a corrupt or hostile one can make arbitrarily large. Anything bigger grows as
+/// it is read.
+const MAX_PREALLOCATION: usize = 1024;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug)]
 pub struct RDB {
+    path: PathBuf,
+
     reader: Option<BufReader<File>>,
+
+    /// Keys that expired by its time are left out when loading.
+    clock: Arc<dyn Clock>,
 }
 
 impl RDB {
-    pub fn new(file_path: &PathBuf) -> anyhow::Result<Self> {
+    pub fn new(file_path: &PathBuf, clock: Arc<dyn Clock>) -> anyhow::Result<Self> {
         let file = OpenOptions::new()
             .read(true)
             .write(true)
@@ -140,194 +192,456 @@ impl RDB {
         };
 
         Ok(Self {
+            path: file_path.clone(),
             reader: buff_option,
+            clock,
         })
     }
 
     fn decode_length(&self, data: &[u8]) -> anyhow::Result<(usize, usize)> {
-        let first_byte = data[0];
+        let first_byte = *data.first().with_context(|| "Missing length")?;
 
-        let mut next_byte_idx = 1usize;
+        match first_byte >> 6 {
+            // 6 bit length
+            0b00 => Ok(((first_byte & 0x3F) as usize, 1)),
 
-        let bit1 = (first_byte & 0b10000000) >> 7;
+            // 14 bit length
+            0b01 => {
+                ensure!(data.len() >= 2, "Truncated length");
 
-        let bit2 = (first_byte & 0b01000000) >> 6;
+                Ok(((((first_byte & 0x3F) as usize) << 8) | data[1] as usize, 2))
+            }
 
-        let len = match (bit1, bit2) {
-            (0, 1) => {
-                let byte1 = (first_byte & 0x3F) as u16;
-                let byte2 = data[next_byte_idx] as u16;
-                next_byte_idx += 1;
+            // 32 or 64 bit big endian length
+            0b10 => {
+                let width = if first_byte == 0x81 { 8 } else { 4 };
 
-                ((byte1 << 8) | byte2) as usize
-            }
+                ensure!(data.len() > width, "Truncated length");
 
-            (1, 0) => {
-                next_byte_idx += 4;
+                let len = data[1..=width]
+                    .iter()
+                    .fold(0u64, |len, byte| (len << 8) | *byte as u64);
 
-                u32::from_be_bytes([
-                    data[next_byte_idx],
-                    data[next_byte_idx + 1],
-                    data[next_byte_idx + 2],
-                    data[next_byte_idx + 3],
-                ]) as usize
+                Ok((len as usize, width + 1))
             }
 
-            // handle (0, 0) and (1, 1)
-            _ => (first_byte & 0b00111111) as usize,
-        };
-
-        Ok((len, next_byte_idx))
+            _ => bail!("Expected a length, found special encoding {first_byte:#04x}"),
+        }
     }
 
-    fn decode_string(&self, data: &[u8]) -> anyhow::Result<(String, usize)> {
-        let (len, next_byte_idx) = self.decode_length(data)?;
+    fn decode_string(&self, data: &[u8]) -> anyhow::Result<(Bytes, usize)> {
+        let first_byte = *data.first().with_context(|| "Missing string")?;
 
-        let mut last_idx = next_byte_idx;
+        // the two high bits set mark an integer or compressed string instead of a length
+        if first_byte >> 6 != 0b11 {
+            let (len, next_byte_idx) = self.decode_length(data)?;
 
-        match len {
-            // 8 bit unsigned integer as string
-            0 => {
-                let num = u8::from_le_bytes([data[next_byte_idx]]);
+            let last_idx = next_byte_idx + len;
+
+            ensure!(last_idx <= data.len(), "Invalid length");
 
-                last_idx += 1;
+            return Ok((
+                Bytes::copy_from_slice(&data[next_byte_idx..last_idx]),
+                last_idx,
+            ));
+        }
 
-                Ok((num.to_string(), last_idx))
+        match first_byte & 0x3F {
+            // 8 bit signed integer as string
+            0 => {
+                ensure!(data.len() >= 2, "Truncated integer");
+
+                Ok((Bytes::from((data[1] as i8).to_string()), 2))
             }
 
-            // 16 bit unsigned integer as string
+            // 16 bit signed integer as string
             1 => {
-                let num = u16::from_le_bytes([data[next_byte_idx], data[next_byte_idx + 1]]);
+                ensure!(data.len() >= 3, "Truncated integer");
 
-                last_idx += 2;
+                let num = i16::from_le_bytes([data[1], data[2]]);
 
-                Ok((num.to_string(), last_idx))
+                Ok((Bytes::from(num.to_string()), 3))
             }
 
-            // 32 bit unsigned integer as string
+            // 32 bit signed integer as string
             2 => {
-                let num = u32::from_le_bytes([
-                    data[next_byte_idx],
-                    data[next_byte_idx + 1],
-                    data[next_byte_idx + 2],
-                    data[next_byte_idx + 3],
-                ]);
+                ensure!(data.len() >= 5, "Truncated integer");
 
-                last_idx += 4;
+                let num = i32::from_le_bytes([data[1], data[2], data[3], data[4]]);
 
-                Ok((num.to_string(), last_idx))
+                Ok((Bytes::from(num.to_string()), 5))
             }
 
-            // Compressed String
-            3 => todo!(),
+            // LZF compressed string
+            3 => {
+                let mut idx = 1;
+
+                let (compressed_len, next_idx) = self.decode_length(&data[idx..])?;
+                idx += next_idx;
+
+                let (len, next_idx) = self.decode_length(&data[idx..])?;
+                idx += next_idx;
 
-            // Length Prefixed String
-            _ => {
-                last_idx += len;
+                ensure!(
+                    idx.checked_add(compressed_len)
+                        .is_some_and(|end| end <= data.len()),
+                    "Invalid compressed length"
+                );
 
-                ensure!(last_idx <= data.len(), "Invalid length");
+                let value = lzf_decompress(&data[idx..idx + compressed_len], len)?;
 
-                Ok((
-                    String::from_utf8(data[next_byte_idx..last_idx].to_vec())?,
-                    last_idx,
-                ))
+                Ok((Bytes::from(value), idx + compressed_len))
             }
+
+            encoding => bail!("Unknown string encoding {encoding}"),
         }
     }
 
+    /// The expiration a key may be preceded by, in seconds after `0xFD` or in milliseconds
+    /// after `0xFC`, both little endian, and the bytes it took.
     fn decode_expiration_time(
         &self,
         data: &[u8],
     ) -> anyhow::Result<Option<(DateTime<Utc>, usize)>> {
-        let first_byte = data[0];
-
-        let mut next_byte_idx = 1;
+        match data.first() {
+            Some(0xFD) => {
+                let seconds = data.get(1..5).context("Truncated expiration time")?;
 
-        let experation_duration = match first_byte {
-            // Timestampe in secends
-            0xFD => {
-                let unix_time = u32::from_be_bytes([
-                    data[next_byte_idx + 3],
-                    data[next_byte_idx + 2],
-                    data[next_byte_idx + 1],
-                    data[next_byte_idx],
-                ]);
+                let seconds = u32::from_le_bytes(seconds.try_into()?);
 
-                next_byte_idx += 4;
+                let expiration_time = DateTime::from_timestamp(seconds as i64, 0)
+                    .context("Invalid expiration time")?;
 
-                let expiration_time =
-                    DateTime::from_timestamp(unix_time as i64, 0).expect("Invalid timestamp");
-
-                Some((expiration_time, next_byte_idx))
+                Ok(Some((expiration_time, 5)))
             }
 
-            // Timestampe in miliseconds
-            0xFC => {
-                let unix_time = u64::from_be_bytes([
-                    data[next_byte_idx + 7],
-                    data[next_byte_idx + 6],
-                    data[next_byte_idx + 5],
-                    data[next_byte_idx + 4],
-                    data[next_byte_idx + 3],
-                    data[next_byte_idx + 2],
-                    data[next_byte_idx + 1],
-                    data[next_byte_idx],
-                ]);
-
-                next_byte_idx += 8;
+            Some(0xFC) => {
+                let millis = decode_millis(&data[1..])?;
 
                 let expiration_time =
-                    DateTime::from_timestamp_millis(unix_time as i64).expect("Invalid timestamp");
+                    DateTime::from_timestamp_millis(millis).context("Invalid expiration time")?;
 
-                Some((expiration_time, next_byte_idx))
+                Ok(Some((expiration_time, 9)))
             }
 
-            _ => None,
-        };
-
-        Ok(experation_duration)
+            _ => Ok(None),
+        }
     }
 
     fn decode_key_value(
         &self,
         data: &[u8],
         key_type: &KeyType,
-    ) -> anyhow::Result<(Vec<u8>, usize)> {
+    ) -> anyhow::Result<(RedisValue, usize)> {
         match key_type {
             KeyType::String => {
                 let (key, next_idx) = self
                     .decode_string(data)
                     .with_context(|| "Could not parse key value for Type String")?;
 
-                Ok((key.as_bytes().to_vec(), next_idx))
+                Ok((RedisValue::String(key), next_idx))
+            }
+
+            KeyType::List => {
+                let (len, mut idx) = self.decode_length(data)?;
+
+                let mut list = VecDeque::with_capacity(len.min(MAX_PREALLOCATION));
+
+                for _ in 0..len {
+                    let (element, next_idx) = self.decode_string(&data[idx..])?;
+
+                    list.push_back(element);
+
+                    idx += next_idx;
+                }
+
+                Ok((RedisValue::List(list), idx))
             }
 
-            KeyType::List => todo!(),
+            KeyType::Set => {
+                let (len, mut idx) = self.decode_length(data)?;
 
-            KeyType::Set => todo!(),
+                let mut set = Set::default();
 
-            KeyType::SortedSet => todo!(),
+                for _ in 0..len {
+                    let (member, next_idx) = self.decode_string(&data[idx..])?;
 
-            KeyType::Hash => todo!(),
+                    set.insert(member);
 
-            KeyType::Zipmap => todo!(),
+                    idx += next_idx;
+                }
 
-            KeyType::Ziplist => todo!(),
+                Ok((RedisValue::Set(set), idx))
+            }
+
+            // members each followed by their score as text
+            KeyType::SortedSet => {
+                let (len, mut idx) = self.decode_length(data)?;
+
+                let mut zset = SortedSet::default();
+
+                for _ in 0..len {
+                    let (member, next_idx) = self.decode_string(&data[idx..])?;
+
+                    idx += next_idx;
+
+                    let (score, next_idx) = decode_text_score(&data[idx..])?;
+
+                    idx += next_idx;
+
+                    zset.insert(member, checked_score(score)?);
+                }
+
+                Ok((RedisValue::ZSet(zset), idx))
+            }
+
+            KeyType::Hash => {
+                let (len, mut idx) = self.decode_length(data)?;
+
+                let mut hash = Hash::default();
+
+                for _ in 0..len {
+                    let (field, next_idx) = self.decode_string(&data[idx..])?;
+
+                    idx += next_idx;
+
+                    let (value, next_idx) = self.decode_string(&data[idx..])?;
+
+                    idx += next_idx;
+
+                    hash.insert(field, value);
+                }
+
+                Ok((RedisValue::Hash(hash), idx))
+            }
 
-            KeyType::Intset => todo!(),
+            // members each followed by their score as a little endian double
+            KeyType::SortedSet2 => {
+                let (len, mut idx) = self.decode_length(data)?;
 
-            KeyType::ZHashMap => todo!(),
+                let mut zset = SortedSet::default();
 
-            KeyType::ZSortedSet => todo!(),
+                for _ in 0..len {
+                    let (member, next_idx) = self.decode_string(&data[idx..])?;
+
+                    idx += next_idx;
+
+                    let score = data
+                        .get(idx..idx + 8)
+                        .with_context(|| "Truncated sorted set score")?;
+
+                    idx += 8;
+
+                    zset.insert(
+                        member,
+                        checked_score(f64::from_le_bytes(score.try_into()?))?,
+                    );
+                }
+
+                Ok((RedisValue::ZSet(zset), idx))
+            }
+
+            KeyType::Zipmap => {
+                let (zipmap, next_idx) = self.decode_string(data)?;
+
+                let hash = zipmap_entries(&zipmap)?.into_iter().collect();
+
+                Ok((RedisValue::Hash(hash), next_idx))
+            }
+
+            KeyType::Ziplist => {
+                let (ziplist, next_idx) = self.decode_string(data)?;
+
+                let list = ziplist_entries(&ziplist)?;
+
+                Ok((RedisValue::List(list.into()), next_idx))
+            }
+
+            KeyType::Intset => {
+                let (intset, next_idx) = self.decode_string(data)?;
+
+                let set = intset_entries(&intset)?.into_iter().collect();
+
+                Ok((RedisValue::Set(set), next_idx))
+            }
 
-            KeyType::ListQuickList => todo!(),
+            KeyType::SetListpack => {
+                let (listpack, next_idx) = self.decode_string(data)?;
+
+                let set = listpack_entries(&listpack)?.into_iter().collect();
+
+                Ok((RedisValue::Set(set), next_idx))
+            }
+
+            // fields and values taking turns in a ziplist
+            KeyType::ZHashMap => {
+                let (ziplist, next_idx) = self.decode_string(data)?;
+
+                let hash = field_value_pairs(ziplist_entries(&ziplist)?)?;
+
+                Ok((RedisValue::Hash(hash), next_idx))
+            }
+
+            KeyType::HashListpack => {
+                let (listpack, next_idx) = self.decode_string(data)?;
+
+                let hash = field_value_pairs(listpack_entries(&listpack)?)?;
+
+                Ok((RedisValue::Hash(hash), next_idx))
+            }
+
+            // members and scores taking turns in a ziplist
+            KeyType::ZSortedSet => {
+                let (ziplist, next_idx) = self.decode_string(data)?;
+
+                let zset = member_score_pairs(ziplist_entries(&ziplist)?)?;
+
+                Ok((RedisValue::ZSet(zset), next_idx))
+            }
+
+            KeyType::ZSetListpack => {
+                let (listpack, next_idx) = self.decode_string(data)?;
+
+                let zset = member_score_pairs(listpack_entries(&listpack)?)?;
+
+                Ok((RedisValue::ZSet(zset), next_idx))
+            }
+
+            // the earliest field expiration, then every field and value preceded by its
+            // expiration relative to that one: 0 when it has none, otherwise one more than the
+            // difference
+            KeyType::HashMetadata => {
+                let min_expire = decode_millis(data)?;
+
+                let (len, next_idx) = self.decode_length(&data[8..])?;
+
+                let mut idx = 8 + next_idx;
+
+                let now = self.clock.now();
+
+                let mut hash = Hash::default();
+
+                for _ in 0..len {
+                    let (ttl, next_idx) = self.decode_length(&data[idx..])?;
+
+                    idx += next_idx;
+
+                    let (field, next_idx) = self.decode_string(&data[idx..])?;
+
+                    idx += next_idx;
+
+                    let (value, next_idx) = self.decode_string(&data[idx..])?;
+
+                    idx += next_idx;
+
+                    let expires_at = match ttl {
+                        0 => None,
+
+                        ttl => {
+                            let ms = i64::try_from(ttl - 1)
+                                .ok()
+                                .and_then(|delta| min_expire.checked_add(delta))
+                                .with_context(|| "Invalid hash field expiration")?;
+
+                            Some(field_expiration(ms)?)
+                        }
+                    };
+
+                    load_field(&mut hash, field, value, expires_at, now);
+                }
+
+                Ok((RedisValue::Hash(hash), idx))
+            }
+
+            // the earliest field expiration, then a listpack of field, value and expiration
+            // triples, with 0 for the fields that do not expire
+            KeyType::HashListpackEx => {
+                decode_millis(data)?;
+
+                let (listpack, next_idx) = self.decode_string(&data[8..])?;
+
+                let entries = listpack_entries(&listpack)?;
+
+                ensure!(
+                    entries.len().is_multiple_of(3),
+                    "Hash field without a value or an expiration"
+                );
+
+                let now = self.clock.now();
+
+                let mut hash = Hash::default();
+
+                for triple in entries.chunks(3) {
+                    let ttl = std::str::from_utf8(&triple[2])
+                        .ok()
+                        .and_then(|ttl| ttl.parse::<i64>().ok())
+                        .with_context(|| "Invalid hash field expiration")?;
+
+                    let expires_at = match ttl {
+                        0 => None,
+
+                        ttl => Some(field_expiration(ttl)?),
+                    };
+
+                    load_field(
+                        &mut hash,
+                        triple[0].clone(),
+                        triple[1].clone(),
+                        expires_at,
+                        now,
+                    );
+                }
+
+                Ok((RedisValue::Hash(hash), 8 + next_idx))
+            }
+
+            // a list of ziplists
+            KeyType::ListQuickList => {
+                let (nodes, mut idx) = self.decode_length(data)?;
+
+                let mut list = VecDeque::new();
+
+                for _ in 0..nodes {
+                    let (ziplist, next_idx) = self.decode_string(&data[idx..])?;
+
+                    list.extend(ziplist_entries(&ziplist)?);
+
+                    idx += next_idx;
+                }
+
+                Ok((RedisValue::List(list), idx))
+            }
+
+            // a list of nodes that are either a listpack or a single large element
+            KeyType::ListQuickList2 => {
+                let (nodes, mut idx) = self.decode_length(data)?;
+
+                let mut list = VecDeque::new();
+
+                for _ in 0..nodes {
+                    let (container, next_idx) = self.decode_length(&data[idx..])?;
+
+                    idx += next_idx;
+
+                    let (node, next_idx) = self.decode_string(&data[idx..])?;
+
+                    idx += next_idx;
+
+                    match container {
+                        QUICKLIST_NODE_PLAIN => list.push_back(node),
+
+                        QUICKLIST_NODE_PACKED => list.extend(listpack_entries(&node)?),
+
+                        _ => bail!("Unknown quicklist container {container}"),
+                    }
+                }
+
+                Ok((RedisValue::List(list), idx))
+            }
         }
     }
 
-    fn decode_key(
-        &self,
-        data: &[u8],
-    ) -> anyhow::Result<(String, Vec<u8>, KeyType, Option<DateTime<Utc>>, usize)> {
+    fn decode_key(&self, data: &[u8]) -> anyhow::Result<DecodedKey> {
         let mut current_idx = 0usize;
 
         let expiration_time = self
@@ -344,7 +658,11 @@ impl RDB {
             None => None,
         };
 
-        let key_type = KeyType::from(data[current_idx]);
+        let key_type = KeyType::try_from(
+            *data
+                .get(current_idx)
+                .with_context(|| "Missing value type")?,
+        )?;
 
         current_idx += 1;
 
@@ -369,124 +687,433 @@ impl RDB {
         let mut databases = HashMap::<u32, Arc<Database>>::new();
 
         let mut selected_db: u32 = 0;
-        let mut db_hashmap_size: u32;
-        let mut expiration_hashmap_size: u32;
 
         loop {
-            let op_code = OperationCode::try_from(&data[current_idx]);
+            let byte = data
+                .get(current_idx)
+                .with_context(|| "Unexpected end of rdb file")?;
+
+            // anything that is not an operation code starts a key, possibly with its expiration
+            let Ok(code) = OperationCode::try_from(byte) else {
+                let (name, value, _, expiration, next_idx) = self
+                    .decode_key(&data[current_idx..])
+                    .with_context(|| format!("Could not parse key in database {selected_db}"))?;
+
+                current_idx += next_idx;
+
+                if expiration.is_some_and(|exp| exp < self.clock.now()) {
+                    continue;
+                }
+
+                // a hash whose fields all expired
+                if matches!(&value, RedisValue::Hash(hash) if hash.is_empty()) {
+                    continue;
+                }
+
+                databases
+                    .entry(selected_db)
+                    .or_insert_with(|| Arc::new(Database::new(selected_db, self.clock.clone())))
+                    .insert(name, value, expiration)
+                    .await;
+
+                continue;
+            };
 
             current_idx += 1;
 
-            match &op_code {
-                Ok(code) => match code {
-                    OperationCode::Aux => {
-                        let (key_string, key_next_idx) =
-                            self.decode_string(&data[current_idx..]).with_context(|| {
-                                format!("Could not parse header string in {code} section")
-                            })?;
+            match code {
+                OperationCode::Aux => {
+                    let (key_string, key_next_idx) =
+                        self.decode_string(&data[current_idx..]).with_context(|| {
+                            format!("Could not parse header string in {code} section")
+                        })?;
 
-                        current_idx += key_next_idx;
+                    current_idx += key_next_idx;
 
-                        let (value_string, value_next_idx) =
-                            self.decode_string(&data[current_idx..]).with_context(|| {
-                                format!("Could not parse header string in {code} section")
-                            })?;
+                    let (value_string, value_next_idx) =
+                        self.decode_string(&data[current_idx..]).with_context(|| {
+                            format!("Could not parse header string in {code} section")
+                        })?;
 
-                        headers.insert(key_string, value_string);
+                    headers.insert(
+                        String::from_utf8_lossy(&key_string).into_owned(),
+                        String::from_utf8_lossy(&value_string).into_owned(),
+                    );
 
-                        current_idx += value_next_idx;
-                    }
+                    current_idx += value_next_idx;
+                }
 
-                    OperationCode::SelectDb => {
-                        let (value, next_idx) =
-                            self.decode_length(&data[current_idx..]).with_context(|| {
-                                format!("Could not parse header string in {code} section")
-                            })?;
+                OperationCode::SelectDb => {
+                    let (value, next_idx) = self
+                        .decode_length(&data[current_idx..])
+                        .with_context(|| format!("Could not parse value in {code} section"))?;
 
-                        current_idx += next_idx;
+                    current_idx += next_idx;
 
-                        selected_db = value as u32;
+                    selected_db = value as u32;
 
-                        databases.insert(selected_db, Arc::new(Database::new(selected_db)));
-                    }
+                    databases.entry(selected_db).or_insert_with(|| {
+                        Arc::new(Database::new(selected_db, self.clock.clone()))
+                    });
+                }
 
-                    OperationCode::ResizeDb => {
-                        let (db_size, next_idx) = self
-                            .decode_length(&data[current_idx..])
-                            .with_context(|| format!("Could not parse value in {code} section"))?;
+                OperationCode::ResizeDb => {
+                    let (db_size, next_idx) = self
+                        .decode_length(&data[current_idx..])
+                        .with_context(|| format!("Could not parse value in {code} section"))?;
 
-                        db_hashmap_size = db_size as u32;
+                    current_idx += next_idx;
 
-                        current_idx += next_idx;
+                    let (expiration_size, next_idx) = self
+                        .decode_length(&data[current_idx..])
+                        .with_context(|| format!("Could not parse value in {code} section"))?;
 
-                        let (expiration_size, next_idx) = self
-                            .decode_length(&data[current_idx..])
-                            .with_context(|| format!("Could not parse value in {code} section"))?;
+                    current_idx += next_idx;
 
-                        current_idx += next_idx;
+                    println!("db_size: {db_size}, expiration_size: {expiration_size}");
+                }
 
-                        expiration_hashmap_size = expiration_size as u32;
+                OperationCode::Eof => {
+                    break;
+                }
+            }
+        }
 
-                        println!("db_size: {db_hashmap_size}, expiration_size: {expiration_hashmap_size}");
+        Ok(databases)
+    }
 
-                        loop {
-                            let (name, value, _, expiration, next_idx) =
-                                self.decode_key(&data[current_idx..]).with_context(|| {
-                                    format!("Could not parse key in {code} section")
-                                })?;
+    /// Serializes a snapshot, one `SELECTDB` section per non-empty database.
+    fn encode(&self, snapshot: &Snapshot) -> anyhow::Result<Vec<u8>> {
+        let mut out = Vec::new();
 
-                            current_idx += next_idx;
+        out.extend_from_slice(RDB_MAGIC);
 
-                            let mut should_insert = true;
+        let ctime = self.clock.now().timestamp().to_string();
 
-                            if let Some(exp) = expiration {
-                                if exp < Utc::now() {
-                                    should_insert = false;
-                                }
-                            }
+        for (key, value) in [
+            ("redis-ver", REDIS_VERSION),
+            ("redis-bits", "64"),
+            ("ctime", ctime.as_str()),
+        ] {
+            out.push(0xFA);
+            encode_string(&mut out, key.as_bytes());
+            encode_string(&mut out, value.as_bytes());
+        }
 
-                            if should_insert {
-                                databases
-                                    .get_mut(&selected_db)
-                                    .with_context(|| {
-                                        format!("Could not find database {selected_db}")
-                                    })?
-                                    .insert(name, String::from_utf8(value)?, expiration)
-                                    .await;
+        for (db, entries) in snapshot {
+            if entries.is_empty() {
+                continue;
+            }
+
+            out.push(0xFE);
+            encode_length(&mut out, *db as usize);
+
+            let expires = entries
+                .iter()
+                .filter(|(_, record)| record.expires_at.is_some())
+                .count();
+
+            out.push(0xFB);
+            encode_length(&mut out, entries.len());
+            encode_length(&mut out, expires);
+
+            for (key, record) in entries {
+                if let Some(expires_at) = record.expires_at {
+                    out.push(0xFC);
+                    out.extend_from_slice(&expires_at.timestamp_millis().to_le_bytes());
+                }
+
+                match &record.value {
+                    RedisValue::String(value) => {
+                        out.push(0x00);
+                        encode_string(&mut out, key);
+                        encode_string(&mut out, value);
+                    }
+
+                    RedisValue::List(list) => {
+                        out.push(0x01);
+                        encode_string(&mut out, key);
+                        encode_length(&mut out, list.len());
+
+                        for element in list {
+                            encode_string(&mut out, element);
+                        }
+                    }
+
+                    RedisValue::Set(set) => {
+                        out.push(0x02);
+                        encode_string(&mut out, key);
+                        encode_length(&mut out, set.len());
+
+                        for member in set.iter() {
+                            encode_string(&mut out, member);
+                        }
+                    }
+
+                    RedisValue::Hash(hash) => match hash.next_expiration() {
+                        None => {
+                            out.push(0x04);
+                            encode_string(&mut out, key);
+                            encode_length(&mut out, hash.len());
+
+                            for (field, value) in hash.iter() {
+                                encode_string(&mut out, field);
+                                encode_string(&mut out, value);
                             }
+                        }
+
+                        Some(min_expire) => {
+                            let min_expire = min_expire.timestamp_millis();
+
+                            out.push(0x18);
+                            encode_string(&mut out, key);
+                            out.extend_from_slice(&min_expire.to_le_bytes());
+                            encode_length(&mut out, hash.len());
 
-                            if OperationCode::try_from(&data[current_idx]).is_ok() {
-                                break;
-                            } else {
-                                continue;
+                            for (field, value) in hash.iter() {
+                                let ttl = hash.expires_at(field).map_or(0, |expires_at| {
+                                    expires_at.timestamp_millis() - min_expire + 1
+                                });
+
+                                encode_length(&mut out, ttl as usize);
+                                encode_string(&mut out, field);
+                                encode_string(&mut out, value);
                             }
                         }
-                    }
+                    },
+
+                    RedisValue::ZSet(zset) => {
+                        out.push(0x05);
+                        encode_string(&mut out, key);
+                        encode_length(&mut out, zset.len());
 
-                    OperationCode::Eof => {
-                        break;
+                        for (member, score) in zset.iter() {
+                            encode_string(&mut out, member);
+                            out.extend_from_slice(&score.to_le_bytes());
+                        }
                     }
-                },
 
-                Err(err) => bail!("Invalid operation code: {err}"),
+                    value => bail!("{} values can not be saved yet", value.type_name()),
+                }
             }
         }
 
-        Ok(databases)
+        out.push(0xFF);
+
+        // a zero checksum tells readers that checksumming was disabled
+        out.extend_from_slice(&[0u8; 8]);
+
+        Ok(out)
+    }
+}
+
+/// Pairs up the flat field, value, field, value... entries of a compact hash encoding.
+fn field_value_pairs(entries: Vec<Bytes>) -> anyhow::Result<Hash> {
+    ensure!(
+        entries.len().is_multiple_of(2),
+        "Hash field without a value"
+    );
+
+    let mut entries = entries.into_iter();
+
+    let mut hash = Hash::default();
+
+    while let (Some(field), Some(value)) = (entries.next(), entries.next()) {
+        hash.insert(field, value);
+    }
+
+    Ok(hash)
+}
+
+/// Pairs up the flat member, score, member, score... entries of a compact sorted set
+/// encoding, where scores are stored as text.
+fn member_score_pairs(entries: Vec<Bytes>) -> anyhow::Result<SortedSet> {
+    ensure!(
+        entries.len().is_multiple_of(2),
+        "Sorted set member without a score"
+    );
+
+    let mut entries = entries.into_iter();
+
+    let mut zset = SortedSet::default();
+
+    while let (Some(member), Some(score)) = (entries.next(), entries.next()) {
+        zset.insert(member, checked_score(parse_score(&score)?)?);
+    }
+
+    Ok(zset)
+}
+
+/// A score stored as text after a one byte length, which is 253 for NaN, 254 for infinity
+/// and 255 for negative infinity instead.
+fn decode_text_score(data: &[u8]) -> anyhow::Result<(f64, usize)> {
+    let len = *data.first().with_context(|| "Missing sorted set score")?;
+
+    match len {
+        253 => Ok((f64::NAN, 1)),
+
+        254 => Ok((f64::INFINITY, 1)),
+
+        255 => Ok((f64::NEG_INFINITY, 1)),
+
+        len => {
+            let len = len as usize;
+
+            let text = data
+                .get(1..1 + len)
+                .with_context(|| "Truncated sorted set score")?;
+
+            Ok((parse_score(text)?, 1 + len))
+        }
+    }
+}
+
+fn parse_score(text: &[u8]) -> anyhow::Result<f64> {
+    std::str::from_utf8(text)
+        .ok()
+        .and_then(|text| text.parse().ok())
+        .with_context(|| "Invalid sorted set score")
+}
+
+/// Sorted sets can not hold NaN scores, so a file with one is corrupt.
+fn checked_score(score: f64) -> anyhow::Result<f64> {
+    ensure!(!score.is_nan(), "Sorted set with a NaN score");
+
+    Ok(score)
+}
+
+/// The little endian Unix time in milliseconds at the start of `data`.
+fn decode_millis(data: &[u8]) -> anyhow::Result<i64> {
+    let bytes = data.get(..8).with_context(|| "Truncated time")?;
+
+    Ok(i64::from_le_bytes(bytes.try_into()?))
+}
+
+fn field_expiration(ms: i64) -> anyhow::Result<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(ms).with_context(|| "Invalid hash field expiration")
+}
+
+/// Adds a field read from a hash with field expirations, unless it already expired.
+fn load_field(
+    hash: &mut Hash,
+    field: Bytes,
+    value: Bytes,
+    expires_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) {
+    if expires_at.is_some_and(|expires_at| expires_at <= now) {
+        return;
+    }
+
+    hash.insert(field.clone(), value);
+    hash.set_expires_at(&field, expires_at);
+}
+
+fn encode_length(out: &mut Vec<u8>, len: usize) {
+    if len < 1 << 6 {
+        out.push(len as u8);
+    } else if len < 1 << 14 {
+        out.push(0x40 | (len >> 8) as u8);
+        out.push(len as u8);
+    } else if len <= u32::MAX as usize {
+        out.push(0x80);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    } else {
+        out.push(0x81);
+        out.extend_from_slice(&(len as u64).to_be_bytes());
     }
 }
 
+fn encode_string(out: &mut Vec<u8>, value: &[u8]) {
+    encode_length(out, value.len());
+    out.extend_from_slice(value);
+}
+
+/// Expands an LZF block the way `lzf_decompress` in Redis does.
+fn lzf_decompress(input: &[u8], len: usize) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(len.min(MAX_PREALLOCATION));
+
+    let mut idx = 0;
+
+    while idx < input.len() {
+        let ctrl = input[idx] as usize;
+        idx += 1;
+
+        if ctrl < 1 << 5 {
+            // a run of ctrl + 1 literal bytes
+            let end = idx + ctrl + 1;
+
+            ensure!(end <= input.len(), "Truncated LZF literal");
+
+            out.extend_from_slice(&input[idx..end]);
+            idx = end;
+        } else {
+            // a back reference, with the length in the top 3 bits
+            let mut ref_len = ctrl >> 5;
+
+            if ref_len == 7 {
+                ref_len += *input.get(idx).with_context(|| "Truncated LZF reference")? as usize;
+                idx += 1;
+            }
+
+            let low = *input.get(idx).with_context(|| "Truncated LZF reference")? as usize;
+            idx += 1;
+
+            let offset = ((ctrl & 0x1F) << 8) + low + 1;
+
+            ensure!(offset <= out.len(), "Invalid LZF back reference");
+
+            let start = out.len() - offset;
+
+            // byte by byte, since the reference can overlap the bytes it produces
+            for i in 0..ref_len + 2 {
+                out.push(out[start + i]);
+            }
+        }
+    }
+
+    ensure!(out.len() == len, "LZF data does not match its length");
+
+    Ok(out)
+}
+
 impl Persistent for RDB {
-    fn save(&self) -> anyhow::Result<()> {
-        todo!()
+    fn save(&mut self, snapshot: &Snapshot) -> anyhow::Result<()> {
+        let data = self.encode(snapshot)?;
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| "Could not create rdb directory")?;
+        }
+
+        // written next to the target and renamed, so a crash never leaves a partial file
+        let tmp_path = self.path.with_extension("tmp");
+
+        fs::write(&tmp_path, data).with_context(|| "Could not write rdb file")?;
+
+        fs::rename(&tmp_path, &self.path).with_context(|| "Could not replace rdb file")?;
+
+        Ok(())
+    }
+
+    fn dump(&self, snapshot: &Snapshot) -> anyhow::Result<Vec<u8>> {
+        self.encode(snapshot)
+    }
+
+    fn restore(&self, data: &[u8]) -> anyhow::Result<HashMap<u32, Arc<Database>>> {
+        ensure!(
+            data.len() >= RDB_MAGIC.len() && data.starts_with(b"REDIS"),
+            "Invalid rdb file"
+        );
+
+        futures::executor::block_on(self.parse_file(&data[RDB_MAGIC.len()..]))
     }
 
     fn load(&mut self) -> anyhow::Result<HashMap<u32, Arc<Database>>> {
         let mut data = Vec::new();
 
         let bytes_read = {
-            match self.reader {
-                Some(ref mut reader) => reader
+            match self.reader.take() {
+                Some(mut reader) => reader
                     .read_to_end(&mut data)
                     .with_context(|| "Could not read rdb file")?,
 
@@ -495,21 +1122,218 @@ impl Persistent for RDB {
         };
 
         if bytes_read == 0 {
-            let mut hashmap = HashMap::new();
+            return Ok(HashMap::new());
+        }
 
-            hashmap.insert(0, Arc::new(Database::new(0)));
+        self.restore(&data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::database::value::Record;
+
+    const NOW_MS: i64 = 1_700_000_000_000;
 
-            return Ok(hashmap);
+    fn rdb() -> RDB {
+        RDB {
+            path: PathBuf::new(),
+            reader: None,
+            clock: Arc::new(ManualClock::new(at(NOW_MS))),
         }
+    }
 
-        let magic_string = String::from_utf8(data[0..9].to_vec())?;
+    fn at(ms: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(ms).unwrap()
+    }
 
-        ensure!(&magic_string[0..5] == "REDIS", "Invalid rdb file");
+    #[test]
+    fn expiration_times_decode() {
+        let mut millis = vec![0xFC];
+        millis.extend(NOW_MS.to_le_bytes());
+
+        assert_eq!(
+            rdb().decode_expiration_time(&millis).unwrap(),
+            Some((at(NOW_MS), 9))
+        );
+        assert_eq!(
+            rdb()
+                .decode_expiration_time(&[0xFD, 0x10, 0x27, 0, 0, 0x00])
+                .unwrap(),
+            Some((at(10_000_000), 5))
+        );
+        assert_eq!(rdb().decode_expiration_time(&[0x00]).unwrap(), None);
+        assert_eq!(rdb().decode_expiration_time(&[]).unwrap(), None);
+    }
 
-        let future = self.parse_file(&data[9..]);
+    #[test]
+    fn bad_expiration_times_are_errors() {
+        let mut out_of_range = vec![0xFC];
+        out_of_range.extend(i64::MAX.to_le_bytes());
 
-        let databases = futures::executor::block_on(future)?;
+        assert!(rdb().decode_expiration_time(&out_of_range).is_err());
+        assert!(rdb().decode_expiration_time(&[0xFC, 1, 2, 3]).is_err());
+        assert!(rdb().decode_expiration_time(&[0xFD, 1, 2]).is_err());
+    }
 
-        Ok(databases)
+    #[test]
+    fn lengths_decode() {
+        let rdb = rdb();
+
+        assert_eq!(rdb.decode_length(&[0x05]).unwrap(), (5, 1));
+        assert_eq!(rdb.decode_length(&[0x41, 0x02]).unwrap(), (258, 2));
+        assert_eq!(rdb.decode_length(&[0x80, 0, 1, 0, 0]).unwrap(), (65536, 5));
+        assert_eq!(
+            rdb.decode_length(&[0x81, 0, 0, 0, 1, 0, 0, 0, 0]).unwrap(),
+            (1 << 32, 9)
+        );
+
+        assert!(rdb.decode_length(&[]).is_err());
+        assert!(rdb.decode_length(&[0x41]).is_err());
+        assert!(rdb.decode_length(&[0x80, 1]).is_err());
+        assert!(rdb.decode_length(&[0xC0]).is_err());
+    }
+
+    #[test]
+    fn strings_decode() {
+        let rdb = rdb();
+
+        let decode = |data: &[u8]| rdb.decode_string(data).unwrap();
+
+        assert_eq!(decode(&[3, b'a', b'b', b'c', 9]), (Bytes::from("abc"), 4));
+        assert_eq!(decode(&[0xC0, 0xFF]), (Bytes::from("-1"), 2));
+        assert_eq!(decode(&[0xC1, 0x39, 0x30]), (Bytes::from("12345"), 3));
+        assert_eq!(
+            decode(&[0xC2, 0x00, 0x00, 0x00, 0x80]),
+            (Bytes::from(i32::MIN.to_string()), 5)
+        );
+
+        // "abc" as literals, then a reference copying 6 bytes from 3 bytes back
+        let lzf = [0xC3, 6, 9, 0x02, b'a', b'b', b'c', 0x80, 0x02];
+
+        assert_eq!(decode(&lzf), (Bytes::from("abcabcabc"), 9));
+    }
+
+    #[test]
+    fn bad_strings_are_errors() {
+        let rdb = rdb();
+
+        assert!(rdb.decode_string(&[5, b'a']).is_err());
+        assert!(rdb.decode_string(&[0xC1, 0x39]).is_err());
+        assert!(rdb.decode_string(&[0xC4]).is_err());
+        assert!(rdb
+            .decode_string(&[0xC3, 6, 8, 0x02, b'a', b'b', b'c', 0x80, 0x02])
+            .is_err());
+        assert!(rdb
+            .decode_string(&[0xC3, 6, 9, 0x02, b'a', b'b', b'c', 0x80, 0x05])
+            .is_err());
+        assert!(rdb.decode_string(&[0xC3, 9, 9, 0x02, b'a']).is_err());
+    }
+
+    fn snapshot() -> Snapshot {
+        let mut hash = Hash::default();
+        hash.insert(Bytes::from("f1"), Bytes::from("v1"));
+        hash.insert(Bytes::from("f2"), Bytes::from("v2"));
+        hash.set_expires_at(b"f2", Some(at(NOW_MS + 5_000)));
+
+        let mut set = Set::default();
+        set.insert(Bytes::from("m"));
+
+        let mut zset = SortedSet::default();
+        zset.insert(Bytes::from("a"), 1.5);
+        zset.insert(Bytes::from("b"), f64::NEG_INFINITY);
+
+        let string = RedisValue::String(Bytes::from("value"));
+
+        vec![
+            (
+                0,
+                vec![
+                    (
+                        Bytes::from("string"),
+                        Record::new(string.clone(), Some(at(NOW_MS + 1_000))),
+                    ),
+                    (
+                        Bytes::from("expired"),
+                        Record::new(string, Some(at(NOW_MS - 1_000))),
+                    ),
+                    (
+                        Bytes::from("list"),
+                        Record::new(
+                            RedisValue::List(VecDeque::from([Bytes::from("x"), Bytes::from("y")])),
+                            None,
+                        ),
+                    ),
+                ],
+            ),
+            (
+                3,
+                vec![
+                    (
+                        Bytes::from("hash"),
+                        Record::new(RedisValue::Hash(hash), None),
+                    ),
+                    (Bytes::from("set"), Record::new(RedisValue::Set(set), None)),
+                    (
+                        Bytes::from("zset"),
+                        Record::new(RedisValue::ZSet(zset), None),
+                    ),
+                ],
+            ),
+        ]
+    }
+
+    fn entries(databases: &HashMap<u32, Arc<Database>>, db: u32) -> HashMap<String, Record> {
+        futures::executor::block_on(databases[&db].entries())
+            .into_iter()
+            .map(|(key, record)| (String::from_utf8_lossy(&key).into_owned(), record))
+            .collect()
+    }
+
+    #[test]
+    fn dumped_snapshots_restore() {
+        let rdb = rdb();
+
+        let databases = rdb.restore(&rdb.dump(&snapshot()).unwrap()).unwrap();
+
+        let db0 = entries(&databases, 0);
+
+        assert_eq!(db0.len(), 2);
+        assert_eq!(db0["string"].value.as_string().unwrap(), "value");
+        assert_eq!(db0["string"].expires_at, Some(at(NOW_MS + 1_000)));
+        assert_eq!(
+            db0["list"].value.as_list().unwrap(),
+            &VecDeque::from([Bytes::from("x"), Bytes::from("y")])
+        );
+
+        let db3 = entries(&databases, 3);
+
+        let hash = db3["hash"].value.as_hash().unwrap();
+
+        assert_eq!(hash.get(b"f1"), Some(&Bytes::from("v1")));
+        assert_eq!(hash.expires_at(b"f1"), None);
+        assert_eq!(hash.get(b"f2"), Some(&Bytes::from("v2")));
+        assert_eq!(hash.expires_at(b"f2"), Some(at(NOW_MS + 5_000)));
+
+        assert!(db3["set"].value.as_set().unwrap().contains(b"m"));
+
+        let zset = db3["zset"].value.as_zset().unwrap();
+
+        assert_eq!(zset.score(b"a"), Some(1.5));
+        assert_eq!(zset.score(b"b"), Some(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn truncated_files_are_errors() {
+        let rdb = rdb();
+
+        let data = rdb.dump(&snapshot()).unwrap();
+
+        // everything up to the end of file marker, which the checksum follows
+        for len in 0..data.len() - 8 {
+            assert!(rdb.restore(&data[..len]).is_err(), "prefix of {len} bytes");
+        }
     }
 }
@@ -1,35 +1,54 @@
 use anyhow::Context;
+use bytes::BytesMut;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::io::AsyncReadExt;
-use tokio::net::{TcpListener, TcpStream};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpListener;
 use tokio::sync::{Mutex, RwLock};
-use tokio::time::timeout;
 
+use crate::client::Client;
+use crate::clock::ServerClock;
 use crate::configs::cmd_options::CmdOptions;
 use crate::persistence::rdb::RDB;
 use crate::redis_service::RedisService;
+use crate::resp::{FrameDecoder, ProtocolLimits, RespDataTypes};
 use crate::state::server_state::ServerState;
 
+const READ_BUFFER_SIZE: usize = 4096;
+
 #[derive(Debug)]
 pub struct RedisServer {
     service: Arc<RedisService>,
     state: Arc<RwLock<ServerState>>,
+    limits: ProtocolLimits,
 }
 
 impl RedisServer {
     pub fn new(options: CmdOptions) -> Self {
         let state = ServerState::from(options);
 
-        let rdb = RDB::new(&state.get_rdb_path()).expect("Could not create RDB instance");
+        let clock = Arc::new(ServerClock::default());
+
+        let rdb =
+            RDB::new(&state.get_rdb_path(), clock.clone()).expect("Could not create RDB instance");
+
+        let limits = state.get_protocol_limits();
+
+        let database_count = state.get_databases();
 
         let final_state = Arc::new(RwLock::new(state));
 
-        let service = Arc::new(RedisService::new(final_state.clone(), Box::new(rdb)));
+        let service = Arc::new(RedisService::new(
+            final_state.clone(),
+            Box::new(rdb),
+            database_count,
+            clock,
+        ));
 
         Self {
             service,
             state: final_state,
+            limits,
         }
     }
 
@@ -50,10 +69,14 @@ impl RedisServer {
         });
 
         match result {
-            Ok(connection) => {
+            Ok(link) => {
                 println!("Replica initialized successfully");
 
-                self.handle_connection(connection, false);
+                if let Err(e) = self.service.restore_snapshot(&link.snapshot).await {
+                    eprintln!("Could not load the snapshot sent by master: {e:?}");
+                }
+
+                self.handle_connection(link.reader, link.writer, link.pending, true);
             }
             Err(e) => {
                 eprintln!("Error initializing replica: {e:?}");
@@ -62,13 +85,21 @@ impl RedisServer {
 
         drop(state);
 
+        self.service.start_active_expire();
+
         loop {
             let stream = listener.accept().await;
 
             match stream {
                 Ok((stream, _)) => {
-                    let stream_arc = Arc::new(Mutex::new(stream));
-                    self.handle_connection(stream_arc, true);
+                    let (reader, writer) = stream.into_split();
+
+                    self.handle_connection(
+                        reader,
+                        Arc::new(Mutex::new(writer)),
+                        BytesMut::with_capacity(READ_BUFFER_SIZE),
+                        false,
+                    );
                 }
 
                 Err(e) => {
@@ -78,56 +109,132 @@ impl RedisServer {
         }
     }
 
-    fn handle_connection(&self, stream_arc: Arc<Mutex<TcpStream>>, with_timeout: bool) {
+    /// Serves one connection; `from_master` marks the link a replica keeps to its master,
+    /// which is never answered. Only this task reads, while the write half is shared with
+    /// whoever propagates commands to the connection once it is a replica.
+    fn handle_connection(
+        &self,
+        mut reader: OwnedReadHalf,
+        writer: Arc<Mutex<OwnedWriteHalf>>,
+        mut buffer: BytesMut,
+        from_master: bool,
+    ) {
         let service_clone = self.service.clone();
 
+        let limits = self.limits;
+
         tokio::spawn(async move {
-            println!("accepted new connection");
+            let address = reader
+                .peer_addr()
+                .map_or("unknown address".to_string(), |address| address.to_string());
 
-            let mut buffer = [0u8; 2048];
+            let local_address = reader
+                .local_addr()
+                .map_or("unknown address".to_string(), |address| address.to_string());
 
-            let mut stream_guard = stream_arc.lock().await;
+            let mut client = Client::new(address.clone(), local_address);
 
-            let mut res = stream_guard.read(&mut buffer).await;
+            client.flags.master = from_master;
 
-            drop(stream_guard);
+            service_clone.update_client(&client);
 
-            while let Ok(bytes_read) = res {
-                if bytes_read == 0 {
-                    break;
-                }
+            let killed = client.killed.clone();
 
-                service_clone
-                    .execute_command(&buffer[0..bytes_read], stream_arc.clone())
-                    .await
-                    .unwrap();
+            // bytes handed over with the connection (e.g. commands the master sent right
+            // after the replication snapshot) are served before reading any more
+            let mut pending_input = !buffer.is_empty();
 
-                let mut stream_guard = stream_arc.lock().await;
+            let mut decoder = FrameDecoder::default();
 
-                let address = stream_guard
-                    .peer_addr()
-                    .unwrap_or_else(|_| "unknown address".parse().unwrap());
+            loop {
+                let res = if pending_input {
+                    pending_input = false;
 
-                // set timeout for reading from the stream to not block when the client
-                // is not sending data
-                res = if with_timeout {
-                    match timeout(Duration::from_secs(1), stream_guard.read(&mut buffer)).await {
-                        Ok(Ok(bytes_read)) => Ok(bytes_read),
+                    Ok(buffer.len())
+                } else {
+                    tokio::select! {
+                        res = reader.read_buf(&mut buffer) => res,
 
-                        Ok(Err(e)) => {
-                            eprintln!("error reading from stream: {e:?}");
+                        _ = killed.notified() => {
+                            println!("client {address} was killed");
                             break;
                         }
+                    }
+                };
+
+                match res {
+                    Ok(0) => break,
+
+                    Ok(_) => {}
+
+                    Err(e) => {
+                        eprintln!("error reading from stream: {e:?}");
+                        break;
+                    }
+                }
+
+                if buffer.len() + decoder.partial_len() > limits.max_query_buffer {
+                    eprintln!("closing client {address} that reached max query buffer length");
+                    break;
+                }
+
+                // a single read can carry several pipelined commands, or only part of one,
+                // which the decoder holds on to until the rest arrives
+                let mut close_connection = false;
+
+                loop {
+                    match decoder.decode(&mut buffer, &limits) {
+                        Ok(Some(frame)) => {
+                            let result = service_clone
+                                .execute_command(frame, writer.clone(), &mut client)
+                                .await;
+
+                            if let Err(e) = result {
+                                eprintln!("closing connection {address}: {e:?}");
+                                close_connection = true;
+                                break;
+                            }
+
+                            if client.flags.close_after_reply {
+                                close_connection = true;
+                                break;
+                            }
+                        }
+
+                        Ok(None) => break,
+
+                        Err(e) => {
+                            eprintln!("{e} from {address}");
+
+                            client.add_reply(&RespDataTypes::SimpleError(format!("ERR {e}")));
+
+                            close_connection = true;
 
-                        Err(_) => {
-                            eprintln!("timeout reading from stream {address}");
                             break;
                         }
                     }
-                } else {
-                    stream_guard.read(&mut buffer).await
-                };
+                }
+
+                // replies to the whole batch go out in a single write
+                if !client.output.is_empty() {
+                    let write_result = writer.lock().await.write_all(&client.output).await;
+
+                    client.clear_output();
+
+                    if let Err(e) = write_result {
+                        eprintln!("error writing to stream {address}: {e:?}");
+                        break;
+                    }
+                }
+
+                if close_connection {
+                    break;
+                }
+
+                service_clone.update_client(&client);
             }
+
+            service_clone.remove_client(client.id);
         });
     }
 }
@@ -0,0 +1,494 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+
+use crate::client::Client;
+use crate::clock::Clock;
+use crate::commands::BlockingOp;
+use crate::database::keyspace::Keyspace;
+use crate::database::value::{DatabaseError, RedisValue};
+use crate::resp::RespDataTypes;
+
+use super::lists::{list_mut, move_element, pop, pop_command, remove_if_empty};
+use super::RedisService;
+
+/// A client waiting in a blocking command.
+#[derive(Debug)]
+struct Waiter {
+    client_id: u64,
+
+    db: u32,
+
+    keys: Vec<Bytes>,
+
+    op: BlockingOp,
+
+    /// Taken by whatever ends the wait: a write serving the client, its timeout or
+    /// `CLIENT UNBLOCK`. Serving holds the lock from the pop to the reply, so exactly one
+    /// of them answers.
+    reply: Mutex<Option<oneshot::Sender<RespDataTypes>>>,
+}
+
+/// Clients waiting in blocking commands, queued per key in the order they blocked.
+#[derive(Debug, Default)]
+pub struct BlockedClients {
+    queues: Mutex<Queues>,
+}
+
+#[derive(Debug, Default)]
+struct Queues {
+    by_key: HashMap<(u32, Bytes), VecDeque<Arc<Waiter>>>,
+
+    by_client: HashMap<u64, Arc<Waiter>>,
+}
+
+impl BlockedClients {
+    fn add(&self, waiter: Arc<Waiter>) {
+        let mut queues = self.queues.lock().unwrap();
+
+        for key in &waiter.keys {
+            let queue = queues.by_key.entry((waiter.db, key.clone())).or_default();
+
+            // a key named twice is waited on once
+            if !queue
+                .iter()
+                .any(|queued| queued.client_id == waiter.client_id)
+            {
+                queue.push_back(waiter.clone());
+            }
+        }
+
+        queues.by_client.insert(waiter.client_id, waiter);
+    }
+
+    fn remove(&self, client_id: u64) {
+        let mut queues = self.queues.lock().unwrap();
+
+        let Some(waiter) = queues.by_client.remove(&client_id) else {
+            return;
+        };
+
+        for key in &waiter.keys {
+            let slot = (waiter.db, key.clone());
+
+            if let Some(queue) = queues.by_key.get_mut(&slot) {
+                queue.retain(|queued| queued.client_id != client_id);
+
+                if queue.is_empty() {
+                    queues.by_key.remove(&slot);
+                }
+            }
+        }
+    }
+
+    /// The client that has waited the longest on `key` among those `accepts` holds for.
+    fn first_where(
+        &self,
+        db: u32,
+        key: &Bytes,
+        accepts: impl Fn(&Waiter) -> bool,
+    ) -> Option<Arc<Waiter>> {
+        self.queues
+            .lock()
+            .unwrap()
+            .by_key
+            .get(&(db, key.clone()))
+            .and_then(|queue| queue.iter().find(|waiter| accepts(waiter)).cloned())
+    }
+
+    fn get(&self, client_id: u64) -> Option<Arc<Waiter>> {
+        self.queues
+            .lock()
+            .unwrap()
+            .by_client
+            .get(&client_id)
+            .cloned()
+    }
+
+    /// Keys of database `db` that clients wait on.
+    fn keys_in(&self, db: u32) -> Vec<Bytes> {
+        self.queues
+            .lock()
+            .unwrap()
+            .by_key
+            .keys()
+            .filter(|(key_db, _)| *key_db == db)
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+}
+
+/// The outcome of running a blocking command's operation on one of its keys.
+struct Served {
+    reply: RespDataTypes,
+
+    /// The write replicas should run to do the same, if anything changed.
+    propagate: Option<Vec<Bytes>>,
+
+    /// A key that got a new element, which may in turn serve other blocked clients.
+    pushed: Option<Bytes>,
+}
+
+impl Served {
+    fn error(error: DatabaseError) -> Self {
+        Self {
+            reply: RespDataTypes::SimpleError(error.to_string()),
+            propagate: None,
+            pushed: None,
+        }
+    }
+}
+
+impl RedisService {
+    /// Runs a blocking command: serves it right away when one of `keys` has something to
+    /// pop, otherwise waits in line on all of them until a write serves it, `timeout`
+    /// elapses or `CLIENT UNBLOCK` ends the wait.
+    pub(super) async fn block(
+        &self,
+        keys: Vec<Bytes>,
+        op: BlockingOp,
+        timeout: Option<Duration>,
+        stream: &tokio::sync::Mutex<OwnedWriteHalf>,
+        client: &mut Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        // writes serve the clients already waiting before they release the keyspace, so a key
+        // with something to pop has nobody queued ahead of this client
+        for key in &keys {
+            if let Some(served) = serve(&mut keyspace, key, &op, now) {
+                if let Some(args) = served.propagate {
+                    self.propagate(&mut keyspace, client.db, args).await?;
+                }
+
+                if let Some(pushed) = served.pushed {
+                    self.serve_blocked(&mut keyspace, client.db, pushed).await?;
+                }
+
+                return Ok(served.reply);
+            }
+        }
+
+        let (sender, mut receiver) = oneshot::channel();
+
+        let waiter = Arc::new(Waiter {
+            client_id: client.id,
+            db: client.db,
+            keys,
+            op,
+            reply: Mutex::new(Some(sender)),
+        });
+
+        // queued before the keyspace is released, so no push can slip in unnoticed
+        self.blocked.add(waiter.clone());
+
+        drop(keyspace);
+
+        let mut stream = stream.lock().await;
+
+        // replies to the commands pipelined before this one should not wait with it
+        if !client.output.is_empty() {
+            stream
+                .write_all(&client.output)
+                .await
+                .with_context(|| "could not write to stream")?;
+
+            client.clear_output();
+        }
+
+        client.flags.blocked = true;
+
+        self.update_client(client);
+
+        let killed = client.killed.clone();
+
+        let served = tokio::select! {
+            reply = &mut receiver => reply.ok(),
+
+            _ = wait(timeout) => None,
+
+            _ = killed.notified() => {
+                self.blocked.remove(client.id);
+
+                bail!("client was killed while blocked");
+            }
+
+            _ = disconnected(stream.as_ref()) => {
+                self.blocked.remove(client.id);
+
+                bail!("client disconnected while blocked");
+            }
+        };
+
+        client.flags.blocked = false;
+
+        let reply = match served {
+            Some(reply) => reply,
+
+            None => {
+                let timed_out = waiter.reply.lock().unwrap().take().is_some();
+
+                if timed_out {
+                    self.blocked.remove(client.id);
+
+                    RespDataTypes::NullArray
+                } else {
+                    // served or unblocked just as the timeout fired
+                    receiver.await.unwrap_or(RespDataTypes::NullArray)
+                }
+            }
+        };
+
+        Ok(reply)
+    }
+
+    /// Serves the clients blocked on `key`, first come first served, for as long as it has
+    /// something to pop. Called after every write that may have added to it, with the
+    /// keyspace of database `db_id` the write still holds, so that no other client can take
+    /// what was added ahead of those already waiting. Clients blocked in a command for
+    /// another type than the key holds keep waiting.
+    pub(super) async fn serve_blocked(
+        &self,
+        keyspace: &mut Keyspace,
+        db_id: u32,
+        key: Bytes,
+    ) -> anyhow::Result<()> {
+        let mut ready = VecDeque::from([key]);
+
+        while let Some(key) = ready.pop_front() {
+            loop {
+                let now = self.clock.now();
+
+                let Some(value_type) = keyspace
+                    .get(&key, now)
+                    .map(|record| record.value.type_name())
+                else {
+                    break;
+                };
+
+                let Some(waiter) = self
+                    .blocked
+                    .first_where(db_id, &key, |waiter| waiter.op.value_type() == value_type)
+                else {
+                    break;
+                };
+
+                let served = {
+                    let mut slot = waiter.reply.lock().unwrap();
+
+                    match slot.take() {
+                        // timed out or unblocked, and about to leave the queue
+                        None => None,
+
+                        Some(sender) => match serve(keyspace, &key, &waiter.op, now) {
+                            Some(served) => {
+                                let _ = sender.send(served.reply);
+
+                                Some((served.propagate, served.pushed))
+                            }
+
+                            None => {
+                                *slot = Some(sender);
+
+                                break;
+                            }
+                        },
+                    }
+                };
+
+                self.blocked.remove(waiter.client_id);
+
+                let Some((propagate, pushed)) = served else {
+                    continue;
+                };
+
+                if let Some(args) = propagate {
+                    self.propagate(keyspace, db_id, args).await?;
+                }
+
+                ready.extend(pushed);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serves whatever clients blocked on database `db` can be served, after its contents,
+    /// the locked `keyspace`, were replaced wholesale.
+    pub(super) async fn serve_blocked_in(
+        &self,
+        keyspace: &mut Keyspace,
+        db: u32,
+    ) -> anyhow::Result<()> {
+        for key in self.blocked.keys_in(db) {
+            self.serve_blocked(keyspace, db, key).await?;
+        }
+
+        Ok(())
+    }
+
+    /// `CLIENT UNBLOCK`: ends the wait of client `id` as if it timed out, or with an error.
+    /// False when the client is not blocked.
+    pub(super) fn unblock(&self, id: u64, error: bool) -> bool {
+        let Some(waiter) = self.blocked.get(id) else {
+            return false;
+        };
+
+        let Some(sender) = waiter.reply.lock().unwrap().take() else {
+            return false;
+        };
+
+        self.blocked.remove(id);
+
+        let reply = if error {
+            RespDataTypes::SimpleError("UNBLOCKED client unblocked via CLIENT UNBLOCK".to_string())
+        } else {
+            RespDataTypes::NullArray
+        };
+
+        let _ = sender.send(reply);
+
+        true
+    }
+}
+
+/// Runs a blocking command's operation on `key`, `None` while it has nothing to pop.
+fn serve(
+    keyspace: &mut Keyspace,
+    key: &Bytes,
+    op: &BlockingOp,
+    now: DateTime<Utc>,
+) -> Option<Served> {
+    match op {
+        BlockingOp::Pop(end) => {
+            let element = match list_mut(keyspace, key, now) {
+                Ok(list) => pop(list?, *end)?,
+
+                Err(e) => return Some(Served::error(e)),
+            };
+
+            remove_if_empty(keyspace, key, now);
+
+            Some(Served {
+                reply: RespDataTypes::Array(vec![
+                    RespDataTypes::BulkString(key.clone()),
+                    RespDataTypes::BulkString(element),
+                ]),
+                propagate: Some(vec![pop_command(*end).into(), key.clone()]),
+                pushed: None,
+            })
+        }
+
+        BlockingOp::MPop(end, count) => {
+            let popped: Vec<Bytes> = match list_mut(keyspace, key, now) {
+                Ok(list) => {
+                    let list = list?;
+
+                    (0..*count).map_while(|_| pop(list, *end)).collect()
+                }
+
+                Err(e) => return Some(Served::error(e)),
+            };
+
+            remove_if_empty(keyspace, key, now);
+
+            Some(Served {
+                reply: RespDataTypes::Array(vec![
+                    RespDataTypes::BulkString(key.clone()),
+                    RespDataTypes::from(popped),
+                ]),
+                propagate: Some(vec![
+                    pop_command(*end).into(),
+                    key.clone(),
+                    count.to_string().into(),
+                ]),
+                pushed: None,
+            })
+        }
+
+        BlockingOp::Move(destination, from, to) => {
+            let element = match move_element(keyspace, key, destination, *from, *to, now) {
+                Ok(element) => element?,
+
+                Err(e) => return Some(Served::error(e)),
+            };
+
+            Some(Served {
+                reply: RespDataTypes::BulkString(element),
+                propagate: Some(vec![
+                    "LMOVE".into(),
+                    key.clone(),
+                    destination.clone(),
+                    from.name().into(),
+                    to.name().into(),
+                ]),
+                pushed: Some(destination.clone()),
+            })
+        }
+
+        BlockingOp::ZPopMin | BlockingOp::ZPopMax => {
+            let max = matches!(op, BlockingOp::ZPopMax);
+
+            let zset = match keyspace
+                .get_value_mut(key, now)
+                .map(RedisValue::as_zset_mut)
+                .transpose()
+            {
+                Ok(zset) => zset?,
+
+                Err(e) => return Some(Served::error(e)),
+            };
+
+            let (member, score) = zset.pop(max)?;
+
+            if zset.is_empty() {
+                keyspace.remove(key, now);
+            }
+
+            let command = if max { "ZPOPMAX" } else { "ZPOPMIN" };
+
+            Some(Served {
+                reply: RespDataTypes::Array(vec![
+                    RespDataTypes::BulkString(key.clone()),
+                    RespDataTypes::BulkString(member),
+                    RespDataTypes::Double(score),
+                ]),
+                propagate: Some(vec![command.into(), key.clone()]),
+                pushed: None,
+            })
+        }
+    }
+}
+
+/// Sleeps for `timeout`, or forever without one.
+async fn wait(timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => tokio::time::sleep(timeout).await,
+
+        None => std::future::pending().await,
+    }
+}
+
+/// Completes once the peer closes the connection. Commands it sends in the meantime wait
+/// for the blocked one, so then this never completes.
+async fn disconnected(stream: &TcpStream) {
+    let mut byte = [0; 1];
+
+    match stream.peek(&mut byte).await {
+        Ok(0) | Err(_) => {}
+
+        Ok(_) => std::future::pending().await,
+    }
+}
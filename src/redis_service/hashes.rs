@@ -0,0 +1,981 @@
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use rand::seq::{IndexedRandom, IteratorRandom, SliceRandom};
+
+use crate::client::Client;
+use crate::clock::Clock;
+use crate::commands::{
+    Expiration, ExpireCondition, HSetExCommand, SetCondition, TimeUnit, TtlUpdate,
+};
+use crate::database::keyspace::Keyspace;
+use crate::database::value::{DatabaseError, Hash, Record, RedisValue, MAX_FIELD_EXPIRE_MS};
+use crate::resp::{format_double, ProtocolVersion, RespDataTypes};
+
+use super::{invalid_expire_time, RedisService, MAX_RANDOM_PREALLOCATION};
+
+impl RedisService {
+    /// Sets every pair, replying with how many fields are new.
+    pub(super) async fn hset(
+        &self,
+        key: Bytes,
+        pairs: Vec<(Bytes, Bytes)>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let hash = match hash_or_create(&mut keyspace, &key, now) {
+            Ok(hash) => hash,
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let mut created = 0;
+
+        for (field, value) in &pairs {
+            if hash.insert(field.clone(), value.clone()).is_none() {
+                created += 1;
+            }
+        }
+
+        let mut args = Vec::with_capacity(pairs.len() * 2 + 2);
+
+        args.push("HSET".into());
+        args.push(key);
+
+        for (field, value) in pairs {
+            args.push(field);
+            args.push(value);
+        }
+
+        self.propagate(&mut keyspace, client.db, args).await?;
+
+        Ok(RespDataTypes::Integer(created))
+    }
+
+    pub(super) async fn hsetnx(
+        &self,
+        key: Bytes,
+        field: Bytes,
+        value: Bytes,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let hash = match hash_or_create(&mut keyspace, &key, now) {
+            Ok(hash) => hash,
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        if hash.contains_key(&field) {
+            return Ok(RespDataTypes::Integer(0));
+        }
+
+        hash.insert(field.clone(), value.clone());
+
+        self.propagate(
+            &mut keyspace,
+            client.db,
+            vec!["HSETNX".into(), key, field, value],
+        )
+        .await?;
+
+        Ok(RespDataTypes::Integer(1))
+    }
+
+    pub(super) async fn hget(&self, key: Bytes, field: Bytes, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        match hash(&mut keyspace, &key, now) {
+            Ok(hash) => hash
+                .and_then(|hash| hash.get(&field))
+                .map_or(RespDataTypes::Null, |value| {
+                    RespDataTypes::BulkString(value.clone())
+                }),
+
+            Err(e) => RespDataTypes::SimpleError(e.to_string()),
+        }
+    }
+
+    pub(super) async fn hmget(
+        &self,
+        key: Bytes,
+        fields: Vec<Bytes>,
+        client: &Client,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let hash = match hash(&mut keyspace, &key, now) {
+            Ok(hash) => hash,
+
+            Err(e) => return RespDataTypes::SimpleError(e.to_string()),
+        };
+
+        RespDataTypes::Array(
+            fields
+                .iter()
+                .map(|field| {
+                    hash.and_then(|hash| hash.get(field))
+                        .map_or(RespDataTypes::Null, |value| {
+                            RespDataTypes::BulkString(value.clone())
+                        })
+                })
+                .collect(),
+        )
+    }
+
+    pub(super) async fn hdel(
+        &self,
+        key: Bytes,
+        fields: Vec<Bytes>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let hash = match hash_mut(&mut keyspace, &key, now) {
+            Ok(Some(hash)) => hash,
+
+            Ok(None) => return Ok(RespDataTypes::Integer(0)),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let removed = fields
+            .iter()
+            .filter(|field| hash.remove(field).is_some())
+            .count();
+
+        remove_if_empty(&mut keyspace, &key, now);
+
+        if removed > 0 {
+            let mut args = vec!["HDEL".into(), key];
+
+            args.extend(fields);
+
+            self.propagate(&mut keyspace, client.db, args).await?;
+        }
+
+        Ok(RespDataTypes::Integer(removed as i64))
+    }
+
+    pub(super) async fn hexists(&self, key: Bytes, field: Bytes, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        match hash(&mut keyspace, &key, now) {
+            Ok(hash) => {
+                RespDataTypes::Integer(hash.is_some_and(|hash| hash.contains_key(&field)) as i64)
+            }
+
+            Err(e) => RespDataTypes::SimpleError(e.to_string()),
+        }
+    }
+
+    pub(super) async fn hlen(&self, key: Bytes, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        match hash(&mut keyspace, &key, now) {
+            Ok(hash) => RespDataTypes::Integer(hash.map_or(0, Hash::len) as i64),
+
+            Err(e) => RespDataTypes::SimpleError(e.to_string()),
+        }
+    }
+
+    pub(super) async fn hstrlen(&self, key: Bytes, field: Bytes, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        match hash(&mut keyspace, &key, now) {
+            Ok(hash) => RespDataTypes::Integer(
+                hash.and_then(|hash| hash.get(&field)).map_or(0, Bytes::len) as i64,
+            ),
+
+            Err(e) => RespDataTypes::SimpleError(e.to_string()),
+        }
+    }
+
+    /// `HKEYS`, `HVALS` and `HGETALL`, which is a map for RESP3 clients.
+    pub(super) async fn hgetall(
+        &self,
+        key: Bytes,
+        keys: bool,
+        values: bool,
+        client: &Client,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let hash = match hash(&mut keyspace, &key, now) {
+            Ok(hash) => hash.into_iter().flat_map(Hash::iter),
+
+            Err(e) => return RespDataTypes::SimpleError(e.to_string()),
+        };
+
+        let bulk = |value: &Bytes| RespDataTypes::BulkString(value.clone());
+
+        match (keys, values) {
+            (true, true) => RespDataTypes::Map(
+                hash.map(|(field, value)| (bulk(field), bulk(value)))
+                    .collect(),
+            ),
+
+            (true, false) => RespDataTypes::Array(hash.map(|(field, _)| bulk(field)).collect()),
+
+            _ => RespDataTypes::Array(hash.map(|(_, value)| bulk(value)).collect()),
+        }
+    }
+
+    pub(super) async fn hincrby(
+        &self,
+        key: Bytes,
+        field: Bytes,
+        increment: i64,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let hash = match hash_or_create(&mut keyspace, &key, now) {
+            Ok(hash) => hash,
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let current = match hash.get(&field) {
+            Some(value) => match std::str::from_utf8(value).ok().and_then(|v| v.parse().ok()) {
+                Some(current) => current,
+
+                None => {
+                    return Ok(RespDataTypes::SimpleError(
+                        "ERR hash value is not an integer".to_string(),
+                    ))
+                }
+            },
+
+            None => 0i64,
+        };
+
+        let Some(updated) = current.checked_add(increment) else {
+            return Ok(RespDataTypes::SimpleError(
+                "ERR increment or decrement would overflow".to_string(),
+            ));
+        };
+
+        hash.insert_keep_ttl(field.clone(), updated.to_string().into());
+
+        self.propagate(
+            &mut keyspace,
+            client.db,
+            vec!["HINCRBY".into(), key, field, increment.to_string().into()],
+        )
+        .await?;
+
+        Ok(RespDataTypes::Integer(updated))
+    }
+
+    /// Replicas are sent the resulting value, so that they never redo the float arithmetic.
+    pub(super) async fn hincrbyfloat(
+        &self,
+        key: Bytes,
+        field: Bytes,
+        increment: f64,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let hash = match hash_or_create(&mut keyspace, &key, now) {
+            Ok(hash) => hash,
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let current = match hash.get(&field) {
+            Some(value) => match std::str::from_utf8(value)
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|current| !current.is_nan())
+            {
+                Some(current) => current,
+
+                None => {
+                    return Ok(RespDataTypes::SimpleError(
+                        "ERR hash value is not a float".to_string(),
+                    ))
+                }
+            },
+
+            None => 0.0,
+        };
+
+        let updated = current + increment;
+
+        if !updated.is_finite() {
+            return Ok(RespDataTypes::SimpleError(
+                "ERR increment would produce NaN or Infinity".to_string(),
+            ));
+        }
+
+        let updated = Bytes::from(format_double(updated));
+
+        hash.insert_keep_ttl(field.clone(), updated.clone());
+
+        let expires_at = hash.expires_at(&field);
+
+        self.propagate(
+            &mut keyspace,
+            client.db,
+            vec!["HSET".into(), key.clone(), field.clone(), updated.clone()],
+        )
+        .await?;
+
+        // HSET drops the expiration, which the field keeps
+        if let Some(expires_at) = expires_at {
+            self.propagate(
+                &mut keyspace,
+                client.db,
+                expire_fields_at(key, expires_at, vec![field]),
+            )
+            .await?;
+        }
+
+        Ok(RespDataTypes::BulkString(updated))
+    }
+
+    /// Without a count, a single field or null. A positive count picks distinct fields, a
+    /// negative one picks that many fields that may repeat.
+    pub(super) async fn hrandfield(
+        &self,
+        key: Bytes,
+        count: Option<i64>,
+        with_values: bool,
+        client: &Client,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let hash = match hash(&mut keyspace, &key, now) {
+            Ok(hash) => hash,
+
+            Err(e) => return RespDataTypes::SimpleError(e.to_string()),
+        };
+
+        let mut rng = rand::rng();
+
+        let Some(count) = count else {
+            return hash
+                .and_then(|hash| hash.keys().choose(&mut rng))
+                .map_or(RespDataTypes::Null, |field| {
+                    RespDataTypes::BulkString(field.clone())
+                });
+        };
+
+        let Some(hash) = hash else {
+            return RespDataTypes::Array(Vec::new());
+        };
+
+        let picked: Vec<(&Bytes, &Bytes)> = if count >= 0 {
+            let count = (count as usize).min(hash.len());
+
+            let mut picked = hash.iter().choose_multiple(&mut rng, count);
+
+            picked.shuffle(&mut rng);
+
+            picked
+        } else {
+            let entries: Vec<(&Bytes, &Bytes)> = hash.iter().collect();
+
+            let count = count.unsigned_abs() as usize;
+
+            let mut picked = Vec::with_capacity(count.min(MAX_RANDOM_PREALLOCATION));
+
+            picked.extend((0..count).filter_map(|_| entries.choose(&mut rng).copied()));
+
+            picked
+        };
+
+        let bulk = |value: &Bytes| RespDataTypes::BulkString(value.clone());
+
+        let reply = match (with_values, client.protocol) {
+            (false, _) => picked.into_iter().map(|(field, _)| bulk(field)).collect(),
+
+            // RESP3 clients get each field paired with its value
+            (true, ProtocolVersion::Resp3) => picked
+                .into_iter()
+                .map(|(field, value)| RespDataTypes::Array(vec![bulk(field), bulk(value)]))
+                .collect(),
+
+            (true, _) => picked
+                .into_iter()
+                .flat_map(|(field, value)| [bulk(field), bulk(value)])
+                .collect(),
+        };
+
+        RespDataTypes::Array(reply)
+    }
+
+    /// The `HEXPIRE` family, replying for every field with -2 when it does not exist, 0 when
+    /// the condition is not met, 1 when the expiration was set, and 2 when the field was
+    /// deleted because the time is already past.
+    pub(super) async fn hexpire(
+        &self,
+        key: Bytes,
+        expiration: Expiration,
+        condition: ExpireCondition,
+        fields: Vec<Bytes>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let Some(deadline) = field_deadline(&expiration, now) else {
+            return Ok(invalid_expire_time(client.last_command));
+        };
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let hash = match hash_mut(&mut keyspace, &key, now) {
+            Ok(Some(hash)) => hash,
+
+            Ok(None) => return Ok(missing_fields(&fields)),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let mut replies = Vec::with_capacity(fields.len());
+
+        let mut updated = Vec::new();
+
+        let mut deleted = Vec::new();
+
+        for field in fields {
+            let reply = if !hash.contains_key(&field) {
+                -2
+            } else if !condition.allows(hash.expires_at(&field), deadline) {
+                0
+            } else if deadline <= now {
+                hash.remove(&field);
+
+                deleted.push(field);
+
+                2
+            } else {
+                hash.set_expires_at(&field, Some(deadline));
+
+                updated.push(field);
+
+                1
+            };
+
+            replies.push(RespDataTypes::Integer(reply));
+        }
+
+        keyspace.track_field_expirations(&key);
+
+        remove_if_empty(&mut keyspace, &key, now);
+
+        if !deleted.is_empty() {
+            let mut args = vec!["HDEL".into(), key.clone()];
+
+            args.extend(deleted);
+
+            self.propagate(&mut keyspace, client.db, args).await?;
+        }
+
+        if !updated.is_empty() {
+            self.propagate(
+                &mut keyspace,
+                client.db,
+                expire_fields_at(key, deadline, updated),
+            )
+            .await?;
+        }
+
+        Ok(RespDataTypes::Array(replies))
+    }
+
+    /// `HTTL` and `HPTTL`: the remaining time to live of every field, with the same -2 and -1
+    /// conventions as `HEXPIRETIME`.
+    pub(super) async fn httl(
+        &self,
+        key: Bytes,
+        unit: TimeUnit,
+        fields: Vec<Bytes>,
+        client: &Client,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        self.field_expirations(key, fields, client, |expires_at| {
+            let ms = (expires_at - now).num_milliseconds();
+
+            match unit {
+                TimeUnit::Seconds => (ms + 999) / 1000,
+
+                TimeUnit::Milliseconds => ms,
+            }
+        })
+        .await
+    }
+
+    /// `HEXPIRETIME` and `HPEXPIRETIME`
+    pub(super) async fn hexpire_time(
+        &self,
+        key: Bytes,
+        unit: TimeUnit,
+        fields: Vec<Bytes>,
+        client: &Client,
+    ) -> RespDataTypes {
+        self.field_expirations(key, fields, client, |expires_at| match unit {
+            TimeUnit::Seconds => expires_at.timestamp(),
+
+            TimeUnit::Milliseconds => expires_at.timestamp_millis(),
+        })
+        .await
+    }
+
+    /// Replies with `time` of the expiration of every field: -2 for the fields that do not
+    /// exist and -1 for those that never expire.
+    async fn field_expirations(
+        &self,
+        key: Bytes,
+        fields: Vec<Bytes>,
+        client: &Client,
+        time: impl Fn(DateTime<Utc>) -> i64,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let hash = match hash(&mut keyspace, &key, now) {
+            Ok(Some(hash)) => hash,
+
+            Ok(None) => return missing_fields(&fields),
+
+            Err(e) => return RespDataTypes::SimpleError(e.to_string()),
+        };
+
+        RespDataTypes::Array(
+            fields
+                .iter()
+                .map(|field| {
+                    let reply = match hash.expires_at(field) {
+                        _ if !hash.contains_key(field) => -2,
+
+                        None => -1,
+
+                        Some(expires_at) => time(expires_at),
+                    };
+
+                    RespDataTypes::Integer(reply)
+                })
+                .collect(),
+        )
+    }
+
+    /// Replies for every field with -2 when it does not exist, -1 when it has no expiration,
+    /// and 1 when its expiration was removed.
+    pub(super) async fn hpersist(
+        &self,
+        key: Bytes,
+        fields: Vec<Bytes>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let hash = match hash_mut(&mut keyspace, &key, now) {
+            Ok(Some(hash)) => hash,
+
+            Ok(None) => return Ok(missing_fields(&fields)),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let mut replies = Vec::with_capacity(fields.len());
+
+        let mut persisted = Vec::new();
+
+        for field in fields {
+            let reply = match hash.expires_at(&field) {
+                _ if !hash.contains_key(&field) => -2,
+
+                None => -1,
+
+                Some(_) => {
+                    hash.set_expires_at(&field, None);
+
+                    persisted.push(field);
+
+                    1
+                }
+            };
+
+            replies.push(RespDataTypes::Integer(reply));
+        }
+
+        if !persisted.is_empty() {
+            let mut args = vec!["HPERSIST".into(), key];
+
+            push_fields(&mut args, persisted);
+
+            self.propagate(&mut keyspace, client.db, args).await?;
+        }
+
+        Ok(RespDataTypes::Array(replies))
+    }
+
+    /// Like `HMGET`, also changing the expiration of the fields that exist. A time in the past
+    /// deletes them once read.
+    pub(super) async fn hgetex(
+        &self,
+        key: Bytes,
+        update: Option<TtlUpdate>,
+        fields: Vec<Bytes>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let deadline = match update {
+            Some(TtlUpdate::Expire(expiration)) => match field_deadline(&expiration, now) {
+                Some(deadline) => Some(deadline),
+
+                None => return Ok(invalid_expire_time(client.last_command)),
+            },
+
+            _ => None,
+        };
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let hash = match hash_mut(&mut keyspace, &key, now) {
+            Ok(Some(hash)) => hash,
+
+            Ok(None) => {
+                return Ok(RespDataTypes::Array(vec![
+                    RespDataTypes::Null;
+                    fields.len()
+                ]))
+            }
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let values = fields
+            .iter()
+            .map(|field| {
+                hash.get(field).map_or(RespDataTypes::Null, |value| {
+                    RespDataTypes::BulkString(value.clone())
+                })
+            })
+            .collect();
+
+        let mut changed: Vec<Bytes> = Vec::new();
+
+        for field in fields {
+            if !hash.contains_key(&field) || changed.contains(&field) {
+                continue;
+            }
+
+            match (update, deadline) {
+                (Some(TtlUpdate::Persist), _) if hash.expires_at(&field).is_some() => {
+                    hash.set_expires_at(&field, None);
+                }
+
+                (_, Some(deadline)) if deadline <= now => {
+                    hash.remove(&field);
+                }
+
+                (_, Some(deadline)) => {
+                    hash.set_expires_at(&field, Some(deadline));
+                }
+
+                _ => continue,
+            }
+
+            changed.push(field);
+        }
+
+        keyspace.track_field_expirations(&key);
+
+        remove_if_empty(&mut keyspace, &key, now);
+
+        if !changed.is_empty() {
+            let args = match deadline {
+                None => {
+                    let mut args = vec!["HPERSIST".into(), key];
+
+                    push_fields(&mut args, changed);
+
+                    args
+                }
+
+                Some(deadline) if deadline <= now => {
+                    let mut args = vec!["HDEL".into(), key];
+
+                    args.extend(changed);
+
+                    args
+                }
+
+                Some(deadline) => expire_fields_at(key, deadline, changed),
+            };
+
+            self.propagate(&mut keyspace, client.db, args).await?;
+        }
+
+        Ok(RespDataTypes::Array(values))
+    }
+
+    /// Sets every pair unless `FNX` or `FXX` refuse to, replying with 1 when they were set and
+    /// 0 otherwise. Without `KEEPTTL` the fields lose their expiration, or get the new one.
+    pub(super) async fn hsetex(
+        &self,
+        command: HSetExCommand,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let deadline = match &command.expiration {
+            Some(expiration) => match field_deadline(expiration, now) {
+                Some(deadline) => Some(deadline),
+
+                None => return Ok(invalid_expire_time(client.last_command)),
+            },
+
+            None => None,
+        };
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let existing = match hash(&mut keyspace, &command.key, now) {
+            Ok(hash) => command
+                .pairs
+                .iter()
+                .filter(|(field, _)| hash.is_some_and(|hash| hash.contains_key(field)))
+                .count(),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let allowed = match command.condition {
+            Some(SetCondition::Nx) => existing == 0,
+
+            Some(SetCondition::Xx) => existing == command.pairs.len(),
+
+            None => true,
+        };
+
+        if !allowed {
+            return Ok(RespDataTypes::Integer(0));
+        }
+
+        let hash = hash_or_create(&mut keyspace, &command.key, now)?;
+
+        for (field, value) in &command.pairs {
+            if command.keep_ttl {
+                hash.insert_keep_ttl(field.clone(), value.clone());
+            } else {
+                hash.insert(field.clone(), value.clone());
+            }
+
+            match deadline {
+                Some(deadline) if deadline <= now => {
+                    hash.remove(field);
+                }
+
+                Some(deadline) => {
+                    hash.set_expires_at(field, Some(deadline));
+                }
+
+                None => {}
+            }
+        }
+
+        keyspace.track_field_expirations(&command.key);
+
+        remove_if_empty(&mut keyspace, &command.key, now);
+
+        let mut args = Vec::with_capacity(command.pairs.len() * 2 + 6);
+
+        match deadline {
+            // the fields were deleted as soon as they were set
+            Some(deadline) if deadline <= now => {
+                args.push("HDEL".into());
+                args.push(command.key);
+                args.extend(command.pairs.into_iter().map(|(field, _)| field));
+            }
+
+            None if !command.keep_ttl => {
+                args.push("HSET".into());
+                args.push(command.key);
+                args.extend(
+                    command
+                        .pairs
+                        .into_iter()
+                        .flat_map(|(field, value)| [field, value]),
+                );
+            }
+
+            _ => {
+                args.push("HSETEX".into());
+                args.push(command.key);
+
+                match deadline {
+                    Some(deadline) => {
+                        args.push("PXAT".into());
+                        args.push(deadline.timestamp_millis().to_string().into());
+                    }
+
+                    None => args.push("KEEPTTL".into()),
+                }
+
+                args.push("FIELDS".into());
+                args.push(command.pairs.len().to_string().into());
+                args.extend(
+                    command
+                        .pairs
+                        .into_iter()
+                        .flat_map(|(field, value)| [field, value]),
+                );
+            }
+        }
+
+        self.propagate(&mut keyspace, client.db, args).await?;
+
+        Ok(RespDataTypes::Integer(1))
+    }
+}
+
+/// The point in time a field expiration refers to, `None` when it is out of range.
+fn field_deadline(expiration: &Expiration, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    expiration
+        .deadline(now)
+        .filter(|deadline| deadline.timestamp_millis() <= MAX_FIELD_EXPIRE_MS)
+}
+
+/// The reply to the field expiration commands on a key that does not exist.
+fn missing_fields(fields: &[Bytes]) -> RespDataTypes {
+    RespDataTypes::Array(vec![RespDataTypes::Integer(-2); fields.len()])
+}
+
+/// Appends the `FIELDS numfields field ...` block of the field expiration commands.
+fn push_fields(args: &mut Vec<Bytes>, fields: Vec<Bytes>) {
+    args.push("FIELDS".into());
+    args.push(fields.len().to_string().into());
+    args.extend(fields);
+}
+
+/// `HPEXPIREAT key ms FIELDS numfields field ...`, for replicas to expire `fields` when
+/// the master does.
+fn expire_fields_at(key: Bytes, deadline: DateTime<Utc>, fields: Vec<Bytes>) -> Vec<Bytes> {
+    let mut args = vec![
+        "HPEXPIREAT".into(),
+        key,
+        deadline.timestamp_millis().to_string().into(),
+    ];
+
+    push_fields(&mut args, fields);
+
+    args
+}
+
+/// The hash at `key`, `None` when there is no such key.
+fn hash<'a>(
+    keyspace: &'a mut Keyspace,
+    key: &[u8],
+    now: DateTime<Utc>,
+) -> Result<Option<&'a Hash>, DatabaseError> {
+    keyspace
+        .get(key, now)
+        .map(|record| record.value.as_hash())
+        .transpose()
+}
+
+/// The hash at `key` for writing, `None` when there is no such key.
+fn hash_mut<'a>(
+    keyspace: &'a mut Keyspace,
+    key: &[u8],
+    now: DateTime<Utc>,
+) -> Result<Option<&'a mut Hash>, DatabaseError> {
+    keyspace
+        .get_value_mut(key, now)
+        .map(RedisValue::as_hash_mut)
+        .transpose()
+}
+
+/// The hash at `key` for writing, created empty when there is no such key.
+fn hash_or_create<'a>(
+    keyspace: &'a mut Keyspace,
+    key: &Bytes,
+    now: DateTime<Utc>,
+) -> Result<&'a mut Hash, DatabaseError> {
+    if !keyspace.contains(key, now) {
+        keyspace.insert(
+            key.clone(),
+            Record::new(RedisValue::Hash(Hash::default()), None),
+        );
+    }
+
+    Ok(hash_mut(keyspace, key, now)?.expect("the hash exists"))
+}
+
+/// Hashes are never stored empty: the key goes away with its last field.
+fn remove_if_empty(keyspace: &mut Keyspace, key: &[u8], now: DateTime<Utc>) {
+    if let Ok(Some(hash)) = hash(keyspace, key, now) {
+        if hash.is_empty() {
+            keyspace.remove(key, now);
+        }
+    }
+}
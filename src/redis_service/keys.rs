@@ -0,0 +1,314 @@
+use bytes::Bytes;
+
+use crate::client::Client;
+use crate::clock::Clock;
+use crate::commands::{CopyCommand, Expiration, ExpireCondition, TimeUnit};
+use crate::database::Database;
+use crate::resp::RespDataTypes;
+
+use super::{invalid_expire_time, RedisService};
+
+impl RedisService {
+    pub(super) async fn del(
+        &self,
+        keys: Vec<Bytes>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        // a key named twice is only deleted, and counted, once
+        let deleted = keys
+            .iter()
+            .filter(|key| keyspace.remove(key, now).is_some())
+            .count();
+
+        if deleted > 0 {
+            let mut args = Vec::with_capacity(keys.len() + 1);
+
+            args.push("DEL".into());
+            args.extend(keys);
+
+            self.propagate(&mut keyspace, client.db, args).await?;
+        }
+
+        Ok(RespDataTypes::Integer(deleted as i64))
+    }
+
+    /// Number of the given keys that exist, counting a key as often as it is named. Serves
+    /// `EXISTS` and `TOUCH`, as there is no access time to update.
+    pub(super) async fn exists(&self, keys: Vec<Bytes>, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let count = keys
+            .iter()
+            .filter(|key| keyspace.contains(key, now))
+            .count();
+
+        RespDataTypes::Integer(count as i64)
+    }
+
+    /// `RENAME`, or `RENAMENX` when `nx` is set. The key keeps its expiration.
+    pub(super) async fn rename(
+        &self,
+        source: Bytes,
+        destination: Bytes,
+        nx: bool,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        if !keyspace.contains(&source, now) {
+            return Ok(RespDataTypes::SimpleError("ERR no such key".to_string()));
+        }
+
+        if source == destination || (nx && keyspace.contains(&destination, now)) {
+            return Ok(if nx {
+                RespDataTypes::Integer(0)
+            } else {
+                RespDataTypes::SimpleString("OK".to_string())
+            });
+        }
+
+        if let Some(record) = keyspace.remove(&source, now) {
+            keyspace.insert(destination.clone(), record);
+        }
+
+        let name = if nx { "RENAMENX" } else { "RENAME" };
+
+        self.propagate(
+            &mut keyspace,
+            client.db,
+            vec![name.into(), source, destination.clone()],
+        )
+        .await?;
+
+        self.serve_blocked(&mut keyspace, client.db, destination)
+            .await?;
+
+        Ok(if nx {
+            RespDataTypes::Integer(1)
+        } else {
+            RespDataTypes::SimpleString("OK".to_string())
+        })
+    }
+
+    pub(super) async fn copy(
+        &self,
+        command: CopyCommand,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let target = match command.db {
+            Some(index) => match self.get_db(index).await {
+                Ok(target) => target,
+
+                Err(e) => return Ok(RespDataTypes::SimpleError(e)),
+            },
+
+            None => self.get_selected_db(client.db).await,
+        };
+
+        if target.id() == client.db && command.source == command.destination {
+            return Ok(RespDataTypes::SimpleError(
+                "ERR source and destination objects are the same".to_string(),
+            ));
+        }
+
+        let now = self.clock.now();
+
+        let source = self.get_selected_db(client.db).await;
+
+        let (mut source_keyspace, mut target_keyspace) = if target.id() == source.id() {
+            (source.lock().await, None)
+        } else {
+            let (source_keyspace, target_keyspace) = Database::lock_pair(&source, &target).await;
+
+            (source_keyspace, Some(target_keyspace))
+        };
+
+        let copied = source_keyspace.copy_to(
+            target_keyspace.as_deref_mut(),
+            &command.source,
+            command.destination.clone(),
+            command.replace,
+            now,
+        );
+
+        if copied {
+            let mut args = vec![
+                "COPY".into(),
+                command.source,
+                command.destination.clone(),
+                "DB".into(),
+                target.id().to_string().into(),
+            ];
+
+            if command.replace {
+                args.push("REPLACE".into());
+            }
+
+            if let Some(target_keyspace) = target_keyspace.as_deref_mut() {
+                self.propagate_expired(target_keyspace, target.id()).await?;
+            }
+
+            self.propagate(&mut source_keyspace, client.db, args)
+                .await?;
+
+            let target_keyspace = match target_keyspace.as_deref_mut() {
+                Some(target_keyspace) => target_keyspace,
+
+                None => &mut source_keyspace,
+            };
+
+            self.serve_blocked(target_keyspace, target.id(), command.destination)
+                .await?;
+        }
+
+        Ok(RespDataTypes::Integer(copied as i64))
+    }
+
+    pub(super) async fn random_key(&self, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let key = db.lock().await.random_key(now);
+
+        key.map_or(RespDataTypes::Null, RespDataTypes::BulkString)
+    }
+
+    pub(super) async fn expire(
+        &self,
+        key: Bytes,
+        expiration: Expiration,
+        condition: ExpireCondition,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let Some(deadline) = expiration.deadline(now) else {
+            return Ok(invalid_expire_time(client.last_command));
+        };
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let Some(record) = keyspace.get(&key, now) else {
+            return Ok(RespDataTypes::Integer(0));
+        };
+
+        if !condition.allows(record.expires_at, deadline) {
+            return Ok(RespDataTypes::Integer(0));
+        }
+
+        // a deadline in the past deletes the key right away
+        if deadline <= now {
+            keyspace.remove(&key, now);
+        } else {
+            keyspace.set_expires_at(&key, Some(deadline));
+        }
+
+        self.propagate(
+            &mut keyspace,
+            client.db,
+            vec![
+                "PEXPIREAT".into(),
+                key,
+                deadline.timestamp_millis().to_string().into(),
+            ],
+        )
+        .await?;
+
+        Ok(RespDataTypes::Integer(1))
+    }
+
+    /// Remaining time to live: -2 when the key does not exist, -1 when it never expires.
+    pub(super) async fn ttl(&self, key: Bytes, unit: TimeUnit, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let ttl = match keyspace.get(&key, now) {
+            None => -2,
+
+            Some(record) => match record.expires_at {
+                None => -1,
+
+                Some(expires_at) => {
+                    let ms = (expires_at - now).num_milliseconds();
+
+                    match unit {
+                        TimeUnit::Seconds => (ms + 500) / 1000,
+
+                        TimeUnit::Milliseconds => ms,
+                    }
+                }
+            },
+        };
+
+        RespDataTypes::Integer(ttl)
+    }
+
+    /// Absolute expiration time, with the same -2 and -1 conventions as `TTL`.
+    pub(super) async fn expire_time(
+        &self,
+        key: Bytes,
+        unit: TimeUnit,
+        client: &Client,
+    ) -> RespDataTypes {
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let time = match keyspace.get(&key, self.clock.now()) {
+            None => -2,
+
+            Some(record) => match (record.expires_at, unit) {
+                (None, _) => -1,
+
+                (Some(expires_at), TimeUnit::Seconds) => expires_at.timestamp(),
+
+                (Some(expires_at), TimeUnit::Milliseconds) => expires_at.timestamp_millis(),
+            },
+        };
+
+        RespDataTypes::Integer(time)
+    }
+
+    pub(super) async fn persist(
+        &self,
+        key: Bytes,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let persisted = keyspace
+            .get(&key, self.clock.now())
+            .is_some_and(|record| record.expires_at.is_some());
+
+        if persisted {
+            keyspace.set_expires_at(&key, None);
+
+            self.propagate(&mut keyspace, client.db, vec!["PERSIST".into(), key])
+                .await?;
+        }
+
+        Ok(RespDataTypes::Integer(persisted as i64))
+    }
+}
@@ -0,0 +1,680 @@
+use std::collections::VecDeque;
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+
+use crate::client::Client;
+use crate::clock::Clock;
+use crate::commands::{LPosOptions, ListEnd};
+use crate::database::keyspace::Keyspace;
+use crate::database::value::{DatabaseError, Record, RedisValue};
+use crate::resp::RespDataTypes;
+
+use super::RedisService;
+
+impl RedisService {
+    /// `LPUSH` and `RPUSH`, or `LPUSHX` and `RPUSHX` with `only_existing`.
+    pub(super) async fn push(
+        &self,
+        key: Bytes,
+        elements: Vec<Bytes>,
+        end: ListEnd,
+        only_existing: bool,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        if !keyspace.contains(&key, now) {
+            if only_existing {
+                return Ok(RespDataTypes::Integer(0));
+            }
+
+            keyspace.insert(
+                key.clone(),
+                Record::new(RedisValue::List(VecDeque::new()), None),
+            );
+        }
+
+        let list = match list_mut(&mut keyspace, &key, now) {
+            Ok(Some(list)) => list,
+
+            Ok(None) => return Ok(RespDataTypes::Integer(0)),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        for element in &elements {
+            push(list, end, element.clone());
+        }
+
+        let len = list.len();
+
+        // the X variants pushed, so the list existed and a plain push does the same
+        let mut args = Vec::with_capacity(elements.len() + 2);
+
+        args.push(push_command(end).into());
+        args.push(key.clone());
+        args.extend(elements);
+
+        self.propagate(&mut keyspace, client.db, args).await?;
+
+        self.serve_blocked(&mut keyspace, client.db, key).await?;
+
+        Ok(RespDataTypes::Integer(len as i64))
+    }
+
+    /// `LPOP` and `RPOP`. With a count the reply is an array, even of one element.
+    pub(super) async fn pop(
+        &self,
+        key: Bytes,
+        end: ListEnd,
+        count: Option<usize>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let list = match list_mut(&mut keyspace, &key, now) {
+            Ok(Some(list)) => list,
+
+            Ok(None) if count.is_some() => return Ok(RespDataTypes::NullArray),
+
+            Ok(None) => return Ok(RespDataTypes::Null),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let popped: Vec<Bytes> = (0..count.unwrap_or(1))
+            .map_while(|_| pop(list, end))
+            .collect();
+
+        remove_if_empty(&mut keyspace, &key, now);
+
+        if !popped.is_empty() {
+            let mut args = vec![pop_command(end).into(), key];
+
+            if let Some(count) = count {
+                args.push(count.to_string().into());
+            }
+
+            self.propagate(&mut keyspace, client.db, args).await?;
+        }
+
+        Ok(match count {
+            Some(_) => RespDataTypes::from(popped),
+
+            None => popped
+                .into_iter()
+                .next()
+                .map_or(RespDataTypes::Null, RespDataTypes::BulkString),
+        })
+    }
+
+    pub(super) async fn lrange(
+        &self,
+        key: Bytes,
+        start: i64,
+        stop: i64,
+        client: &Client,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let list = match list(&mut keyspace, &key, now) {
+            Ok(Some(list)) => list,
+
+            Ok(None) => return RespDataTypes::Array(Vec::new()),
+
+            Err(e) => return RespDataTypes::SimpleError(e.to_string()),
+        };
+
+        let elements = match list_range(start, stop, list.len()) {
+            Some((start, stop)) => list
+                .range(start..=stop)
+                .map(|element| RespDataTypes::BulkString(element.clone()))
+                .collect(),
+
+            None => Vec::new(),
+        };
+
+        RespDataTypes::Array(elements)
+    }
+
+    pub(super) async fn lindex(&self, key: Bytes, index: i64, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        match list(&mut keyspace, &key, now) {
+            Ok(Some(list)) => list_index(index, list.len())
+                .and_then(|index| list.get(index))
+                .map_or(RespDataTypes::Null, |element| {
+                    RespDataTypes::BulkString(element.clone())
+                }),
+
+            Ok(None) => RespDataTypes::Null,
+
+            Err(e) => RespDataTypes::SimpleError(e.to_string()),
+        }
+    }
+
+    pub(super) async fn lset(
+        &self,
+        key: Bytes,
+        index: i64,
+        element: Bytes,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let list = match list_mut(&mut keyspace, &key, now) {
+            Ok(Some(list)) => list,
+
+            Ok(None) => return Ok(RespDataTypes::SimpleError("ERR no such key".to_string())),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let Some(slot) = list_index(index, list.len()).and_then(|index| list.get_mut(index)) else {
+            return Ok(RespDataTypes::SimpleError(
+                "ERR index out of range".to_string(),
+            ));
+        };
+
+        *slot = element.clone();
+
+        self.propagate(
+            &mut keyspace,
+            client.db,
+            vec!["LSET".into(), key, index.to_string().into(), element],
+        )
+        .await?;
+
+        Ok(RespDataTypes::SimpleString("OK".to_string()))
+    }
+
+    pub(super) async fn llen(&self, key: Bytes, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        match list(&mut keyspace, &key, now) {
+            Ok(list) => RespDataTypes::Integer(list.map_or(0, VecDeque::len) as i64),
+
+            Err(e) => RespDataTypes::SimpleError(e.to_string()),
+        }
+    }
+
+    /// Removes up to `count` occurrences of `element` from the head, or from the tail when
+    /// `count` is negative; all of them for 0.
+    pub(super) async fn lrem(
+        &self,
+        key: Bytes,
+        count: i64,
+        element: Bytes,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let list = match list_mut(&mut keyspace, &key, now) {
+            Ok(Some(list)) => list,
+
+            Ok(None) => return Ok(RespDataTypes::Integer(0)),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let limit = if count == 0 {
+            usize::MAX
+        } else {
+            count.unsigned_abs() as usize
+        };
+
+        let mut removed = 0;
+
+        let mut kept = VecDeque::with_capacity(list.len());
+
+        if count >= 0 {
+            for current in list.drain(..) {
+                if removed < limit && current == element {
+                    removed += 1;
+                } else {
+                    kept.push_back(current);
+                }
+            }
+        } else {
+            for current in list.drain(..).rev() {
+                if removed < limit && current == element {
+                    removed += 1;
+                } else {
+                    kept.push_front(current);
+                }
+            }
+        }
+
+        *list = kept;
+
+        remove_if_empty(&mut keyspace, &key, now);
+
+        if removed > 0 {
+            self.propagate(
+                &mut keyspace,
+                client.db,
+                vec!["LREM".into(), key, count.to_string().into(), element],
+            )
+            .await?;
+        }
+
+        Ok(RespDataTypes::Integer(removed as i64))
+    }
+
+    pub(super) async fn ltrim(
+        &self,
+        key: Bytes,
+        start: i64,
+        stop: i64,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let list = match list_mut(&mut keyspace, &key, now) {
+            Ok(Some(list)) => list,
+
+            Ok(None) => return Ok(RespDataTypes::SimpleString("OK".to_string())),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let len = list.len();
+
+        match list_range(start, stop, len) {
+            Some((start, stop)) => {
+                list.truncate(stop + 1);
+                list.drain(..start);
+            }
+
+            None => list.clear(),
+        }
+
+        let trimmed = list.len() < len;
+
+        remove_if_empty(&mut keyspace, &key, now);
+
+        if trimmed {
+            self.propagate(
+                &mut keyspace,
+                client.db,
+                vec![
+                    "LTRIM".into(),
+                    key,
+                    start.to_string().into(),
+                    stop.to_string().into(),
+                ],
+            )
+            .await?;
+        }
+
+        Ok(RespDataTypes::SimpleString("OK".to_string()))
+    }
+
+    pub(super) async fn linsert(
+        &self,
+        key: Bytes,
+        before: bool,
+        pivot: Bytes,
+        element: Bytes,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let list = match list_mut(&mut keyspace, &key, now) {
+            Ok(Some(list)) => list,
+
+            Ok(None) => return Ok(RespDataTypes::Integer(0)),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let Some(position) = list.iter().position(|current| *current == pivot) else {
+            return Ok(RespDataTypes::Integer(-1));
+        };
+
+        list.insert(
+            if before { position } else { position + 1 },
+            element.clone(),
+        );
+
+        let len = list.len();
+
+        let side = if before { "BEFORE" } else { "AFTER" };
+
+        self.propagate(
+            &mut keyspace,
+            client.db,
+            vec!["LINSERT".into(), key, side.into(), pivot, element],
+        )
+        .await?;
+
+        Ok(RespDataTypes::Integer(len as i64))
+    }
+
+    pub(super) async fn lpos(
+        &self,
+        key: Bytes,
+        element: Bytes,
+        options: LPosOptions,
+        client: &Client,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let list = match list(&mut keyspace, &key, now) {
+            Ok(Some(list)) => list,
+
+            Ok(None) if options.count.is_some() => return RespDataTypes::Array(Vec::new()),
+
+            Ok(None) => return RespDataTypes::Null,
+
+            Err(e) => return RespDataTypes::SimpleError(e.to_string()),
+        };
+
+        let len = list.len();
+
+        let compared = match options.max_len {
+            0 => len,
+
+            max_len => max_len.min(len),
+        };
+
+        let positions: Box<dyn Iterator<Item = usize>> = if options.rank > 0 {
+            Box::new(0..len)
+        } else {
+            Box::new((0..len).rev())
+        };
+
+        let limit = match options.count {
+            Some(0) => usize::MAX,
+
+            Some(count) => count,
+
+            None => 1,
+        };
+
+        let matches: Vec<usize> = positions
+            .take(compared)
+            .filter(|index| list[*index] == element)
+            .skip((options.rank.unsigned_abs() - 1) as usize)
+            .take(limit)
+            .collect();
+
+        match options.count {
+            Some(_) => RespDataTypes::Array(
+                matches
+                    .into_iter()
+                    .map(|index| RespDataTypes::Integer(index as i64))
+                    .collect(),
+            ),
+
+            None => matches.first().map_or(RespDataTypes::Null, |index| {
+                RespDataTypes::Integer(*index as i64)
+            }),
+        }
+    }
+
+    /// `LMOVE` and `RPOPLPUSH`. Source and destination may be the same list, which then
+    /// rotates.
+    pub(super) async fn lmove(
+        &self,
+        source: Bytes,
+        destination: Bytes,
+        from: ListEnd,
+        to: ListEnd,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let element = match move_element(&mut keyspace, &source, &destination, from, to, now) {
+            Ok(Some(element)) => element,
+
+            Ok(None) => return Ok(RespDataTypes::Null),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        self.propagate(
+            &mut keyspace,
+            client.db,
+            vec![
+                "LMOVE".into(),
+                source,
+                destination.clone(),
+                from.name().into(),
+                to.name().into(),
+            ],
+        )
+        .await?;
+
+        self.serve_blocked(&mut keyspace, client.db, destination)
+            .await?;
+
+        Ok(RespDataTypes::BulkString(element))
+    }
+
+    /// Pops from the first of `keys` holding a non-empty list, replying with its name and
+    /// the elements.
+    pub(super) async fn lmpop(
+        &self,
+        keys: Vec<Bytes>,
+        end: ListEnd,
+        count: usize,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        for key in keys {
+            let list = match list_mut(&mut keyspace, &key, now) {
+                Ok(Some(list)) => list,
+
+                Ok(None) => continue,
+
+                Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+            };
+
+            let popped: Vec<Bytes> = (0..count).map_while(|_| pop(list, end)).collect();
+
+            remove_if_empty(&mut keyspace, &key, now);
+
+            // replicas get the pop that actually happened
+            self.propagate(
+                &mut keyspace,
+                client.db,
+                vec![
+                    pop_command(end).into(),
+                    key.clone(),
+                    count.to_string().into(),
+                ],
+            )
+            .await?;
+
+            return Ok(RespDataTypes::Array(vec![
+                RespDataTypes::BulkString(key),
+                RespDataTypes::from(popped),
+            ]));
+        }
+
+        Ok(RespDataTypes::NullArray)
+    }
+}
+
+/// Pops from `source` and pushes onto `destination`, which is created if needed. Both types
+/// are checked before anything is popped; `None` when there is no source list.
+pub(super) fn move_element(
+    keyspace: &mut Keyspace,
+    source: &[u8],
+    destination: &Bytes,
+    from: ListEnd,
+    to: ListEnd,
+    now: DateTime<Utc>,
+) -> Result<Option<Bytes>, DatabaseError> {
+    if list(keyspace, source, now)?.is_none() {
+        return Ok(None);
+    }
+
+    list(keyspace, destination, now)?;
+
+    let Some(element) = list_mut(keyspace, source, now)?.and_then(|list| pop(list, from)) else {
+        return Ok(None);
+    };
+
+    if !keyspace.contains(destination, now) {
+        keyspace.insert(
+            destination.clone(),
+            Record::new(RedisValue::List(VecDeque::new()), None),
+        );
+    }
+
+    if let Some(list) = list_mut(keyspace, destination, now)? {
+        push(list, to, element.clone());
+    }
+
+    remove_if_empty(keyspace, source, now);
+
+    Ok(Some(element))
+}
+
+/// The list at `key`, `None` when there is no such key.
+fn list<'a>(
+    keyspace: &'a mut Keyspace,
+    key: &[u8],
+    now: DateTime<Utc>,
+) -> Result<Option<&'a VecDeque<Bytes>>, DatabaseError> {
+    keyspace
+        .get(key, now)
+        .map(|record| record.value.as_list())
+        .transpose()
+}
+
+/// The list at `key` for writing, `None` when there is no such key.
+pub(super) fn list_mut<'a>(
+    keyspace: &'a mut Keyspace,
+    key: &[u8],
+    now: DateTime<Utc>,
+) -> Result<Option<&'a mut VecDeque<Bytes>>, DatabaseError> {
+    keyspace
+        .get_value_mut(key, now)
+        .map(RedisValue::as_list_mut)
+        .transpose()
+}
+
+/// Lists are never stored empty: the key goes away with its last element.
+pub(super) fn remove_if_empty(keyspace: &mut Keyspace, key: &[u8], now: DateTime<Utc>) {
+    if let Ok(Some(list)) = list(keyspace, key, now) {
+        if list.is_empty() {
+            keyspace.remove(key, now);
+        }
+    }
+}
+
+fn push(list: &mut VecDeque<Bytes>, end: ListEnd, element: Bytes) {
+    match end {
+        ListEnd::Left => list.push_front(element),
+
+        ListEnd::Right => list.push_back(element),
+    }
+}
+
+pub(super) fn pop(list: &mut VecDeque<Bytes>, end: ListEnd) -> Option<Bytes> {
+    match end {
+        ListEnd::Left => list.pop_front(),
+
+        ListEnd::Right => list.pop_back(),
+    }
+}
+
+fn push_command(end: ListEnd) -> &'static str {
+    match end {
+        ListEnd::Left => "LPUSH",
+
+        ListEnd::Right => "RPUSH",
+    }
+}
+
+pub(super) fn pop_command(end: ListEnd) -> &'static str {
+    match end {
+        ListEnd::Left => "LPOP",
+
+        ListEnd::Right => "RPOP",
+    }
+}
+
+/// Resolves an index that counts from the tail when negative.
+fn list_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 {
+        index.checked_add(len as i64)?
+    } else {
+        index
+    };
+
+    usize::try_from(index).ok().filter(|index| *index < len)
+}
+
+/// Resolves the inclusive range of `LRANGE` and `LTRIM`, whose ends count from the tail
+/// when negative. `None` when it selects nothing.
+pub(super) fn list_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+
+    let start = if start < 0 {
+        start.saturating_add(len).max(0)
+    } else {
+        start
+    };
+
+    let stop = if stop < 0 {
+        stop.saturating_add(len)
+    } else {
+        stop.min(len - 1)
+    };
+
+    (start <= stop && start < len).then_some((start as usize, stop as usize))
+}
@@ -0,0 +1,1020 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::client::{Client, ClientRegistry};
+use crate::clock::ServerClock;
+use crate::commands::{table, ClientCommand, CommandError, Commands, DebugCommand};
+use crate::database::keyspace::Keyspace;
+use crate::database::Database;
+use crate::glob;
+use crate::persistence::persistence_interface::{Persistent, Snapshot};
+use crate::resp::RespDataTypes;
+use crate::state::server_state::ServerState;
+
+use anyhow::Context;
+use bytes::{BufMut, Bytes};
+
+mod blocking;
+mod hashes;
+mod keys;
+mod lists;
+mod scan;
+mod sets;
+mod sorted_sets;
+mod strings;
+
+use blocking::BlockedClients;
+
+/// Version reported to clients in `HELLO` and `INFO`.
+pub const REDIS_VERSION: &str = "7.2.0";
+
+/// How often the active expire cycle runs, like the default `hz 10` of Redis.
+const ACTIVE_EXPIRE_CYCLE_PERIOD: Duration = Duration::from_millis(100);
+
+/// Time one active expire cycle may spend, a quarter of its period as in Redis.
+const ACTIVE_EXPIRE_CYCLE_BUDGET: Duration = Duration::from_millis(25);
+
+/// Reply to `DEBUG` when `enable-debug-command` does not allow the client to run it.
+const DEBUG_NOT_ALLOWED: &str = "ERR DEBUG command not allowed. If the enable-debug-command \
+option is set to \"local\", you can run it from a local connection, otherwise you need to \
+set this option in the configuration file, and then restart the server.";
+
+/// Most replies of a random member command with a negative count that are allocated up
+/// front, as the count comes straight from the client.
+const MAX_RANDOM_PREALLOCATION: usize = 1024;
+
+/// Counters reported in the `stats` section of `INFO`.
+#[derive(Debug, Default)]
+struct Stats {
+    expired_keys: u64,
+
+    /// Running estimate of the share of keys with an expiration that are already expired.
+    expired_stale_perc: f64,
+}
+
+#[derive(Debug)]
+pub struct RedisService {
+    state: Arc<RwLock<ServerState>>,
+    databases: RwLock<HashMap<u32, Arc<Database>>>,
+    database_count: u32,
+    persistence: Mutex<Box<dyn Persistent>>,
+    clients: ClientRegistry,
+    blocked: BlockedClients,
+    stats: std::sync::Mutex<Stats>,
+    clock: Arc<ServerClock>,
+
+    /// Cleared by `DEBUG SET-ACTIVE-EXPIRE 0`, leaving expired keys to lazy removal.
+    active_expire_enabled: AtomicBool,
+}
+
+impl RedisService {
+    pub fn new(
+        configs: Arc<RwLock<ServerState>>,
+        mut persistent_layer: Box<dyn Persistent>,
+        database_count: u32,
+        clock: Arc<ServerClock>,
+    ) -> Self {
+        let loaded = persistent_layer
+            .load()
+            .expect("Could not load data from persistent layer");
+
+        let databases = Self::create_databases(database_count, loaded, clock.clone())
+            .expect("Could not create databases");
+
+        Self {
+            state: configs,
+            databases: RwLock::new(databases),
+            database_count,
+            persistence: Mutex::new(persistent_layer),
+            clients: ClientRegistry::default(),
+            blocked: BlockedClients::default(),
+            stats: std::sync::Mutex::new(Stats::default()),
+            clock,
+            active_expire_enabled: AtomicBool::new(true),
+        }
+    }
+
+    /// Spawns the task that removes expired keys nobody reads anymore.
+    pub fn start_active_expire(self: &Arc<Self>) {
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(ACTIVE_EXPIRE_CYCLE_PERIOD);
+
+            loop {
+                interval.tick().await;
+
+                service.active_expire_cycle().await;
+            }
+        });
+    }
+
+    async fn active_expire_cycle(&self) {
+        if !self.active_expire_enabled.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let time_limit = Instant::now() + ACTIVE_EXPIRE_CYCLE_BUDGET;
+
+        let databases: Vec<Arc<Database>> = self.databases.read().await.values().cloned().collect();
+
+        let mut sampled = 0;
+        let mut expired = 0;
+        let mut expired_keys = 0;
+
+        for db in databases {
+            let mut keyspace = db.lock().await;
+
+            let cycle = keyspace.active_expire(db.now(), time_limit);
+
+            let count = keyspace.take_expired_count();
+
+            if let Err(e) = self.propagate_expired(&mut keyspace, db.id()).await {
+                eprintln!("Could not propagate expired keys and hash fields: {e:?}");
+            }
+
+            sampled += cycle.sampled;
+            expired += cycle.expired;
+            expired_keys += count;
+        }
+
+        let current_perc = if sampled > 0 {
+            expired as f64 / sampled as f64
+        } else {
+            0.0
+        };
+
+        let mut stats = self.stats.lock().unwrap();
+
+        stats.expired_keys += expired_keys;
+        stats.expired_stale_perc = current_perc * 0.05 + stats.expired_stale_perc * 0.95;
+    }
+
+    fn stats_section(&self) -> String {
+        let stats = self.stats.lock().unwrap();
+
+        format!(
+            "# Stats\r\nexpired_keys:{}\r\nexpired_stale_perc:{:.2}\r\n",
+            stats.expired_keys,
+            stats.expired_stale_perc * 100.0
+        )
+    }
+
+    /// Fills in the databases missing from `loaded` so that `0..count` all exist.
+    fn create_databases(
+        count: u32,
+        mut loaded: HashMap<u32, Arc<Database>>,
+        clock: Arc<ServerClock>,
+    ) -> anyhow::Result<HashMap<u32, Arc<Database>>> {
+        if let Some(id) = loaded.keys().find(|id| **id >= count) {
+            anyhow::bail!("Database {id} is out of range, only {count} databases are configured");
+        }
+
+        for id in 0..count {
+            loaded
+                .entry(id)
+                .or_insert_with(|| Arc::new(Database::new(id, clock.clone())));
+        }
+
+        Ok(loaded)
+    }
+
+    /// Replaces every database with the contents of an RDB payload, as a replica does after a
+    /// full resync.
+    pub async fn restore_snapshot(&self, data: &[u8]) -> anyhow::Result<()> {
+        let loaded = self.persistence.lock().await.restore(data)?;
+
+        let databases = Self::create_databases(self.database_count, loaded, self.clock.clone())?;
+
+        *self.databases.write().await = databases;
+
+        Ok(())
+    }
+
+    /// Publishes the client's state to `CLIENT LIST` and makes it reachable by `CLIENT KILL`.
+    pub fn update_client(&self, client: &Client) {
+        self.clients.update(client);
+    }
+
+    pub fn remove_client(&self, id: u64) {
+        self.clients.remove(id);
+    }
+
+    async fn get_selected_db(&self, db: u32) -> Arc<Database> {
+        return self
+            .databases
+            .read()
+            .await
+            .get(&db)
+            .expect("No DB found")
+            .clone();
+    }
+
+    /// Looks up a database named by a client, failing like Redis when it does not exist.
+    async fn get_db(&self, index: i64) -> Result<Arc<Database>, String> {
+        u32::try_from(index)
+            .ok()
+            .filter(|index| *index < self.database_count)
+            .ok_or_else(|| "ERR DB index is out of range".to_string())?;
+
+        Ok(self.get_selected_db(index as u32).await)
+    }
+
+    async fn snapshot(&self) -> Snapshot {
+        let databases = self.databases.read().await;
+
+        let mut snapshot = Vec::with_capacity(databases.len());
+
+        for id in 0..self.database_count {
+            if let Some(db) = databases.get(&id) {
+                snapshot.push((id, db.entries().await));
+            }
+        }
+
+        snapshot
+    }
+
+    /// Sends a write to the replicas as the given command line, run against database `db`,
+    /// whose locked keyspace the caller hands in. Holding it until the command is out keeps
+    /// the replicas applying writes to a key in the order the master did. Keys and hash
+    /// fields that expired there in the meantime are deleted on the replicas first, so that
+    /// the write can not be undone by a late `DEL` or `HDEL`.
+    async fn propagate(
+        &self,
+        keyspace: &mut Keyspace,
+        db: u32,
+        args: Vec<Bytes>,
+    ) -> anyhow::Result<()> {
+        self.propagate_expired(keyspace, db).await?;
+
+        self.replicate(db, args).await
+    }
+
+    /// Sends a `DEL` for every key, and an `HDEL` for the fields of every hash, that expired
+    /// in `keyspace`, the keyspace of database `db`, since the last call, so that replicas
+    /// drop them at the same point of the command stream as the master did.
+    async fn propagate_expired(&self, keyspace: &mut Keyspace, db: u32) -> anyhow::Result<()> {
+        let keys = keyspace.take_expired_keys();
+
+        if !keys.is_empty() {
+            let mut args = Vec::with_capacity(keys.len() + 1);
+
+            args.push("DEL".into());
+            args.extend(keys);
+
+            self.replicate(db, args).await?;
+        }
+
+        for (key, fields) in keyspace.take_expired_fields() {
+            let mut args = vec!["HDEL".into(), key];
+
+            args.extend(fields);
+
+            self.replicate(db, args).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends a command line to the replicas as is.
+    async fn replicate(&self, db: u32, args: Vec<Bytes>) -> anyhow::Result<()> {
+        self.state
+            .write()
+            .await
+            .replicate_command(db, &RespDataTypes::from(args))
+            .await
+    }
+
+    pub async fn execute_command(
+        &self,
+        frame: RespDataTypes,
+        stream: Arc<Mutex<OwnedWriteHalf>>,
+        client: &mut Client,
+    ) -> anyhow::Result<()> {
+        // like Redis, empty and null multibulks are skipped without a reply
+        if matches!(&frame, RespDataTypes::Null)
+            || matches!(&frame, RespDataTypes::Array(items) if items.is_empty())
+        {
+            return Ok(());
+        }
+
+        client.begin_command();
+
+        let cmd = Commands::parse(frame);
+
+        match cmd {
+            Ok((spec, cmd)) => {
+                client.last_command = spec.name;
+
+                let response = match cmd {
+                    Commands::Ping(None) => Some(RespDataTypes::SimpleString("PONG".to_string())),
+
+                    Commands::Ping(Some(message)) => Some(RespDataTypes::BulkString(message)),
+
+                    Commands::Echo(message) => Some(RespDataTypes::BulkString(message)),
+
+                    Commands::Set(command) => Some(self.set(command, client).await?),
+
+                    Commands::Get(key) => Some(self.get(key, client).await),
+
+                    Commands::GetEx(key, update) => Some(self.getex(key, update, client).await?),
+
+                    Commands::GetDel(key) => Some(self.getdel(key, client).await?),
+
+                    Commands::GetSet(key, value) => Some(self.getset(key, value, client).await?),
+
+                    Commands::Expire(key, expiration, condition) => {
+                        Some(self.expire(key, expiration, condition, client).await?)
+                    }
+
+                    Commands::Ttl(key, unit) => Some(self.ttl(key, unit, client).await),
+
+                    Commands::ExpireTime(key, unit) => {
+                        Some(self.expire_time(key, unit, client).await)
+                    }
+
+                    Commands::Persist(key) => Some(self.persist(key, client).await?),
+
+                    Commands::Del(keys) => Some(self.del(keys, client).await?),
+
+                    Commands::Exists(keys) | Commands::Touch(keys) => {
+                        Some(self.exists(keys, client).await)
+                    }
+
+                    Commands::Rename(source, destination, nx) => {
+                        Some(self.rename(source, destination, nx, client).await?)
+                    }
+
+                    Commands::Copy(command) => Some(self.copy(command, client).await?),
+
+                    Commands::RandomKey => Some(self.random_key(client).await),
+
+                    Commands::Push(key, elements, end, only_existing) => {
+                        Some(self.push(key, elements, end, only_existing, client).await?)
+                    }
+
+                    Commands::Pop(key, end, count) => {
+                        Some(self.pop(key, end, count, client).await?)
+                    }
+
+                    Commands::LRange(key, start, stop) => {
+                        Some(self.lrange(key, start, stop, client).await)
+                    }
+
+                    Commands::LIndex(key, index) => Some(self.lindex(key, index, client).await),
+
+                    Commands::LSet(key, index, element) => {
+                        Some(self.lset(key, index, element, client).await?)
+                    }
+
+                    Commands::LLen(key) => Some(self.llen(key, client).await),
+
+                    Commands::LRem(key, count, element) => {
+                        Some(self.lrem(key, count, element, client).await?)
+                    }
+
+                    Commands::LTrim(key, start, stop) => {
+                        Some(self.ltrim(key, start, stop, client).await?)
+                    }
+
+                    Commands::LInsert(key, before, pivot, element) => {
+                        Some(self.linsert(key, before, pivot, element, client).await?)
+                    }
+
+                    Commands::LPos(key, element, options) => {
+                        Some(self.lpos(key, element, options, client).await)
+                    }
+
+                    Commands::LMove(source, destination, from, to) => {
+                        Some(self.lmove(source, destination, from, to, client).await?)
+                    }
+
+                    Commands::LMPop(keys, end, count) => {
+                        Some(self.lmpop(keys, end, count, client).await?)
+                    }
+
+                    Commands::Blocking(keys, op, timeout) => {
+                        Some(self.block(keys, op, timeout, &stream, client).await?)
+                    }
+
+                    Commands::HSet(key, pairs) => Some(self.hset(key, pairs, client).await?),
+
+                    Commands::HSetNx(key, field, value) => {
+                        Some(self.hsetnx(key, field, value, client).await?)
+                    }
+
+                    Commands::HGet(key, field) => Some(self.hget(key, field, client).await),
+
+                    Commands::HMGet(key, fields) => Some(self.hmget(key, fields, client).await),
+
+                    Commands::HDel(key, fields) => Some(self.hdel(key, fields, client).await?),
+
+                    Commands::HExists(key, field) => Some(self.hexists(key, field, client).await),
+
+                    Commands::HLen(key) => Some(self.hlen(key, client).await),
+
+                    Commands::HStrLen(key, field) => Some(self.hstrlen(key, field, client).await),
+
+                    Commands::HKeys(key) => Some(self.hgetall(key, true, false, client).await),
+
+                    Commands::HVals(key) => Some(self.hgetall(key, false, true, client).await),
+
+                    Commands::HGetAll(key) => Some(self.hgetall(key, true, true, client).await),
+
+                    Commands::HIncrBy(key, field, increment) => {
+                        Some(self.hincrby(key, field, increment, client).await?)
+                    }
+
+                    Commands::HIncrByFloat(key, field, increment) => {
+                        Some(self.hincrbyfloat(key, field, increment, client).await?)
+                    }
+
+                    Commands::HRandField(key, count, with_values) => {
+                        Some(self.hrandfield(key, count, with_values, client).await)
+                    }
+
+                    Commands::HExpire(key, expiration, condition, fields) => Some(
+                        self.hexpire(key, expiration, condition, fields, client)
+                            .await?,
+                    ),
+
+                    Commands::HTtl(key, unit, fields) => {
+                        Some(self.httl(key, unit, fields, client).await)
+                    }
+
+                    Commands::HExpireTime(key, unit, fields) => {
+                        Some(self.hexpire_time(key, unit, fields, client).await)
+                    }
+
+                    Commands::HPersist(key, fields) => {
+                        Some(self.hpersist(key, fields, client).await?)
+                    }
+
+                    Commands::HGetEx(key, update, fields) => {
+                        Some(self.hgetex(key, update, fields, client).await?)
+                    }
+
+                    Commands::HSetEx(command) => Some(self.hsetex(command, client).await?),
+
+                    Commands::SAdd(key, members) => Some(self.sadd(key, members, client).await?),
+
+                    Commands::SRem(key, members) => Some(self.srem(key, members, client).await?),
+
+                    Commands::SIsMember(key, member) => {
+                        Some(self.sismember(key, vec![member], false, client).await)
+                    }
+
+                    Commands::SMIsMember(key, members) => {
+                        Some(self.sismember(key, members, true, client).await)
+                    }
+
+                    Commands::SMembers(key) => Some(self.smembers(key, client).await),
+
+                    Commands::SCard(key) => Some(self.scard(key, client).await),
+
+                    Commands::SPop(key, count) => Some(self.spop(key, count, client).await?),
+
+                    Commands::SRandMember(key, count) => {
+                        Some(self.srandmember(key, count, client).await)
+                    }
+
+                    Commands::SMove(source, destination, member) => {
+                        Some(self.smove(source, destination, member, client).await?)
+                    }
+
+                    Commands::SetAlgebra(operation, destination, keys) => Some(
+                        self.set_algebra(operation, destination, keys, client)
+                            .await?,
+                    ),
+
+                    Commands::SInterCard(keys, limit) => {
+                        Some(self.sintercard(keys, limit, client).await)
+                    }
+
+                    Commands::ZAdd(command) => Some(self.zadd(command, client).await?),
+
+                    Commands::ZRem(key, members) => Some(self.zrem(key, members, client).await?),
+
+                    Commands::ZScore(key, member) => {
+                        Some(self.zscore(key, vec![member], false, client).await)
+                    }
+
+                    Commands::ZMScore(key, members) => {
+                        Some(self.zscore(key, members, true, client).await)
+                    }
+
+                    Commands::ZCard(key) => Some(self.zcard(key, client).await),
+
+                    Commands::ZCount(key, range) => Some(self.zcount(key, range, client).await),
+
+                    Commands::ZRank(key, member, rev, with_score) => {
+                        Some(self.zrank(key, member, rev, with_score, client).await)
+                    }
+
+                    Commands::ZRange(command) => Some(self.zrange(command, client).await),
+
+                    Commands::ZRemRange(key, range) => {
+                        Some(self.zremrange(key, range, client).await?)
+                    }
+
+                    Commands::ZPop(key, max, count) => {
+                        Some(self.zpop(key, max, count, client).await?)
+                    }
+
+                    Commands::ZRandMember(key, count, with_scores) => {
+                        Some(self.zrandmember(key, count, with_scores, client).await)
+                    }
+
+                    Commands::ZMPop(keys, max, count) => {
+                        Some(self.zmpop(keys, max, count, client).await?)
+                    }
+
+                    Commands::ZSetAlgebra(command) => {
+                        Some(self.zset_algebra(command, client).await?)
+                    }
+
+                    Commands::ZRangeStore(destination, command) => {
+                        Some(self.zrangestore(destination, command, client).await?)
+                    }
+
+                    Commands::ZInterCard(keys, limit) => {
+                        Some(self.zintercard(keys, limit, client).await)
+                    }
+
+                    Commands::Scan(command) => Some(self.scan(command, client).await),
+
+                    Commands::HScan(key, command) => {
+                        Some(self.scan_collection(key, "hash", command, client).await)
+                    }
+
+                    Commands::SScan(key, command) => {
+                        Some(self.scan_collection(key, "set", command, client).await)
+                    }
+
+                    Commands::ZScan(key, command) => {
+                        Some(self.scan_collection(key, "zset", command, client).await)
+                    }
+
+                    Commands::Type(key) => {
+                        let db = self.get_selected_db(client.db).await;
+
+                        Some(RespDataTypes::SimpleString(
+                            db.type_of(&key).await.to_string(),
+                        ))
+                    }
+
+                    Commands::Keys(key) => {
+                        let db = self.get_selected_db(client.db).await;
+
+                        let result_vec = if key.as_ref() == b"*" {
+                            db.keys().await
+                        } else {
+                            db.keys_matching(&key).await
+                        };
+
+                        Some(RespDataTypes::from(result_vec))
+                    }
+
+                    Commands::ConfigGet(parameters) => {
+                        let mut res = Vec::new();
+
+                        let state = self.state.read().await;
+
+                        // every parameter matching any of the patterns is listed once; patterns
+                        // that match nothing are left out of the reply, like Redis does
+                        for &name in state.config_parameters() {
+                            let requested = parameters.iter().any(|pattern| {
+                                glob::matches(pattern.as_bytes(), name.as_bytes(), true)
+                            });
+
+                            if let Some(value) = state.get_from_config(name).filter(|_| requested) {
+                                res.push((
+                                    RespDataTypes::BulkString(name.into()),
+                                    RespDataTypes::BulkString(value.into()),
+                                ));
+                            }
+                        }
+
+                        Some(RespDataTypes::Map(res))
+                    }
+
+                    Commands::Info(section) => {
+                        let section = section.unwrap_or("default".to_string()).to_lowercase();
+
+                        let result = match section.as_str() {
+                            "stats" => self.stats_section(),
+
+                            "replication" => self.state.read().await.get_replication_status(),
+
+                            "default" | "all" | "everything" => format!(
+                                "{}\r\n{}",
+                                self.stats_section(),
+                                self.state.read().await.get_replication_status()
+                            ),
+
+                            _ => String::new(),
+                        };
+
+                        Some(RespDataTypes::Verbatim("txt".to_string(), result))
+                    }
+
+                    Commands::REPLCONF(op1, op2) => {
+                        println!("REPLCONF {op1} {op2}");
+
+                        if op1.to_lowercase() == "listening-port" {
+                            let mut server_state = self.state.write().await;
+
+                            server_state.register_replica(stream.clone()).await?;
+
+                            client.flags.replica = true;
+                        }
+
+                        Some(RespDataTypes::SimpleString("OK".to_string()))
+                    }
+
+                    Commands::PSYNC(op1, op2) => {
+                        println!("PSYNC {op1} {op2}");
+
+                        let server_state = self.state.read().await;
+
+                        let res = server_state.psync()?;
+
+                        client.add_reply(&res);
+
+                        drop(server_state);
+
+                        let snapshot = self.snapshot().await;
+
+                        let buffer = self.persistence.lock().await.dump(&snapshot)?;
+
+                        // the snapshot is sent like a bulk string without the trailing CRLF
+                        client
+                            .output
+                            .put_slice(format!("${}\r\n", buffer.len()).as_bytes());
+                        client.output.put_slice(&buffer);
+
+                        // flushed right away so the replica gets its snapshot before any
+                        // command propagated to it from another connection
+                        let mut stream_guard = stream.lock().await;
+
+                        stream_guard
+                            .write_all(&client.output)
+                            .await
+                            .with_context(|| "could not write to stream")?;
+
+                        client.clear_output();
+
+                        None
+                    }
+
+                    Commands::Command(request) => Some(table::introspect(request)),
+
+                    Commands::Client(request) => self.client_command(request, client),
+
+                    Commands::Select(index) => match self.get_db(index).await {
+                        Ok(db) => {
+                            client.db = db.id();
+
+                            Some(RespDataTypes::SimpleString("OK".to_string()))
+                        }
+
+                        Err(e) => Some(RespDataTypes::SimpleError(e)),
+                    },
+
+                    Commands::SwapDb(first, second) => {
+                        match (self.get_db(first).await, self.get_db(second).await) {
+                            (Ok(first_db), Ok(second_db)) => {
+                                let args = vec![
+                                    "SWAPDB".into(),
+                                    first.to_string().into(),
+                                    second.to_string().into(),
+                                ];
+
+                                if first_db.id() == second_db.id() {
+                                    self.replicate(client.db, args).await?;
+                                } else {
+                                    let (mut first_keyspace, mut second_keyspace) =
+                                        Database::lock_pair(&first_db, &second_db).await;
+
+                                    // keys and fields that expired before the swap are
+                                    // named by the database they expired in
+                                    self.propagate_expired(&mut first_keyspace, first_db.id())
+                                        .await?;
+                                    self.propagate_expired(&mut second_keyspace, second_db.id())
+                                        .await?;
+
+                                    std::mem::swap(&mut *first_keyspace, &mut *second_keyspace);
+
+                                    self.replicate(client.db, args).await?;
+
+                                    // clients blocked on either database may find their keys
+                                    // now
+                                    self.serve_blocked_in(&mut first_keyspace, first_db.id())
+                                        .await?;
+                                    self.serve_blocked_in(&mut second_keyspace, second_db.id())
+                                        .await?;
+                                }
+
+                                Some(RespDataTypes::SimpleString("OK".to_string()))
+                            }
+
+                            (Err(e), _) | (_, Err(e)) => Some(RespDataTypes::SimpleError(e)),
+                        }
+                    }
+
+                    Commands::Move(key, index) => match self.get_db(index).await {
+                        Ok(target) if target.id() == client.db => Some(RespDataTypes::SimpleError(
+                            "ERR source and destination objects are the same".to_string(),
+                        )),
+
+                        Ok(target) => {
+                            let source = self.get_selected_db(client.db).await;
+
+                            let now = source.now();
+
+                            let (mut source_keyspace, mut target_keyspace) =
+                                Database::lock_pair(&source, &target).await;
+
+                            let moved = source_keyspace.move_to(&mut target_keyspace, &key, now);
+
+                            if moved {
+                                self.propagate_expired(&mut target_keyspace, target.id())
+                                    .await?;
+
+                                self.propagate(
+                                    &mut source_keyspace,
+                                    client.db,
+                                    vec!["MOVE".into(), key.clone(), index.to_string().into()],
+                                )
+                                .await?;
+
+                                self.serve_blocked(&mut target_keyspace, target.id(), key)
+                                    .await?;
+                            }
+
+                            Some(RespDataTypes::Integer(moved as i64))
+                        }
+
+                        Err(e) => Some(RespDataTypes::SimpleError(e)),
+                    },
+
+                    Commands::FlushDb(lazy) => {
+                        let db = self.get_selected_db(client.db).await;
+
+                        let mut keyspace = db.lock().await;
+
+                        let contents = keyspace.clear();
+
+                        self.propagate(&mut keyspace, client.db, vec!["FLUSHDB".into()])
+                            .await?;
+
+                        drop(keyspace);
+
+                        Self::free_contents(contents, lazy);
+
+                        Some(RespDataTypes::SimpleString("OK".to_string()))
+                    }
+
+                    Commands::FlushAll(lazy) => {
+                        let databases: Vec<Arc<Database>> = {
+                            let databases = self.databases.read().await;
+
+                            (0..self.database_count)
+                                .filter_map(|id| databases.get(&id).cloned())
+                                .collect()
+                        };
+
+                        // every database stays locked until the command is out, taken in id
+                        // order like `Database::lock_pair` does
+                        let mut keyspaces = Vec::with_capacity(databases.len());
+
+                        for db in &databases {
+                            keyspaces.push(db.lock().await);
+                        }
+
+                        for keyspace in keyspaces.iter_mut() {
+                            Self::free_contents(keyspace.clear(), lazy);
+                        }
+
+                        self.replicate(client.db, vec!["FLUSHALL".into()]).await?;
+
+                        drop(keyspaces);
+
+                        Some(RespDataTypes::SimpleString("OK".to_string()))
+                    }
+
+                    Commands::DbSize => {
+                        let db = self.get_selected_db(client.db).await;
+
+                        Some(RespDataTypes::Integer(db.len().await as i64))
+                    }
+
+                    Commands::Save => {
+                        let snapshot = self.snapshot().await;
+
+                        match self.persistence.lock().await.save(&snapshot) {
+                            Ok(()) => Some(RespDataTypes::SimpleString("OK".to_string())),
+
+                            Err(e) => {
+                                eprintln!("Could not save rdb file: {e:?}");
+
+                                Some(RespDataTypes::SimpleError(format!("ERR {e:#}")))
+                            }
+                        }
+                    }
+
+                    Commands::Debug(request) => {
+                        if self.state.read().await.debug_command_allowed(&client.addr) {
+                            Some(self.debug_command(request))
+                        } else {
+                            Some(RespDataTypes::SimpleError(DEBUG_NOT_ALLOWED.to_string()))
+                        }
+                    }
+
+                    Commands::Hello(protocol, auth, name) => {
+                        if let Some((user, _)) = auth {
+                            // there are no ACL users yet, so only the password-less default
+                            // user can authenticate
+                            if user != "default" {
+                                client.add_reply(&RespDataTypes::SimpleError(
+                                    "WRONGPASS invalid username-password pair or user is disabled."
+                                        .to_string(),
+                                ));
+
+                                return Ok(());
+                            }
+
+                            client.user = user;
+                        }
+
+                        if let Some(protocol) = protocol {
+                            client.protocol = protocol;
+                        }
+
+                        if let Some(name) = name {
+                            client.name = Some(name).filter(|name| !name.is_empty());
+                        }
+
+                        let role = self.state.read().await.get_role_name();
+
+                        Some(RespDataTypes::Map(vec![
+                            (
+                                RespDataTypes::BulkString("server".into()),
+                                RespDataTypes::BulkString("redis".into()),
+                            ),
+                            (
+                                RespDataTypes::BulkString("version".into()),
+                                RespDataTypes::BulkString(REDIS_VERSION.into()),
+                            ),
+                            (
+                                RespDataTypes::BulkString("proto".into()),
+                                RespDataTypes::Integer(client.protocol.as_number()),
+                            ),
+                            (
+                                RespDataTypes::BulkString("id".into()),
+                                RespDataTypes::Integer(client.id as i64),
+                            ),
+                            (
+                                RespDataTypes::BulkString("mode".into()),
+                                RespDataTypes::BulkString("standalone".into()),
+                            ),
+                            (
+                                RespDataTypes::BulkString("role".into()),
+                                RespDataTypes::BulkString(role.into()),
+                            ),
+                            (
+                                RespDataTypes::BulkString("modules".into()),
+                                RespDataTypes::Array(Vec::new()),
+                            ),
+                        ]))
+                    }
+                };
+
+                if let Some(resp) = response {
+                    client.add_reply(&resp);
+                }
+
+                // Ok(())
+            }
+
+            Err(message) => {
+                client.add_reply(&RespDataTypes::SimpleError(message.to_string()));
+            }
+        };
+
+        Ok(())
+    }
+
+    fn client_command(&self, request: ClientCommand, client: &mut Client) -> Option<RespDataTypes> {
+        match request {
+            ClientCommand::Id => Some(RespDataTypes::Integer(client.id as i64)),
+
+            ClientCommand::SetName(name) => {
+                client.name = name;
+
+                Some(RespDataTypes::SimpleString("OK".to_string()))
+            }
+
+            ClientCommand::GetName => {
+                Some(client.name.clone().map_or(RespDataTypes::Null, |name| {
+                    RespDataTypes::BulkString(name.into())
+                }))
+            }
+
+            ClientCommand::List(kind, ids) => {
+                let list = self
+                    .clients
+                    .snapshot(client)
+                    .into_iter()
+                    .filter(|info| kind.is_none_or(|kind| kind == info.kind))
+                    .filter(|info| ids.is_empty() || ids.contains(&info.id))
+                    .map(|info| info.to_line() + "\n")
+                    .collect();
+
+                Some(RespDataTypes::Verbatim("txt".to_string(), list))
+            }
+
+            ClientCommand::Info => Some(RespDataTypes::Verbatim(
+                "txt".to_string(),
+                client.info().to_line() + "\n",
+            )),
+
+            ClientCommand::Kill(filter) => {
+                let mut killed = 0;
+
+                for info in self.clients.snapshot(client) {
+                    if !filter.matches(&info, client.id) {
+                        continue;
+                    }
+
+                    // the current connection is closed once this reply is written
+                    if info.id == client.id {
+                        client.flags.close_after_reply = true;
+
+                        killed += 1;
+                    } else if self.clients.kill(info.id) {
+                        killed += 1;
+                    }
+                }
+
+                if !filter.legacy {
+                    Some(RespDataTypes::Integer(killed))
+                } else if killed > 0 {
+                    Some(RespDataTypes::SimpleString("OK".to_string()))
+                } else {
+                    Some(RespDataTypes::SimpleError("ERR No such client".to_string()))
+                }
+            }
+
+            ClientCommand::Reply(mode) => {
+                // only `ON` gets to see this reply, the other modes drop it right away
+                client.set_reply_mode(mode);
+
+                Some(RespDataTypes::SimpleString("OK".to_string()))
+            }
+
+            ClientCommand::Unblock(id, error) => {
+                Some(RespDataTypes::Integer(self.unblock(id, error) as i64))
+            }
+        }
+    }
+
+    fn debug_command(&self, request: DebugCommand) -> RespDataTypes {
+        match request {
+            DebugCommand::SetActiveExpire(enabled) => {
+                self.active_expire_enabled.store(enabled, Ordering::Relaxed);
+            }
+
+            DebugCommand::FreezeTime(at) => self.clock.freeze(at),
+
+            DebugCommand::UnfreezeTime => self.clock.unfreeze(),
+
+            DebugCommand::AdvanceTime(milliseconds) => {
+                if !self.clock.advance(Duration::from_millis(milliseconds)) {
+                    return RespDataTypes::SimpleError("ERR time is not frozen".to_string());
+                }
+            }
+        }
+
+        RespDataTypes::SimpleString("OK".to_string())
+    }
+
+    /// Drops the contents of a flushed database, on a blocking thread when `lazy` so that
+    /// freeing a large keyspace does not stall the connection.
+    fn free_contents(contents: Keyspace, lazy: bool) {
+        if lazy {
+            tokio::task::spawn_blocking(move || drop(contents));
+        } else {
+            drop(contents);
+        }
+    }
+}
+
+fn invalid_expire_time(command: &str) -> RespDataTypes {
+    RespDataTypes::SimpleError(CommandError::InvalidExpireTime(command.to_string()).to_string())
+}
@@ -0,0 +1,115 @@
+use bytes::Bytes;
+
+use crate::client::Client;
+use crate::clock::Clock;
+use crate::commands::ScanCommand;
+use crate::database::value::{DatabaseError, RedisValue};
+use crate::glob;
+use crate::resp::{format_double, RespDataTypes};
+
+use super::RedisService;
+
+impl RedisService {
+    pub(super) async fn scan(&self, command: ScanCommand, client: &Client) -> RespDataTypes {
+        let db = self.get_selected_db(client.db).await;
+
+        let (next, keys) = db
+            .scan(
+                command.cursor,
+                command.count,
+                command.pattern.as_deref(),
+                command.kind.as_deref(),
+            )
+            .await;
+
+        scan_reply(
+            next,
+            keys.into_iter().map(RespDataTypes::BulkString).collect(),
+        )
+    }
+
+    /// `HSCAN`, `SSCAN` and `ZSCAN`: one step over the elements of the collection at `key`,
+    /// which must be of type `kind`. Hashes reply with field-value pairs, unless `NOVALUES`
+    /// is given, and sorted sets with member-score pairs.
+    pub(super) async fn scan_collection(
+        &self,
+        key: Bytes,
+        kind: &str,
+        command: ScanCommand,
+        client: &Client,
+    ) -> RespDataTypes {
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let Some(record) = keyspace.get(&key, self.clock.now()) else {
+            return scan_reply(0, Vec::new());
+        };
+
+        if record.value.type_name() != kind {
+            return RespDataTypes::SimpleError(DatabaseError::WrongType.to_string());
+        }
+
+        let matches = |element: &Bytes| {
+            command
+                .pattern
+                .as_ref()
+                .is_none_or(|p| glob::matches(p, element, false))
+        };
+
+        let (next, elements) = match &record.value {
+            RedisValue::Hash(hash) => {
+                let (next, page) = hash.scan(command.cursor, command.count);
+
+                let mut elements = Vec::new();
+
+                for (field, value) in page.into_iter().filter(|(field, _)| matches(field)) {
+                    elements.push(RespDataTypes::BulkString(field.clone()));
+
+                    if !command.no_values {
+                        elements.push(RespDataTypes::BulkString(value.clone()));
+                    }
+                }
+
+                (next, elements)
+            }
+
+            RedisValue::Set(set) => {
+                let (next, page) = set.scan(command.cursor, command.count);
+
+                let elements = page
+                    .into_iter()
+                    .filter(|member| matches(member))
+                    .map(|member| RespDataTypes::BulkString(member.clone()))
+                    .collect();
+
+                (next, elements)
+            }
+
+            RedisValue::ZSet(zset) => {
+                let (next, page) = zset.scan(command.cursor, command.count);
+
+                let mut elements = Vec::new();
+
+                for (member, score) in page.into_iter().filter(|(member, _)| matches(member)) {
+                    elements.push(RespDataTypes::BulkString(member.clone()));
+                    elements.push(RespDataTypes::BulkString(format_double(score).into()));
+                }
+
+                (next, elements)
+            }
+
+            _ => (0, Vec::new()),
+        };
+
+        scan_reply(next, elements)
+    }
+}
+
+/// The reply of every scan command: the next cursor as a string, then the elements.
+fn scan_reply(cursor: u64, elements: Vec<RespDataTypes>) -> RespDataTypes {
+    RespDataTypes::Array(vec![
+        RespDataTypes::BulkString(cursor.to_string().into()),
+        RespDataTypes::Array(elements),
+    ])
+}
@@ -0,0 +1,509 @@
+use std::collections::HashSet;
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use rand::seq::{IndexedRandom, IteratorRandom, SliceRandom};
+
+use crate::client::Client;
+use crate::clock::Clock;
+use crate::commands::SetOperation;
+use crate::database::keyspace::Keyspace;
+use crate::database::value::{DatabaseError, Record, RedisValue, Set};
+use crate::resp::RespDataTypes;
+
+use super::{RedisService, MAX_RANDOM_PREALLOCATION};
+
+impl RedisService {
+    /// Adds the members, replying with how many of them are new.
+    pub(super) async fn sadd(
+        &self,
+        key: Bytes,
+        members: Vec<Bytes>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let set = match set_or_create(&mut keyspace, &key, now) {
+            Ok(set) => set,
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let added = members
+            .iter()
+            .filter(|member| set.insert((*member).clone()))
+            .count();
+
+        if added > 0 {
+            let mut args = vec!["SADD".into(), key];
+
+            args.extend(members);
+
+            self.propagate(&mut keyspace, client.db, args).await?;
+        }
+
+        Ok(RespDataTypes::Integer(added as i64))
+    }
+
+    pub(super) async fn srem(
+        &self,
+        key: Bytes,
+        members: Vec<Bytes>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let set = match set_mut(&mut keyspace, &key, now) {
+            Ok(Some(set)) => set,
+
+            Ok(None) => return Ok(RespDataTypes::Integer(0)),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let removed = members.iter().filter(|member| set.remove(member)).count();
+
+        remove_if_empty(&mut keyspace, &key, now);
+
+        if removed > 0 {
+            let mut args = vec!["SREM".into(), key];
+
+            args.extend(members);
+
+            self.propagate(&mut keyspace, client.db, args).await?;
+        }
+
+        Ok(RespDataTypes::Integer(removed as i64))
+    }
+
+    /// `SISMEMBER` and `SMISMEMBER`, which replies with an array even for one member.
+    pub(super) async fn sismember(
+        &self,
+        key: Bytes,
+        members: Vec<Bytes>,
+        multiple: bool,
+        client: &Client,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let set = match set(&mut keyspace, &key, now) {
+            Ok(set) => set,
+
+            Err(e) => return RespDataTypes::SimpleError(e.to_string()),
+        };
+
+        let mut replies: Vec<RespDataTypes> = members
+            .iter()
+            .map(
+                |member| RespDataTypes::Integer(set.is_some_and(|set| set.contains(member)) as i64),
+            )
+            .collect();
+
+        match multiple {
+            true => RespDataTypes::Array(replies),
+
+            false => replies.remove(0),
+        }
+    }
+
+    pub(super) async fn smembers(&self, key: Bytes, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        match set(&mut keyspace, &key, now) {
+            Ok(set) => RespDataTypes::Set(
+                set.into_iter()
+                    .flat_map(Set::iter)
+                    .map(|member| RespDataTypes::BulkString(member.clone()))
+                    .collect(),
+            ),
+
+            Err(e) => RespDataTypes::SimpleError(e.to_string()),
+        }
+    }
+
+    pub(super) async fn scard(&self, key: Bytes, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        match set(&mut keyspace, &key, now) {
+            Ok(set) => RespDataTypes::Integer(set.map_or(0, Set::len) as i64),
+
+            Err(e) => RespDataTypes::SimpleError(e.to_string()),
+        }
+    }
+
+    /// Removes random members. Replicas are told which ones with an `SREM`, so that they
+    /// never make random choices of their own.
+    pub(super) async fn spop(
+        &self,
+        key: Bytes,
+        count: Option<usize>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let set = match set_mut(&mut keyspace, &key, now) {
+            Ok(Some(set)) => set,
+
+            Ok(None) if count.is_some() => return Ok(RespDataTypes::Set(Vec::new())),
+
+            Ok(None) => return Ok(RespDataTypes::Null),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let popped = {
+            let mut rng = rand::rng();
+
+            let count = count.unwrap_or(1).min(set.len());
+
+            let mut popped: Vec<Bytes> = set
+                .iter()
+                .choose_multiple(&mut rng, count)
+                .into_iter()
+                .cloned()
+                .collect();
+
+            popped.shuffle(&mut rng);
+
+            popped
+        };
+
+        for member in &popped {
+            set.remove(member);
+        }
+
+        remove_if_empty(&mut keyspace, &key, now);
+
+        if !popped.is_empty() {
+            let mut args = vec!["SREM".into(), key];
+
+            args.extend(popped.iter().cloned());
+
+            self.propagate(&mut keyspace, client.db, args).await?;
+        }
+
+        Ok(match count {
+            Some(_) => {
+                RespDataTypes::Set(popped.into_iter().map(RespDataTypes::BulkString).collect())
+            }
+
+            None => popped
+                .into_iter()
+                .next()
+                .map_or(RespDataTypes::Null, RespDataTypes::BulkString),
+        })
+    }
+
+    /// Without a count, a single member or null. A positive count picks distinct members, a
+    /// negative one picks that many members that may repeat.
+    pub(super) async fn srandmember(
+        &self,
+        key: Bytes,
+        count: Option<i64>,
+        client: &Client,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let set = match set(&mut keyspace, &key, now) {
+            Ok(set) => set,
+
+            Err(e) => return RespDataTypes::SimpleError(e.to_string()),
+        };
+
+        let mut rng = rand::rng();
+
+        let Some(count) = count else {
+            return set
+                .and_then(|set| set.iter().choose(&mut rng))
+                .map_or(RespDataTypes::Null, |member| {
+                    RespDataTypes::BulkString(member.clone())
+                });
+        };
+
+        let Some(set) = set else {
+            return RespDataTypes::Array(Vec::new());
+        };
+
+        let picked: Vec<&Bytes> = if count >= 0 {
+            let count = (count as usize).min(set.len());
+
+            let mut picked = set.iter().choose_multiple(&mut rng, count);
+
+            picked.shuffle(&mut rng);
+
+            picked
+        } else {
+            let members: Vec<&Bytes> = set.iter().collect();
+
+            let count = count.unsigned_abs() as usize;
+
+            let mut picked = Vec::with_capacity(count.min(MAX_RANDOM_PREALLOCATION));
+
+            picked.extend((0..count).filter_map(|_| members.choose(&mut rng).copied()));
+
+            picked
+        };
+
+        RespDataTypes::Array(
+            picked
+                .into_iter()
+                .map(|member| RespDataTypes::BulkString(member.clone()))
+                .collect(),
+        )
+    }
+
+    /// Moves `member` between sets, replying with 1 when it was in `source`.
+    pub(super) async fn smove(
+        &self,
+        source: Bytes,
+        destination: Bytes,
+        member: Bytes,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let contained = match set(&mut keyspace, &source, now) {
+            Ok(Some(set)) => set.contains(&member),
+
+            Ok(None) => return Ok(RespDataTypes::Integer(0)),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        if let Err(e) = set(&mut keyspace, &destination, now) {
+            return Ok(RespDataTypes::SimpleError(e.to_string()));
+        }
+
+        if !contained || source == destination {
+            return Ok(RespDataTypes::Integer(contained as i64));
+        }
+
+        if let Ok(Some(set)) = set_mut(&mut keyspace, &source, now) {
+            set.remove(&member);
+        }
+
+        remove_if_empty(&mut keyspace, &source, now);
+
+        set_or_create(&mut keyspace, &destination, now)?.insert(member.clone());
+
+        self.propagate(
+            &mut keyspace,
+            client.db,
+            vec!["SMOVE".into(), source, destination, member],
+        )
+        .await?;
+
+        Ok(RespDataTypes::Integer(1))
+    }
+
+    /// `SINTER`, `SUNION`, `SDIFF`, and their `STORE` variants when given a destination,
+    /// which is deleted rather than stored empty.
+    pub(super) async fn set_algebra(
+        &self,
+        operation: SetOperation,
+        destination: Option<Bytes>,
+        keys: Vec<Bytes>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let result = match combine(&mut keyspace, operation, &keys, now) {
+            Ok(result) => result,
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let Some(destination) = destination else {
+            return Ok(RespDataTypes::Set(
+                result.into_iter().map(RespDataTypes::BulkString).collect(),
+            ));
+        };
+
+        let len = result.len();
+
+        if result.is_empty() {
+            keyspace.remove(&destination, now);
+        } else {
+            keyspace.insert(
+                destination.clone(),
+                Record::new(RedisValue::Set(result.into_iter().collect()), None),
+            );
+        }
+
+        let mut args = vec![operation.store_command().into(), destination];
+
+        args.extend(keys);
+
+        self.propagate(&mut keyspace, client.db, args).await?;
+
+        Ok(RespDataTypes::Integer(len as i64))
+    }
+
+    /// Size of the intersection, counting no further than `limit` unless it is 0.
+    pub(super) async fn sintercard(
+        &self,
+        keys: Vec<Bytes>,
+        limit: usize,
+        client: &Client,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        match combine(&mut keyspace, SetOperation::Inter, &keys, now) {
+            Ok(result) if limit > 0 => RespDataTypes::Integer(result.len().min(limit) as i64),
+
+            Ok(result) => RespDataTypes::Integer(result.len() as i64),
+
+            Err(e) => RespDataTypes::SimpleError(e.to_string()),
+        }
+    }
+}
+
+/// Combines the sets at `keys` with `operation`, keys that do not exist counting as empty
+/// sets. Fails when any of them holds something else than a set.
+fn combine(
+    keyspace: &mut Keyspace,
+    operation: SetOperation,
+    keys: &[Bytes],
+    now: DateTime<Utc>,
+) -> Result<HashSet<Bytes>, DatabaseError> {
+    let mut sizes = Vec::with_capacity(keys.len());
+
+    for key in keys {
+        sizes.push(set(keyspace, key, now)?.map_or(0, Set::len));
+    }
+
+    let mut result = HashSet::new();
+
+    match operation {
+        SetOperation::Union => {
+            for key in keys {
+                if let Some(set) = set(keyspace, key, now)? {
+                    result.extend(set.iter().cloned());
+                }
+            }
+        }
+
+        // the smallest set is filtered by every other one
+        SetOperation::Inter => {
+            let Some((smallest, _)) = sizes.iter().enumerate().min_by_key(|(_, size)| **size)
+            else {
+                return Ok(result);
+            };
+
+            if let Some(set) = set(keyspace, &keys[smallest], now)? {
+                result = set.iter().cloned().collect();
+            }
+
+            for (i, key) in keys.iter().enumerate() {
+                if i != smallest && !result.is_empty() {
+                    let other = set(keyspace, key, now)?;
+
+                    result.retain(|member| other.is_some_and(|other| other.contains(member)));
+                }
+            }
+        }
+
+        SetOperation::Diff => {
+            if let Some(set) = set(keyspace, &keys[0], now)? {
+                result = set.iter().cloned().collect();
+            }
+
+            for key in &keys[1..] {
+                if let Some(other) = set(keyspace, key, now)? {
+                    result.retain(|member| !other.contains(member));
+                }
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// The set at `key`, `None` when there is no such key.
+fn set<'a>(
+    keyspace: &'a mut Keyspace,
+    key: &[u8],
+    now: DateTime<Utc>,
+) -> Result<Option<&'a Set>, DatabaseError> {
+    keyspace
+        .get(key, now)
+        .map(|record| record.value.as_set())
+        .transpose()
+}
+
+/// The set at `key` for writing, `None` when there is no such key.
+fn set_mut<'a>(
+    keyspace: &'a mut Keyspace,
+    key: &[u8],
+    now: DateTime<Utc>,
+) -> Result<Option<&'a mut Set>, DatabaseError> {
+    keyspace
+        .get_value_mut(key, now)
+        .map(RedisValue::as_set_mut)
+        .transpose()
+}
+
+/// The set at `key` for writing, created empty when there is no such key.
+fn set_or_create<'a>(
+    keyspace: &'a mut Keyspace,
+    key: &Bytes,
+    now: DateTime<Utc>,
+) -> Result<&'a mut Set, DatabaseError> {
+    if !keyspace.contains(key, now) {
+        keyspace.insert(
+            key.clone(),
+            Record::new(RedisValue::Set(Set::default()), None),
+        );
+    }
+
+    Ok(set_mut(keyspace, key, now)?.expect("the set exists"))
+}
+
+/// Sets are never stored empty: the key goes away with its last member.
+fn remove_if_empty(keyspace: &mut Keyspace, key: &[u8], now: DateTime<Utc>) {
+    if let Ok(Some(set)) = set(keyspace, key, now) {
+        if set.is_empty() {
+            keyspace.remove(key, now);
+        }
+    }
+}
@@ -0,0 +1,922 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use rand::seq::{IndexedRandom, IteratorRandom, SliceRandom};
+
+use crate::client::Client;
+use crate::clock::Clock;
+use crate::commands::{
+    Aggregate, SetOperation, ZAddCommand, ZRange, ZRangeCommand, ZSetAlgebraCommand,
+};
+use crate::database::keyspace::Keyspace;
+use crate::database::value::{DatabaseError, Record, RedisValue, SortedSet};
+use crate::resp::{format_double, ProtocolVersion, RespDataTypes};
+
+use super::lists::list_range;
+use super::{RedisService, MAX_RANDOM_PREALLOCATION};
+
+impl RedisService {
+    /// `ZADD`, replying with how many members are new, or changed too with `CH`. With `INCR`
+    /// the reply is the new score instead, null when a condition prevented the update.
+    pub(super) async fn zadd(
+        &self,
+        command: ZAddCommand,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let not_added = || match command.incr {
+            true => RespDataTypes::Null,
+
+            false => RespDataTypes::Integer(0),
+        };
+
+        // XX only updates, so it never creates the key
+        let zset = if command.xx {
+            zset_mut(&mut keyspace, &command.key, now).map(|zset| zset.ok_or(()))
+        } else {
+            zset_or_create(&mut keyspace, &command.key, now).map(Ok)
+        };
+
+        let zset = match zset {
+            Ok(Ok(zset)) => zset,
+
+            Ok(Err(())) => return Ok(not_added()),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let mut added = 0;
+
+        let mut updated = 0;
+
+        let mut new_score = None;
+
+        let mut args = vec!["ZADD".into(), command.key.clone()];
+
+        for (score, member) in command.pairs {
+            let current = zset.score(&member);
+
+            let score = match current {
+                Some(_) if command.nx => continue,
+
+                None if command.xx => continue,
+
+                Some(current) => {
+                    let score = if command.incr { current + score } else { score };
+
+                    if score.is_nan() {
+                        remove_if_empty(&mut keyspace, &command.key, now);
+
+                        return Ok(RespDataTypes::SimpleError(
+                            "ERR resulting score is not a number (NaN)".to_string(),
+                        ));
+                    }
+
+                    if (command.gt && score <= current) || (command.lt && score >= current) {
+                        continue;
+                    }
+
+                    score
+                }
+
+                None => score,
+            };
+
+            new_score = Some(score);
+
+            match zset.insert(member.clone(), score) {
+                None => added += 1,
+
+                Some(previous) if previous != score => updated += 1,
+
+                Some(_) => continue,
+            }
+
+            // replicas get the resulting score rather than an increment
+            args.push(format_double(score).into());
+            args.push(member);
+        }
+
+        remove_if_empty(&mut keyspace, &command.key, now);
+
+        if args.len() > 2 {
+            self.propagate(&mut keyspace, client.db, args).await?;
+        }
+
+        if added > 0 {
+            self.serve_blocked(&mut keyspace, client.db, command.key)
+                .await?;
+        }
+
+        Ok(match command.incr {
+            true => new_score.map_or(RespDataTypes::Null, RespDataTypes::Double),
+
+            false if command.changed => RespDataTypes::Integer(added + updated),
+
+            false => RespDataTypes::Integer(added),
+        })
+    }
+
+    pub(super) async fn zrem(
+        &self,
+        key: Bytes,
+        members: Vec<Bytes>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let zset = match zset_mut(&mut keyspace, &key, now) {
+            Ok(Some(zset)) => zset,
+
+            Ok(None) => return Ok(RespDataTypes::Integer(0)),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let removed = members
+            .iter()
+            .filter(|member| zset.remove(member).is_some())
+            .count();
+
+        remove_if_empty(&mut keyspace, &key, now);
+
+        if removed > 0 {
+            let mut args = vec!["ZREM".into(), key];
+
+            args.extend(members);
+
+            self.propagate(&mut keyspace, client.db, args).await?;
+        }
+
+        Ok(RespDataTypes::Integer(removed as i64))
+    }
+
+    /// `ZSCORE` and `ZMSCORE`, which replies with an array even for one member.
+    pub(super) async fn zscore(
+        &self,
+        key: Bytes,
+        members: Vec<Bytes>,
+        multiple: bool,
+        client: &Client,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let zset = match zset(&mut keyspace, &key, now) {
+            Ok(zset) => zset,
+
+            Err(e) => return RespDataTypes::SimpleError(e.to_string()),
+        };
+
+        let mut scores = members.iter().map(|member| {
+            zset.and_then(|zset| zset.score(member))
+                .map_or(RespDataTypes::Null, RespDataTypes::Double)
+        });
+
+        match multiple {
+            true => RespDataTypes::Array(scores.collect()),
+
+            false => scores.next().unwrap_or(RespDataTypes::Null),
+        }
+    }
+
+    pub(super) async fn zcard(&self, key: Bytes, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        match zset(&mut keyspace, &key, now) {
+            Ok(zset) => RespDataTypes::Integer(zset.map_or(0, SortedSet::len) as i64),
+
+            Err(e) => RespDataTypes::SimpleError(e.to_string()),
+        }
+    }
+
+    /// `ZCOUNT` and `ZLEXCOUNT`, which count from ranks without walking the members.
+    pub(super) async fn zcount(&self, key: Bytes, range: ZRange, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let zset = match zset(&mut keyspace, &key, now) {
+            Ok(Some(zset)) => zset,
+
+            Ok(None) => return RespDataTypes::Integer(0),
+
+            Err(e) => return RespDataTypes::SimpleError(e.to_string()),
+        };
+
+        let count = match &range {
+            ZRange::Score(min, max) => zset.count_by_score(*min, *max),
+
+            ZRange::Lex(min, max) => zset.count_by_lex(min, max),
+
+            ZRange::Rank(start, stop) => {
+                list_range(*start, *stop, zset.len()).map_or(0, |(start, stop)| stop - start + 1)
+            }
+        };
+
+        RespDataTypes::Integer(count as i64)
+    }
+
+    /// `ZRANK` and `ZREVRANK`, with `WITHSCORE` replying with the rank and the score.
+    pub(super) async fn zrank(
+        &self,
+        key: Bytes,
+        member: Bytes,
+        rev: bool,
+        with_score: bool,
+        client: &Client,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let zset = match zset(&mut keyspace, &key, now) {
+            Ok(zset) => zset,
+
+            Err(e) => return RespDataTypes::SimpleError(e.to_string()),
+        };
+
+        match zset.and_then(|zset| zset.rank(&member, rev)) {
+            Some((rank, score)) if with_score => RespDataTypes::Array(vec![
+                RespDataTypes::Integer(rank as i64),
+                RespDataTypes::Double(score),
+            ]),
+
+            Some((rank, _)) => RespDataTypes::Integer(rank as i64),
+
+            None if with_score => RespDataTypes::NullArray,
+
+            None => RespDataTypes::Null,
+        }
+    }
+
+    pub(super) async fn zrange(&self, command: ZRangeCommand, client: &Client) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let zset = match zset(&mut keyspace, &command.key, now) {
+            Ok(Some(zset)) => zset,
+
+            Ok(None) => return RespDataTypes::Array(Vec::new()),
+
+            Err(e) => return RespDataTypes::SimpleError(e.to_string()),
+        };
+
+        let members = select(zset, &command.range, command.rev, command.limit);
+
+        scored(members, command.with_scores, client.protocol)
+    }
+
+    /// The `ZREMRANGEBY` family, replying with how many members were removed.
+    pub(super) async fn zremrange(
+        &self,
+        key: Bytes,
+        range: ZRange,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let zset = match zset_mut(&mut keyspace, &key, now) {
+            Ok(Some(zset)) => zset,
+
+            Ok(None) => return Ok(RespDataTypes::Integer(0)),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let members: Vec<Bytes> = select(zset, &range, false, None)
+            .into_iter()
+            .map(|(member, _)| member.clone())
+            .collect();
+
+        for member in &members {
+            zset.remove(member);
+        }
+
+        remove_if_empty(&mut keyspace, &key, now);
+
+        let removed = members.len();
+
+        // replicas get the members themselves, which is what the range resolved to here
+        if removed > 0 {
+            let mut args = vec!["ZREM".into(), key];
+
+            args.extend(members);
+
+            self.propagate(&mut keyspace, client.db, args).await?;
+        }
+
+        Ok(RespDataTypes::Integer(removed as i64))
+    }
+
+    /// `ZPOPMIN` and `ZPOPMAX`. Without a count the reply is a single member and its score,
+    /// with one it is a list of them.
+    pub(super) async fn zpop(
+        &self,
+        key: Bytes,
+        max: bool,
+        count: Option<usize>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let zset = match zset_mut(&mut keyspace, &key, now) {
+            Ok(Some(zset)) => zset,
+
+            Ok(None) => return Ok(RespDataTypes::Array(Vec::new())),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let popped: Vec<(Bytes, f64)> = (0..count.unwrap_or(1))
+            .map_while(|_| zset.pop(max))
+            .collect();
+
+        remove_if_empty(&mut keyspace, &key, now);
+
+        if !popped.is_empty() {
+            self.propagate(
+                &mut keyspace,
+                client.db,
+                vec![
+                    pop_command(max).into(),
+                    key,
+                    popped.len().to_string().into(),
+                ],
+            )
+            .await?;
+        }
+
+        let popped = popped
+            .iter()
+            .map(|(member, score)| (member, *score))
+            .collect();
+
+        // a single member comes flat, along with its score, whatever the protocol
+        Ok(match count {
+            Some(_) => scored(popped, true, client.protocol),
+
+            None => scored(popped, true, ProtocolVersion::Resp2),
+        })
+    }
+
+    /// `ZMPOP`, popping from the first of the keys that holds a sorted set and replying with
+    /// that key and what was popped from it.
+    pub(super) async fn zmpop(
+        &self,
+        keys: Vec<Bytes>,
+        max: bool,
+        count: usize,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        for key in keys {
+            let zset = match zset_mut(&mut keyspace, &key, now) {
+                Ok(Some(zset)) => zset,
+
+                Ok(None) => continue,
+
+                Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+            };
+
+            let popped: Vec<(Bytes, f64)> = (0..count).map_while(|_| zset.pop(max)).collect();
+
+            remove_if_empty(&mut keyspace, &key, now);
+
+            self.propagate(
+                &mut keyspace,
+                client.db,
+                vec![
+                    pop_command(max).into(),
+                    key.clone(),
+                    popped.len().to_string().into(),
+                ],
+            )
+            .await?;
+
+            // the pairs are nested whatever the protocol
+            let popped = popped
+                .iter()
+                .map(|(member, score)| (member, *score))
+                .collect();
+
+            return Ok(RespDataTypes::Array(vec![
+                RespDataTypes::BulkString(key),
+                scored(popped, true, ProtocolVersion::Resp3),
+            ]));
+        }
+
+        Ok(RespDataTypes::NullArray)
+    }
+
+    /// `ZUNION`, `ZINTER`, `ZDIFF`, and their `STORE` variants when given a destination,
+    /// which is deleted rather than stored empty.
+    pub(super) async fn zset_algebra(
+        &self,
+        command: ZSetAlgebraCommand,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let result = match combine(
+            &mut keyspace,
+            command.operation,
+            &command.keys,
+            &command.weights,
+            command.aggregate,
+            now,
+        ) {
+            Ok(result) => result,
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let Some(destination) = command.destination.clone() else {
+            return Ok(scored(
+                result.iter().collect(),
+                command.with_scores,
+                client.protocol,
+            ));
+        };
+
+        let len = result.len();
+
+        store(&mut keyspace, &destination, result, now);
+
+        let mut args = vec![
+            store_command(command.operation).into(),
+            destination.clone(),
+            command.keys.len().to_string().into(),
+        ];
+
+        args.extend(command.keys);
+
+        if command.weights.iter().any(|weight| *weight != 1.0) {
+            args.push("WEIGHTS".into());
+            args.extend(
+                command
+                    .weights
+                    .iter()
+                    .map(|weight| format_double(*weight).into()),
+            );
+        }
+
+        if command.aggregate != Aggregate::Sum {
+            args.push("AGGREGATE".into());
+            args.push(command.aggregate.name().into());
+        }
+
+        self.propagate(&mut keyspace, client.db, args).await?;
+
+        if len > 0 {
+            self.serve_blocked(&mut keyspace, client.db, destination)
+                .await?;
+        }
+
+        Ok(RespDataTypes::Integer(len as i64))
+    }
+
+    /// `ZRANGESTORE`, replying with how many members were stored.
+    pub(super) async fn zrangestore(
+        &self,
+        destination: Bytes,
+        command: ZRangeCommand,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let result: SortedSet = match zset(&mut keyspace, &command.key, now) {
+            Ok(Some(zset)) => select(zset, &command.range, command.rev, command.limit)
+                .into_iter()
+                .map(|(member, score)| (member.clone(), score))
+                .collect(),
+
+            Ok(None) => SortedSet::default(),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let len = result.len();
+
+        store(&mut keyspace, &destination, result, now);
+
+        let mut args = vec![
+            "ZRANGESTORE".into(),
+            destination.clone(),
+            command.key.clone(),
+        ];
+
+        args.extend(command.range_arguments());
+
+        self.propagate(&mut keyspace, client.db, args).await?;
+
+        if len > 0 {
+            self.serve_blocked(&mut keyspace, client.db, destination)
+                .await?;
+        }
+
+        Ok(RespDataTypes::Integer(len as i64))
+    }
+
+    /// Size of the intersection, counting no further than `limit` unless it is 0.
+    pub(super) async fn zintercard(
+        &self,
+        keys: Vec<Bytes>,
+        limit: usize,
+        client: &Client,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let weights = vec![1.0; keys.len()];
+
+        match combine(
+            &mut keyspace,
+            SetOperation::Inter,
+            &keys,
+            &weights,
+            Aggregate::Sum,
+            now,
+        ) {
+            Ok(result) if limit > 0 => RespDataTypes::Integer(result.len().min(limit) as i64),
+
+            Ok(result) => RespDataTypes::Integer(result.len() as i64),
+
+            Err(e) => RespDataTypes::SimpleError(e.to_string()),
+        }
+    }
+
+    pub(super) async fn zrandmember(
+        &self,
+        key: Bytes,
+        count: Option<i64>,
+        with_scores: bool,
+        client: &Client,
+    ) -> RespDataTypes {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let zset = match zset(&mut keyspace, &key, now) {
+            Ok(zset) => zset,
+
+            Err(e) => return RespDataTypes::SimpleError(e.to_string()),
+        };
+
+        let mut rng = rand::rng();
+
+        let Some(count) = count else {
+            return zset
+                .and_then(|zset| zset.scores().choose(&mut rng))
+                .map_or(RespDataTypes::Null, |(member, _)| {
+                    RespDataTypes::BulkString(member.clone())
+                });
+        };
+
+        let Some(zset) = zset else {
+            return RespDataTypes::Array(Vec::new());
+        };
+
+        let picked: Vec<(&Bytes, f64)> = if count >= 0 {
+            let count = (count as usize).min(zset.len());
+
+            let mut picked = zset.scores().choose_multiple(&mut rng, count);
+
+            picked.shuffle(&mut rng);
+
+            picked
+        } else {
+            let members: Vec<(&Bytes, f64)> = zset.scores().collect();
+
+            let count = count.unsigned_abs() as usize;
+
+            let mut picked = Vec::with_capacity(count.min(MAX_RANDOM_PREALLOCATION));
+
+            picked.extend((0..count).filter_map(|_| members.choose(&mut rng).copied()));
+
+            picked
+        };
+
+        scored(picked, with_scores, client.protocol)
+    }
+}
+
+/// Combines the sorted sets at `keys` with `operation`, each score multiplied by the weight
+/// of its key and `aggregate` deciding the score of members found in several of them. Plain
+/// sets count as sorted sets whose members all score 1, and keys that do not exist as empty
+/// ones; any other type fails.
+fn combine(
+    keyspace: &mut Keyspace,
+    operation: SetOperation,
+    keys: &[Bytes],
+    weights: &[f64],
+    aggregate: Aggregate,
+    now: DateTime<Utc>,
+) -> Result<SortedSet, DatabaseError> {
+    let mut inputs = Vec::with_capacity(keys.len());
+
+    for (key, weight) in keys.iter().zip(weights) {
+        inputs.push(weighted_scores(keyspace, key, *weight, now)?);
+    }
+
+    let mut result: HashMap<Bytes, f64> = HashMap::new();
+
+    match operation {
+        SetOperation::Union => {
+            for input in inputs {
+                for (member, score) in input {
+                    result
+                        .entry(member)
+                        .and_modify(|total| *total = aggregate.apply(*total, score))
+                        .or_insert(score);
+                }
+            }
+        }
+
+        // the smallest input is filtered by every other one
+        SetOperation::Inter => {
+            let Some((smallest, _)) = inputs
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, input)| input.len())
+            else {
+                return Ok(SortedSet::default());
+            };
+
+            for (member, score) in &inputs[smallest] {
+                let mut total = *score;
+
+                let in_all = inputs
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != smallest)
+                    .all(|(_, other)| match other.get(member) {
+                        Some(score) => {
+                            total = aggregate.apply(total, *score);
+
+                            true
+                        }
+
+                        None => false,
+                    });
+
+                if in_all {
+                    result.insert(member.clone(), total);
+                }
+            }
+        }
+
+        SetOperation::Diff => {
+            if let Some((first, others)) = inputs.split_first() {
+                result = first
+                    .iter()
+                    .filter(|(member, _)| !others.iter().any(|other| other.contains_key(*member)))
+                    .map(|(member, score)| (member.clone(), *score))
+                    .collect();
+            }
+        }
+    }
+
+    Ok(result.into_iter().collect())
+}
+
+/// Members of the sorted set or set at `key` with their scores multiplied by `weight`, a
+/// product of 0 and an infinity giving 0.
+fn weighted_scores(
+    keyspace: &mut Keyspace,
+    key: &[u8],
+    weight: f64,
+    now: DateTime<Utc>,
+) -> Result<HashMap<Bytes, f64>, DatabaseError> {
+    let weigh = |score: f64| {
+        let score = score * weight;
+
+        if score.is_nan() {
+            0.0
+        } else {
+            score
+        }
+    };
+
+    Ok(match keyspace.get(key, now).map(|record| &record.value) {
+        Some(RedisValue::ZSet(zset)) => zset
+            .scores()
+            .map(|(member, score)| (member.clone(), weigh(score)))
+            .collect(),
+
+        Some(RedisValue::Set(set)) => set
+            .iter()
+            .map(|member| (member.clone(), weigh(1.0)))
+            .collect(),
+
+        Some(_) => return Err(DatabaseError::WrongType),
+
+        None => HashMap::new(),
+    })
+}
+
+/// Replaces whatever is at `destination` with a command's result, deleting it instead when
+/// the result is empty.
+fn store(keyspace: &mut Keyspace, destination: &Bytes, result: SortedSet, now: DateTime<Utc>) {
+    if result.is_empty() {
+        keyspace.remove(destination, now);
+    } else {
+        keyspace.insert(
+            destination.clone(),
+            Record::new(RedisValue::ZSet(result), None),
+        );
+    }
+}
+
+/// Name of the variant of `ZUNION`, `ZINTER` or `ZDIFF` that stores its result.
+fn store_command(operation: SetOperation) -> &'static str {
+    match operation {
+        SetOperation::Inter => "ZINTERSTORE",
+
+        SetOperation::Union => "ZUNIONSTORE",
+
+        SetOperation::Diff => "ZDIFFSTORE",
+    }
+}
+
+/// Members `range` selects, from the highest score with `rev`. `limit` skips an offset of
+/// them and caps how many are returned, a negative offset selecting nothing and a negative
+/// count everything.
+fn select<'a>(
+    zset: &'a SortedSet,
+    range: &'a ZRange,
+    rev: bool,
+    limit: Option<(i64, i64)>,
+) -> Vec<(&'a Bytes, f64)> {
+    let (offset, count) = match limit {
+        Some((offset, _)) if offset < 0 => return Vec::new(),
+
+        Some((offset, count)) => (
+            offset as usize,
+            usize::try_from(count).unwrap_or(usize::MAX),
+        ),
+
+        None => (0, usize::MAX),
+    };
+
+    match range {
+        ZRange::Rank(start, stop) => match list_range(*start, *stop, zset.len()) {
+            Some((start, stop)) => zset
+                .starting_at(start, rev)
+                .take(stop - start + 1)
+                .collect(),
+
+            None => Vec::new(),
+        },
+
+        ZRange::Score(min, max) => zset
+            .by_score(*min, *max, rev)
+            .skip(offset)
+            .take(count)
+            .collect(),
+
+        ZRange::Lex(min, max) => zset
+            .by_lex(min, max, rev)
+            .skip(offset)
+            .take(count)
+            .collect(),
+    }
+}
+
+/// Members, with their scores if asked to: paired with them for RESP3 clients, one after the
+/// other for RESP2 ones.
+fn scored(
+    members: Vec<(&Bytes, f64)>,
+    with_scores: bool,
+    protocol: ProtocolVersion,
+) -> RespDataTypes {
+    let bulk = |member: &Bytes| RespDataTypes::BulkString(member.clone());
+
+    let reply = match (with_scores, protocol) {
+        (false, _) => members
+            .into_iter()
+            .map(|(member, _)| bulk(member))
+            .collect(),
+
+        (true, ProtocolVersion::Resp3) => members
+            .into_iter()
+            .map(|(member, score)| {
+                RespDataTypes::Array(vec![bulk(member), RespDataTypes::Double(score)])
+            })
+            .collect(),
+
+        (true, _) => members
+            .into_iter()
+            .flat_map(|(member, score)| [bulk(member), RespDataTypes::Double(score)])
+            .collect(),
+    };
+
+    RespDataTypes::Array(reply)
+}
+
+fn pop_command(max: bool) -> &'static str {
+    if max {
+        "ZPOPMAX"
+    } else {
+        "ZPOPMIN"
+    }
+}
+
+/// The sorted set at `key`, `None` when there is no such key.
+fn zset<'a>(
+    keyspace: &'a mut Keyspace,
+    key: &[u8],
+    now: DateTime<Utc>,
+) -> Result<Option<&'a SortedSet>, DatabaseError> {
+    keyspace
+        .get(key, now)
+        .map(|record| record.value.as_zset())
+        .transpose()
+}
+
+/// The sorted set at `key` for writing, `None` when there is no such key.
+fn zset_mut<'a>(
+    keyspace: &'a mut Keyspace,
+    key: &[u8],
+    now: DateTime<Utc>,
+) -> Result<Option<&'a mut SortedSet>, DatabaseError> {
+    keyspace
+        .get_value_mut(key, now)
+        .map(RedisValue::as_zset_mut)
+        .transpose()
+}
+
+/// The sorted set at `key` for writing, created empty when there is no such key.
+fn zset_or_create<'a>(
+    keyspace: &'a mut Keyspace,
+    key: &Bytes,
+    now: DateTime<Utc>,
+) -> Result<&'a mut SortedSet, DatabaseError> {
+    if !keyspace.contains(key, now) {
+        keyspace.insert(
+            key.clone(),
+            Record::new(RedisValue::ZSet(SortedSet::default()), None),
+        );
+    }
+
+    Ok(zset_mut(keyspace, key, now)?.expect("the sorted set exists"))
+}
+
+/// Sorted sets are never stored empty: the key goes away with its last member.
+fn remove_if_empty(keyspace: &mut Keyspace, key: &[u8], now: DateTime<Utc>) {
+    if let Ok(Some(zset)) = zset(keyspace, key, now) {
+        if zset.is_empty() {
+            keyspace.remove(key, now);
+        }
+    }
+}
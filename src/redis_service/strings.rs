@@ -0,0 +1,219 @@
+use bytes::Bytes;
+
+use crate::client::Client;
+use crate::clock::Clock;
+use crate::commands::{SetCommand, SetCondition, TtlUpdate};
+use crate::database::value::{Record, RedisValue};
+use crate::resp::RespDataTypes;
+
+use super::{invalid_expire_time, RedisService};
+
+impl RedisService {
+    pub(super) async fn get(&self, key: Bytes, client: &Client) -> RespDataTypes {
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        match keyspace.get(&key, self.clock.now()) {
+            Some(record) => match record.value.as_string() {
+                Ok(value) => RespDataTypes::BulkString(value.clone()),
+
+                Err(e) => RespDataTypes::SimpleError(e.to_string()),
+            },
+
+            None => RespDataTypes::Null,
+        }
+    }
+
+    pub(super) async fn set(
+        &self,
+        command: SetCommand,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let expires_at = match command.expiration {
+            Some(expiration) => match expiration.deadline(now) {
+                Some(deadline) => Some(deadline),
+
+                None => return Ok(invalid_expire_time("set")),
+            },
+
+            None => None,
+        };
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let existing = keyspace.get(&command.key, now);
+
+        let old_value = match existing.map(|record| record.value.as_string()) {
+            Some(Ok(value)) => Some(value.clone()),
+
+            // `GET` can only hand back strings, so nothing is written
+            Some(Err(e)) if command.get => return Ok(RespDataTypes::SimpleError(e.to_string())),
+
+            _ => None,
+        };
+
+        let allowed = match command.condition {
+            None => true,
+
+            Some(SetCondition::Nx) => existing.is_none(),
+
+            Some(SetCondition::Xx) => existing.is_some(),
+        };
+
+        let reply = if command.get {
+            old_value.map_or(RespDataTypes::Null, RespDataTypes::BulkString)
+        } else if allowed {
+            RespDataTypes::SimpleString("OK".to_string())
+        } else {
+            RespDataTypes::Null
+        };
+
+        if !allowed {
+            return Ok(reply);
+        }
+
+        let expires_at = if command.keep_ttl {
+            existing.and_then(|record| record.expires_at)
+        } else {
+            expires_at
+        };
+
+        keyspace.insert(
+            command.key.clone(),
+            Record::new(RedisValue::String(command.value.clone()), expires_at),
+        );
+
+        // relative expirations go out as absolute ones, so replicas expire the key at the
+        // same moment no matter when they apply the command
+        let mut args = vec!["SET".into(), command.key, command.value];
+
+        if command.keep_ttl {
+            args.push("KEEPTTL".into());
+        } else if let Some(expires_at) = expires_at {
+            args.push("PXAT".into());
+            args.push(expires_at.timestamp_millis().to_string().into());
+        }
+
+        self.propagate(&mut keyspace, client.db, args).await?;
+
+        Ok(reply)
+    }
+
+    pub(super) async fn getex(
+        &self,
+        key: Bytes,
+        update: Option<TtlUpdate>,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let Some(record) = keyspace.get(&key, now) else {
+            return Ok(RespDataTypes::Null);
+        };
+
+        let value = match record.value.as_string() {
+            Ok(value) => value.clone(),
+
+            Err(e) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+        };
+
+        let has_expiration = record.expires_at.is_some();
+
+        let propagated = match update {
+            None => None,
+
+            Some(TtlUpdate::Persist) if has_expiration => {
+                keyspace.set_expires_at(&key, None);
+
+                Some(vec!["PERSIST".into(), key.clone()])
+            }
+
+            Some(TtlUpdate::Persist) => None,
+
+            Some(TtlUpdate::Expire(expiration)) => match expiration.deadline(now) {
+                None => return Ok(invalid_expire_time("getex")),
+
+                Some(deadline) if deadline <= now => {
+                    keyspace.remove(&key, now);
+
+                    Some(vec!["GETDEL".into(), key.clone()])
+                }
+
+                Some(deadline) => {
+                    keyspace.set_expires_at(&key, Some(deadline));
+
+                    Some(vec![
+                        "PEXPIREAT".into(),
+                        key.clone(),
+                        deadline.timestamp_millis().to_string().into(),
+                    ])
+                }
+            },
+        };
+
+        if let Some(args) = propagated {
+            self.propagate(&mut keyspace, client.db, args).await?;
+        }
+
+        Ok(RespDataTypes::BulkString(value))
+    }
+
+    pub(super) async fn getdel(
+        &self,
+        key: Bytes,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        let now = self.clock.now();
+
+        let db = self.get_selected_db(client.db).await;
+
+        let mut keyspace = db.lock().await;
+
+        let value = match keyspace
+            .get(&key, now)
+            .map(|record| record.value.as_string())
+        {
+            Some(Ok(value)) => value.clone(),
+
+            Some(Err(e)) => return Ok(RespDataTypes::SimpleError(e.to_string())),
+
+            None => return Ok(RespDataTypes::Null),
+        };
+
+        keyspace.remove(&key, now);
+
+        self.propagate(&mut keyspace, client.db, vec!["GETDEL".into(), key])
+            .await?;
+
+        Ok(RespDataTypes::BulkString(value))
+    }
+
+    pub(super) async fn getset(
+        &self,
+        key: Bytes,
+        value: Bytes,
+        client: &Client,
+    ) -> anyhow::Result<RespDataTypes> {
+        self.set(
+            SetCommand {
+                key,
+                value,
+                condition: None,
+                get: true,
+                keep_ttl: false,
+                expiration: None,
+            },
+            client,
+        )
+        .await
+    }
+}
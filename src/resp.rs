@@ -1,362 +1,936 @@
-use anyhow::bail;
-use chrono::{DateTime, Utc};
-use regex::Regex;
-use std::{fmt::Display, time::Duration};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::fmt::{Display, Write};
+use thiserror::Error;
+
+const CRLF: &[u8] = b"\r\n";
+
+const MAX_PREALLOCATED_ITEMS: usize = 1024;
+
+/// First bytes of every RESP2 and RESP3 type; anything else starts an inline command.
+const TYPE_BYTES: &[u8] = b"+-:$*_#,(=%~|>";
+
+/// Longest length or type header line accepted before its CRLF shows up.
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// Bounds on what a single peer may make the decoder buffer or allocate.
+#[derive(Debug, Clone, Copy)]
+pub struct ProtocolLimits {
+    /// Longest bulk string accepted, `proto-max-bulk-len`.
+    pub max_bulk_len: i64,
+
+    /// Most elements accepted in one aggregate.
+    pub max_multibulk_len: i64,
+
+    /// Most aggregates accepted inside one another.
+    pub max_nesting_depth: usize,
+
+    /// Most unprocessed bytes buffered for a client, `client-query-buffer-limit`.
+    pub max_query_buffer: usize,
+}
+
+impl Default for ProtocolLimits {
+    fn default() -> Self {
+        Self {
+            max_bulk_len: 512 * 1024 * 1024,
+            max_multibulk_len: 1024 * 1024,
+            max_nesting_depth: 16,
+            max_query_buffer: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+/// Protocol spoken on a connection, negotiated with `HELLO`. Every connection starts on RESP2.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProtocolVersion {
+    #[default]
+    Resp2,
+
+    Resp3,
+}
+
+impl ProtocolVersion {
+    pub fn as_number(&self) -> i64 {
+        match self {
+            Self::Resp2 => 2,
+
+            Self::Resp3 => 3,
+        }
+    }
+}
+
+impl TryFrom<i64> for ProtocolVersion {
+    type Error = &'static str;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        match value {
+            2 => Ok(Self::Resp2),
+
+            3 => Ok(Self::Resp3),
+
+            _ => Err("NOPROTO unsupported protocol version"),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum RespDataTypes {
     #[allow(dead_code)]
     SimpleString(String),
 
+    SimpleError(String),
+
     Integer(i64),
 
-    BulkString(String),
+    BulkString(Bytes),
 
     Array(Vec<RespDataTypes>),
 
-    SimpleError(Option<String>),
+    Null,
+
+    /// A missing aggregate, which RESP2 tells apart from a missing string as `*-1`.
+    NullArray,
+
+    Boolean(bool),
+
+    Double(f64),
+
+    BigNumber(String),
+
+    /// Three letter format (`txt`, `mkd`) followed by the text itself.
+    Verbatim(String, String),
+
+    Map(Vec<(RespDataTypes, RespDataTypes)>),
+
+    Set(Vec<RespDataTypes>),
+
+    Attribute(Vec<(RespDataTypes, RespDataTypes)>),
+
+    Push(Vec<RespDataTypes>),
 }
 
 impl RespDataTypes {
-    fn len(value: &RespDataTypes) -> usize {
-        match value {
-            Self::Array(arr) => arr.len() + 1,
+    /// Serializes the value into a standalone buffer, for one-off writes like the replication
+    /// handshake. Replies to clients go through [`RespDataTypes::encode_into`] instead.
+    pub fn to_bytes(&self, protocol: ProtocolVersion) -> Bytes {
+        let mut out = BytesMut::new();
 
-            Self::BulkString(_) => 2,
+        self.encode_into(&mut out, protocol);
 
-            _ => 1,
-        }
+        out.freeze()
     }
-}
 
-impl Display for RespDataTypes {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    /// Appends the value to `out` for a client speaking `protocol`, without building any
+    /// intermediate strings.
+    ///
+    /// RESP3-only types are downgraded for RESP2 clients the same way Redis does it: maps
+    /// become flat arrays, sets and pushes become arrays, booleans become integers, and
+    /// doubles, big numbers and verbatim strings become bulk strings. Attributes are dropped.
+    pub fn encode_into(&self, out: &mut BytesMut, protocol: ProtocolVersion) {
+        let resp3 = protocol == ProtocolVersion::Resp3;
+
         match self {
-            Self::SimpleString(value) => write!(f, "+{value}\r\n"),
+            Self::SimpleString(value) => Self::encode_line(out, b'+', value),
 
-            Self::Integer(value) => write!(f, ":{}\r\n", value),
+            Self::SimpleError(message) => Self::encode_line(out, b'-', message),
 
-            Self::BulkString(value) => write!(f, "${}\r\n{}\r\n", value.len(), value),
+            Self::Integer(value) => Self::encode_line(out, b':', value),
 
-            Self::Array(arr) => {
-                let mut result = String::new();
+            Self::BulkString(value) => Self::encode_bulk(out, value),
 
-                for item in arr {
-                    result.push_str(&item.to_string());
-                }
+            Self::Array(items) => Self::encode_aggregate(out, b'*', items, protocol),
+
+            Self::Null if resp3 => out.put_slice(b"_\r\n"),
+
+            Self::Null => out.put_slice(b"$-1\r\n"),
+
+            Self::NullArray if resp3 => out.put_slice(b"_\r\n"),
+
+            Self::NullArray => out.put_slice(b"*-1\r\n"),
+
+            Self::Boolean(value) if resp3 => {
+                out.put_slice(if *value { b"#t\r\n" } else { b"#f\r\n" })
+            }
+
+            Self::Boolean(value) => Self::encode_line(out, b':', *value as i64),
+
+            Self::Double(value) if resp3 => Self::encode_line(out, b',', format_double(*value)),
+
+            Self::Double(value) => Self::encode_bulk(out, format_double(*value).as_bytes()),
+
+            Self::BigNumber(value) if resp3 => Self::encode_line(out, b'(', value),
+
+            Self::BigNumber(value) => Self::encode_bulk(out, value.as_bytes()),
+
+            Self::Verbatim(format, text) if resp3 => {
+                Self::encode_line(out, b'=', text.len() + 4);
+
+                out.put_slice(format.as_bytes());
+                out.put_u8(b':');
+                out.put_slice(text.as_bytes());
+                out.put_slice(CRLF);
+            }
+
+            Self::Verbatim(_, text) => Self::encode_bulk(out, text.as_bytes()),
+
+            Self::Map(pairs) => {
+                let prefix = if resp3 { b'%' } else { b'*' };
+
+                let len = if resp3 { pairs.len() } else { pairs.len() * 2 };
+
+                Self::encode_pairs(out, prefix, len, pairs, protocol);
+            }
+
+            Self::Set(items) => {
+                Self::encode_aggregate(out, if resp3 { b'~' } else { b'*' }, items, protocol)
+            }
 
-                write!(f, "*{}\r\n{}", arr.len(), result)
+            Self::Attribute(pairs) if resp3 => {
+                Self::encode_pairs(out, b'|', pairs.len(), pairs, protocol)
             }
 
-            Self::SimpleError(str) => {
-                write!(f, "$-{}\r\n", str.to_owned().unwrap_or("1".to_string()))
+            Self::Attribute(_) => {}
+
+            Self::Push(items) => {
+                Self::encode_aggregate(out, if resp3 { b'>' } else { b'*' }, items, protocol)
             }
         }
     }
-}
 
-impl From<Vec<String>> for RespDataTypes {
-    fn from(value: Vec<String>) -> Self {
-        let mut columns: Vec<RespDataTypes> = Vec::with_capacity(value.len());
+    fn encode_line(out: &mut BytesMut, prefix: u8, value: impl Display) {
+        out.put_u8(prefix);
+
+        // formatting straight into the buffer; writing to a BytesMut cannot fail
+        let _ = write!(out, "{value}");
+
+        out.put_slice(CRLF);
+    }
+
+    fn encode_bulk(out: &mut BytesMut, value: &[u8]) {
+        Self::encode_line(out, b'$', value.len());
+
+        out.reserve(value.len() + CRLF.len());
+        out.put_slice(value);
+        out.put_slice(CRLF);
+    }
 
-        for item in value {
-            columns.push(RespDataTypes::from(item));
+    fn encode_aggregate(out: &mut BytesMut, prefix: u8, items: &[Self], protocol: ProtocolVersion) {
+        Self::encode_line(out, prefix, items.len());
+
+        for item in items {
+            item.encode_into(out, protocol);
         }
+    }
 
-        Self::Array(columns)
+    fn encode_pairs(
+        out: &mut BytesMut,
+        prefix: u8,
+        len: usize,
+        pairs: &[(Self, Self)],
+        protocol: ProtocolVersion,
+    ) {
+        Self::encode_line(out, prefix, len);
+
+        for (key, value) in pairs {
+            key.encode_into(out, protocol);
+            value.encode_into(out, protocol);
+        }
     }
 }
 
-impl From<String> for RespDataTypes {
-    fn from(value: String) -> Self {
-        let result = value.parse::<f64>();
+/// Formats a double the way Redis replies with it, including `inf`, `-inf` and `nan`.
+pub fn format_double(value: f64) -> String {
+    if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
 
-        match result {
-            Ok(num) => Self::Integer(num as i64),
+/// RESP2 encoding, which is what every connection, replica link and handshake starts with.
+/// Binary payloads are shown lossily, so this is meant for logs; use
+/// [`RespDataTypes::encode_into`] for anything written to a socket.
+impl Display for RespDataTypes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            String::from_utf8_lossy(&self.to_bytes(ProtocolVersion::Resp2))
+        )
+    }
+}
 
-            Err(_) => Self::BulkString(value),
-        }
+impl From<Vec<Bytes>> for RespDataTypes {
+    fn from(value: Vec<Bytes>) -> Self {
+        Self::Array(value.into_iter().map(RespDataTypes::BulkString).collect())
     }
 }
 
-impl TryFrom<Vec<&str>> for RespDataTypes {
-    type Error = &'static str;
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ProtocolError {
+    #[error("Protocol error: invalid multibulk length")]
+    InvalidMultibulkLength,
+
+    #[error("Protocol error: invalid bulk length")]
+    InvalidBulkLength,
+
+    #[error("Protocol error: too big count string")]
+    TooBigCountString,
+
+    #[error("Protocol error: too deeply nested")]
+    TooDeeplyNested,
+
+    #[error("Protocol error: too big inline request")]
+    TooBigInlineRequest,
 
-    fn try_from(value: Vec<&str>) -> Result<Self, Self::Error> {
-        let array_re = Regex::new(r"^[*]?\d+$").unwrap();
-        let bulk_array_re = Regex::new(r"^[$]?\d+$").unwrap();
+    #[error("Protocol error: unbalanced quotes in request")]
+    UnbalancedQuotes,
 
-        let first = value.first().unwrap_or(&"error");
+    #[error("Protocol error: invalid integer")]
+    InvalidInteger,
 
-        match *first {
-            "+" => {
-                let str = value.get(1);
+    #[error("Protocol error: bulk string is not terminated by CRLF")]
+    UnterminatedBulk,
 
-                match str {
-                    None => Err("Invalid SimpleString"),
+    #[error("Protocol error: invalid boolean")]
+    InvalidBoolean,
 
-                    Some(string) => Ok(Self::SimpleString(string.to_string())),
+    #[error("Protocol error: invalid double")]
+    InvalidDouble,
+
+    #[error("Protocol error: invalid UTF-8 string")]
+    InvalidString,
+
+    #[error("Protocol error: unexpected type byte '{0}'")]
+    UnexpectedType(char),
+}
+
+/// Decodes the frames a connection sends as its bytes arrive. Elements are consumed as soon
+/// as they are complete and the aggregates still waiting for more are kept here, so a large
+/// pipelined array arriving over many reads is parsed once instead of again on every read.
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    /// Aggregates of the frame being decoded that still wait for elements, outermost first.
+    open: Vec<OpenAggregate>,
+
+    /// Bytes of the frame being decoded already consumed from the buffer.
+    consumed: usize,
+}
+
+#[derive(Debug)]
+struct OpenAggregate {
+    type_byte: u8,
+
+    /// Elements still to come.
+    remaining: usize,
+
+    items: Vec<RespDataTypes>,
+}
+
+impl OpenAggregate {
+    fn new(type_byte: u8, len: usize) -> Self {
+        Self {
+            type_byte,
+            remaining: len,
+            // the declared length is untrusted, so only preallocate a bounded amount
+            items: Vec::with_capacity(len.min(MAX_PREALLOCATED_ITEMS)),
+        }
+    }
+
+    fn close(self) -> RespDataTypes {
+        match self.type_byte {
+            b'~' => RespDataTypes::Set(self.items),
+
+            b'>' => RespDataTypes::Push(self.items),
+
+            b'%' | b'|' => {
+                let mut pairs = Vec::with_capacity(self.items.len() / 2);
+
+                let mut items = self.items.into_iter();
+
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    pairs.push((key, value));
+                }
+
+                if self.type_byte == b'%' {
+                    RespDataTypes::Map(pairs)
+                } else {
+                    RespDataTypes::Attribute(pairs)
                 }
             }
 
-            ":" => {
-                let num = value.get(1);
+            _ => RespDataTypes::Array(self.items),
+        }
+    }
+}
 
-                match num {
-                    None => Err("Invalid Integer"),
+impl FrameDecoder {
+    /// Decodes the next complete frame in `buffer`, consuming its bytes.
+    ///
+    /// Returns `Ok(None)` when the buffer only holds part of a frame. Whatever elements of it
+    /// are complete get consumed and kept, and decoding resumes after them once the next
+    /// read is appended.
+    pub fn decode(
+        &mut self,
+        buffer: &mut BytesMut,
+        limits: &ProtocolLimits,
+    ) -> Result<Option<RespDataTypes>, ProtocolError> {
+        if self.open.is_empty() {
+            if let Some(type_byte) = buffer.first() {
+                if !TYPE_BYTES.contains(type_byte) {
+                    return Self::decode_inline(buffer);
+                }
+            }
+        }
 
-                    Some(number_str) => {
-                        let number = number_str.parse::<i64>();
+        loop {
+            let Some(mut value) = self.next_value(buffer, limits)? else {
+                return Ok(None);
+            };
 
-                        if let Ok(value) = number {
-                            Ok(Self::Integer(value))
-                        } else {
-                            Err("Invalid Integer")
-                        }
-                    }
+            // hand the value to its aggregate, closing every aggregate it completes
+            loop {
+                let Some(mut open) = self.open.pop() else {
+                    self.consumed = 0;
+
+                    return Ok(Some(value));
+                };
+
+                open.items.push(value);
+
+                open.remaining -= 1;
+
+                if open.remaining > 0 {
+                    self.open.push(open);
+
+                    break;
                 }
+
+                value = open.close();
             }
+        }
+    }
 
-            _ if array_re.is_match(first) => {
-                let n_str = first;
+    /// Bytes of a partially received frame held by the decoder rather than the buffer,
+    /// which count towards the query buffer limit all the same.
+    pub fn partial_len(&self) -> usize {
+        self.consumed
+    }
 
-                let n_result = n_str.replace("*", "").parse::<i64>();
+    /// Consumes the next scalar value, or the headers of the aggregates opened before it.
+    fn next_value(
+        &mut self,
+        buffer: &mut BytesMut,
+        limits: &ProtocolLimits,
+    ) -> Result<Option<RespDataTypes>, ProtocolError> {
+        loop {
+            let Some(&type_byte) = buffer.first() else {
+                return Ok(None);
+            };
+
+            let Some((line, next_idx)) = read_line(buffer, 1) else {
+                // a header line is only a few bytes, so a huge unterminated one is garbage
+                if buffer.len() > MAX_LINE_LEN {
+                    return Err(ProtocolError::TooBigCountString);
+                }
 
-                if let Ok(n) = n_result {
-                    let mut columns: Vec<RespDataTypes> = Vec::with_capacity(n as usize);
+                return Ok(None);
+            };
 
-                    let mut i = 1;
+            let value = match type_byte {
+                b'+' => RespDataTypes::SimpleString(to_string(line)?),
 
-                    while i < value.len() {
-                        let column_value = RespDataTypes::try_from(value[i..].to_vec());
+                b'-' => RespDataTypes::SimpleError(to_string(line)?),
 
-                        match column_value {
-                            Ok(final_value) => {
-                                i += RespDataTypes::len(&final_value);
+                b'_' => RespDataTypes::Null,
 
-                                columns.push(final_value);
-                            }
+                b'#' => match line {
+                    b"t" => RespDataTypes::Boolean(true),
 
-                            _ => {
-                                return column_value;
-                            }
-                        }
+                    b"f" => RespDataTypes::Boolean(false),
+
+                    _ => return Err(ProtocolError::InvalidBoolean),
+                },
+
+                b',' => {
+                    let number = to_string(line)?
+                        .parse::<f64>()
+                        .map_err(|_| ProtocolError::InvalidDouble)?;
+
+                    RespDataTypes::Double(number)
+                }
+
+                b'(' => RespDataTypes::BigNumber(to_string(line)?),
+
+                b':' => {
+                    RespDataTypes::Integer(parse_number(line).ok_or(ProtocolError::InvalidInteger)?)
+                }
+
+                b'$' => {
+                    let len = parse_number(line)
+                        .filter(|len| *len <= limits.max_bulk_len)
+                        .ok_or(ProtocolError::InvalidBulkLength)?;
+
+                    if len < 0 {
+                        RespDataTypes::Null
+                    } else {
+                        let Some(payload) = Self::take_payload(buffer, next_idx, len)? else {
+                            return Ok(None);
+                        };
+
+                        self.consumed += next_idx + payload.len() + CRLF.len();
+
+                        return Ok(Some(RespDataTypes::BulkString(payload)));
                     }
+                }
 
-                    Ok(Self::Array(columns.clone()))
-                } else {
-                    Err("Invalid Array")
+                b'=' => {
+                    let len = parse_number(line)
+                        .filter(|len| *len <= limits.max_bulk_len)
+                        .ok_or(ProtocolError::InvalidBulkLength)?;
+
+                    let Some(payload) = Self::take_payload(buffer, next_idx, len.max(0))? else {
+                        return Ok(None);
+                    };
+
+                    self.consumed += next_idx + payload.len() + CRLF.len();
+
+                    return match to_string(&payload)?.split_once(':') {
+                        Some((format, text)) if format.len() == 3 => Ok(Some(
+                            RespDataTypes::Verbatim(format.to_string(), text.to_string()),
+                        )),
+
+                        _ => Err(ProtocolError::InvalidBulkLength),
+                    };
                 }
-            }
 
-            _ if bulk_array_re.is_match(first) => {
-                let string_option = value.get(1);
+                b'*' | b'~' | b'>' | b'%' | b'|' => {
+                    let pairs = matches!(type_byte, b'%' | b'|');
+
+                    let len = parse_number(line)
+                        .filter(|len| {
+                            let items = if pairs { len.saturating_mul(2) } else { *len };
+
+                            items <= limits.max_multibulk_len
+                        })
+                        .ok_or(ProtocolError::InvalidMultibulkLength)?;
+
+                    if len < 0 && !pairs {
+                        RespDataTypes::Null
+                    } else {
+                        if self.open.len() >= limits.max_nesting_depth {
+                            return Err(ProtocolError::TooDeeplyNested);
+                        }
+
+                        let len = if pairs {
+                            len.max(0) as usize * 2
+                        } else {
+                            len as usize
+                        };
 
-                match string_option {
-                    None => Err("Invalid BulkString"),
+                        let aggregate = OpenAggregate::new(type_byte, len);
 
-                    Some(string) => Ok(Self::BulkString(string.to_string())),
+                        buffer.advance(next_idx);
+
+                        self.consumed += next_idx;
+
+                        if len == 0 {
+                            return Ok(Some(aggregate.close()));
+                        }
+
+                        self.open.push(aggregate);
+
+                        continue;
+                    }
                 }
-            }
 
-            _ => Err("invalid Type"),
+                other => return Err(ProtocolError::UnexpectedType(other as char)),
+            };
+
+            buffer.advance(next_idx);
+
+            self.consumed += next_idx;
+
+            return Ok(Some(value));
         }
     }
-}
 
-#[derive(Debug)]
-pub enum Commands {
-    Ping,
+    /// Splits off the `len` bytes of a bulk payload starting at `start` without copying them,
+    /// along with the header before it and the CRLF after it.
+    fn take_payload(
+        buffer: &mut BytesMut,
+        start: usize,
+        len: i64,
+    ) -> Result<Option<Bytes>, ProtocolError> {
+        let end_idx = (start as u64)
+            .checked_add(len as u64)
+            .and_then(|end_idx| usize::try_from(end_idx).ok())
+            .ok_or(ProtocolError::InvalidBulkLength)?;
+
+        if buffer.len() < end_idx.saturating_add(CRLF.len()) {
+            return Ok(None);
+        }
 
-    Echo(String),
+        if &buffer[end_idx..end_idx + CRLF.len()] != CRLF {
+            return Err(ProtocolError::UnterminatedBulk);
+        }
 
-    Set(String, String, Option<DateTime<Utc>>),
+        buffer.advance(start);
 
-    Get(String),
+        let payload = buffer.split_to(end_idx - start).freeze();
 
-    Config(Vec<String>),
+        buffer.advance(CRLF.len());
 
-    Keys(String),
+        Ok(Some(payload))
+    }
 
-    Info(Option<String>),
+    /// Decodes a telnet-style command line such as `SET foo "bar baz"` into an array of
+    /// bulk strings. Lines may end with either CRLF or a bare LF.
+    fn decode_inline(buffer: &mut BytesMut) -> Result<Option<RespDataTypes>, ProtocolError> {
+        let Some(newline_idx) = buffer.iter().position(|byte| *byte == b'\n') else {
+            if buffer.len() > MAX_LINE_LEN {
+                return Err(ProtocolError::TooBigInlineRequest);
+            }
+
+            return Ok(None);
+        };
+
+        let line = buffer[..newline_idx]
+            .strip_suffix(b"\r")
+            .unwrap_or(&buffer[..newline_idx]);
+
+        let args = split_inline_args(line).ok_or(ProtocolError::UnbalancedQuotes)?;
 
-    REPLCONF(String, String),
+        buffer.advance(newline_idx + 1);
 
-    PSYNC(String, String),
+        Ok(Some(RespDataTypes::Array(
+            args.into_iter().map(RespDataTypes::BulkString).collect(),
+        )))
+    }
 }
 
-impl Commands {
-    fn decode_command_options(
-        arr: &[RespDataTypes],
-        name: &str,
-        must_have_options: bool,
-    ) -> anyhow::Result<Vec<String>> {
-        let mut options = Vec::new();
-
-        if arr.len() < 2 {
-            if must_have_options {
-                bail!("{name} command must be followed by a key");
-            } else {
-                return Ok(options);
-            }
+/// Splits a line into arguments following the rules of Redis' `sdssplitargs`: arguments
+/// are separated by whitespace, double quoted arguments understand `\n`, `\r`, `\t`, `\b`,
+/// `\a` and `\xHH` escapes, and single quoted arguments only understand `\'`. A closing
+/// quote must be followed by whitespace. Returns `None` for unbalanced quotes.
+pub fn split_inline_args(line: &[u8]) -> Option<Vec<Bytes>> {
+    let mut args = Vec::new();
+
+    let mut idx = 0;
+
+    loop {
+        while idx < line.len() && line[idx].is_ascii_whitespace() {
+            idx += 1;
         }
 
-        for i in 1..arr.len() {
-            let record_option = arr.get(i);
+        if idx == line.len() {
+            return Some(args);
+        }
+
+        let mut current = Vec::new();
+
+        let mut in_double_quotes = false;
+        let mut in_single_quotes = false;
+
+        loop {
+            let byte = line.get(idx).copied();
+
+            if in_double_quotes {
+                match byte {
+                    None => return None,
+
+                    Some(b'\\') if hex_escape(line, idx).is_some() => {
+                        current.extend(hex_escape(line, idx));
+
+                        idx += 3;
+                    }
+
+                    Some(b'\\') if idx + 1 < line.len() => {
+                        idx += 1;
+
+                        current.push(match line[idx] {
+                            b'n' => b'\n',
+                            b'r' => b'\r',
+                            b't' => b'\t',
+                            b'b' => 0x08,
+                            b'a' => 0x07,
+                            other => other,
+                        });
+                    }
+
+                    Some(b'"') => {
+                        // the closing quote must be followed by a space or nothing
+                        if line
+                            .get(idx + 1)
+                            .is_some_and(|next| !next.is_ascii_whitespace())
+                        {
+                            return None;
+                        }
+
+                        idx += 1;
 
-            match record_option {
-                None => {
-                    if must_have_options {
-                        bail!("{name} command must be followed by a key");
-                    } else {
                         break;
                     }
+
+                    Some(other) => current.push(other),
                 }
+            } else if in_single_quotes {
+                match byte {
+                    None => return None,
+
+                    Some(b'\\') if line.get(idx + 1) == Some(&b'\'') => {
+                        idx += 1;
 
-                Some(record) => match record {
-                    RespDataTypes::BulkString(string) => {
-                        options.push(string.to_owned());
+                        current.push(b'\'');
                     }
 
-                    RespDataTypes::Integer(int) => {
-                        options.push(int.to_string());
+                    Some(b'\'') => {
+                        if line
+                            .get(idx + 1)
+                            .is_some_and(|next| !next.is_ascii_whitespace())
+                        {
+                            return None;
+                        }
+
+                        idx += 1;
+
+                        break;
                     }
 
-                    _ => bail!("{name} command must be followed by a key"),
-                },
+                    Some(other) => current.push(other),
+                }
+            } else {
+                match byte {
+                    None => break,
+
+                    Some(byte) if byte.is_ascii_whitespace() => break,
+
+                    Some(b'"') => in_double_quotes = true,
+
+                    Some(b'\'') => in_single_quotes = true,
+
+                    Some(other) => current.push(other),
+                }
             }
+
+            idx += 1;
         }
 
-        Ok(options)
+        args.push(Bytes::from(current));
     }
 }
 
-impl TryFrom<RespDataTypes> for Commands {
-    type Error = &'static str;
+/// Value of a `\xHH` escape starting at `idx`, if there is a well formed one.
+fn hex_escape(line: &[u8], idx: usize) -> Option<u8> {
+    let digits = line.get(idx + 2..idx + 4)?;
 
-    fn try_from(value: RespDataTypes) -> Result<Self, Self::Error> {
-        println!("Data: {value:?}");
+    if line.get(idx + 1) != Some(&b'x') || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
 
-        match value {
-            RespDataTypes::Array(arr) => {
-                let command_name = arr.first();
-
-                if let Some(command_name) = command_name {
-                    match command_name {
-                        RespDataTypes::BulkString(cmd_name) => match cmd_name
-                            .to_uppercase()
-                            .as_str()
-                        {
-                            "ECHO" => {
-                                let options =
-                                    Self::decode_command_options(&arr, "ECHO", true).unwrap();
+    u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
+}
 
-                                Ok(Self::Echo(options[0].clone()))
-                            }
+/// Returns the bytes between `start` and the next CRLF, plus the index right after it.
+fn read_line(data: &[u8], start: usize) -> Option<(&[u8], usize)> {
+    let rest = data.get(start..)?;
 
-                            "PING" => Ok(Commands::Ping),
+    let end = rest.windows(CRLF.len()).position(|window| window == CRLF)?;
 
-                            "SET" => {
-                                let options =
-                                    Self::decode_command_options(&arr, "SET", true).unwrap();
+    Some((&rest[..end], start + end + CRLF.len()))
+}
 
-                                let mut expires_at = None;
+fn parse_number(line: &[u8]) -> Option<i64> {
+    std::str::from_utf8(line).ok()?.parse::<i64>().ok()
+}
 
-                                if let Some(exp_unit_str) = options.get(2) {
-                                    let now = Utc::now();
+fn to_string(bytes: &[u8]) -> Result<String, ProtocolError> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| ProtocolError::InvalidString)
+}
 
-                                    let expiration: u64;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                                    if let Some(exp_duration_str) = options.get(3) {
-                                        match exp_unit_str.to_lowercase().as_str() {
-                                            "px" => {
-                                                expiration =
-                                                    exp_duration_str.parse::<u64>().unwrap_or(0);
-                                            }
+    fn limits() -> ProtocolLimits {
+        ProtocolLimits {
+            max_bulk_len: 8,
+            max_multibulk_len: 4,
+            max_nesting_depth: 2,
+            ..ProtocolLimits::default()
+        }
+    }
 
-                                            "ex" => {
-                                                expiration =
-                                                    exp_duration_str.parse::<u64>().unwrap_or(0)
-                                                        * 1000;
-                                            }
+    fn decode(input: &[u8]) -> Result<Option<RespDataTypes>, ProtocolError> {
+        FrameDecoder::default().decode(&mut BytesMut::from(input), &limits())
+    }
 
-                                            _ => return Err("Invalid Duration"),
-                                        }
+    fn encoded(frame: &RespDataTypes) -> Bytes {
+        frame.to_bytes(ProtocolVersion::Resp3)
+    }
 
-                                        let duration = Duration::from_millis(expiration);
+    #[test]
+    fn frames_fed_a_byte_at_a_time_decode_once_complete() {
+        let input = b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n*1\r\n$4\r\nPING\r\n";
 
-                                        expires_at = Some(now + duration);
-                                    }
-                                }
+        let mut decoder = FrameDecoder::default();
+        let mut buffer = BytesMut::new();
+        let mut frames = Vec::new();
 
-                                Ok(Self::Set(
-                                    options[0].clone(),
-                                    options[1].clone(),
-                                    expires_at,
-                                ))
-                            }
+        for (idx, byte) in input.iter().enumerate() {
+            buffer.put_u8(*byte);
 
-                            "GET" => {
-                                let options =
-                                    Self::decode_command_options(&arr, "GET", true).unwrap();
+            if let Some(frame) = decoder.decode(&mut buffer, &limits()).unwrap() {
+                frames.push((idx, encoded(&frame)));
+            }
+        }
 
-                                Ok(Self::Get(options[0].clone()))
-                            }
+        assert_eq!(
+            frames,
+            vec![
+                (21, Bytes::from_static(b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n")),
+                (35, Bytes::from_static(b"*1\r\n$4\r\nPING\r\n")),
+            ]
+        );
+        assert!(buffer.is_empty());
+        assert_eq!(decoder.partial_len(), 0);
+    }
 
-                            "KEYS" => {
-                                let options =
-                                    Self::decode_command_options(&arr, "KEYS", true).unwrap();
+    #[test]
+    fn complete_elements_of_a_partial_frame_are_consumed() {
+        let mut decoder = FrameDecoder::default();
+        let mut buffer = BytesMut::from(&b"*2\r\n$3\r\nfoo\r\n$3\r\nba"[..]);
 
-                                Ok(Self::Keys(options[0].clone()))
-                            }
+        assert!(decoder.decode(&mut buffer, &limits()).unwrap().is_none());
+        assert_eq!(&buffer[..], b"$3\r\nba");
+        assert_eq!(decoder.partial_len(), 13);
 
-                            "CONFIG" => {
-                                let options =
-                                    Self::decode_command_options(&arr, "CONFIG", true).unwrap();
+        buffer.put_slice(b"r\r\n+OK\r\n");
 
-                                Ok(Self::Config(options))
-                            }
+        let frame = decoder.decode(&mut buffer, &limits()).unwrap().unwrap();
 
-                            "INFO" => {
-                                let options =
-                                    Self::decode_command_options(&arr, "INFO", false).unwrap();
+        assert_eq!(encoded(&frame), &b"*2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n"[..]);
+        assert_eq!(&buffer[..], b"+OK\r\n");
+        assert_eq!(decoder.partial_len(), 0);
+    }
 
-                                Ok(Self::Info(options.first().cloned()))
-                            }
+    #[test]
+    fn resp3_types_decode() {
+        let input = b"*4\r\n%1\r\n+a\r\n:-1\r\n~2\r\n#t\r\n,1.5\r\n>0\r\n=7\r\ntxt:abc\r\n";
 
-                            "REPLCONF" => {
-                                let options =
-                                    Self::decode_command_options(&arr, "REPLCONF", true).unwrap();
+        let frame = decode(input).unwrap().unwrap();
 
-                                if options.len() == 2 {
-                                    Ok(Self::REPLCONF(options[0].clone(), options[1].clone()))
-                                } else {
-                                    Err("Invalid REPLCONF command")
-                                }
-                            }
+        assert_eq!(encoded(&frame), &input[..]);
+    }
 
-                            "PSYNC" => {
-                                let options =
-                                    Self::decode_command_options(&arr, "PSYNC", true).unwrap();
+    #[test]
+    fn nulls_decode() {
+        for input in [&b"$-1\r\n"[..], b"*-1\r\n", b"_\r\n"] {
+            assert!(matches!(decode(input), Ok(Some(RespDataTypes::Null))));
+        }
+    }
 
-                                if options.len() == 2 {
-                                    Ok(Self::PSYNC(options[0].clone(), options[1].clone()))
-                                } else {
-                                    Err("Invalid PSYNC command")
-                                }
-                            }
+    #[test]
+    fn limits_are_enforced() {
+        assert_eq!(
+            decode(b"$9\r\n").unwrap_err(),
+            ProtocolError::InvalidBulkLength
+        );
+        assert_eq!(
+            decode(b"*5\r\n").unwrap_err(),
+            ProtocolError::InvalidMultibulkLength
+        );
+        assert_eq!(
+            decode(b"%3\r\n").unwrap_err(),
+            ProtocolError::InvalidMultibulkLength
+        );
+        assert_eq!(
+            decode(b"*1\r\n*1\r\n*1\r\n").unwrap_err(),
+            ProtocolError::TooDeeplyNested
+        );
+        assert!(decode(b"*1\r\n*1\r\n:1\r\n").unwrap().is_some());
+
+        let mut header = b"*".to_vec();
+        header.resize(MAX_LINE_LEN + 2, b'1');
+
+        assert_eq!(
+            decode(&header).unwrap_err(),
+            ProtocolError::TooBigCountString
+        );
+
+        let inline = vec![b'a'; MAX_LINE_LEN + 1];
+
+        assert_eq!(
+            decode(&inline).unwrap_err(),
+            ProtocolError::TooBigInlineRequest
+        );
+    }
 
-                            _ => Err("Invalid Command"),
-                        },
+    #[test]
+    fn malformed_frames_are_rejected() {
+        assert_eq!(
+            decode(b"$3\r\nfooXX").unwrap_err(),
+            ProtocolError::UnterminatedBulk
+        );
+        assert_eq!(
+            decode(b"$x\r\n").unwrap_err(),
+            ProtocolError::InvalidBulkLength
+        );
+        assert_eq!(
+            decode(b":1.5\r\n").unwrap_err(),
+            ProtocolError::InvalidInteger
+        );
+        assert_eq!(
+            decode(b"#x\r\n").unwrap_err(),
+            ProtocolError::InvalidBoolean
+        );
+        assert_eq!(
+            decode(b"*1\r\nx\r\n").unwrap_err(),
+            ProtocolError::UnexpectedType('x')
+        );
+    }
 
-                        _ => Err("Invalid command"),
-                    }
-                } else {
-                    Err("Invalid Command")
-                }
-            }
+    #[test]
+    fn inline_commands_decode_into_bulk_strings() {
+        let mut decoder = FrameDecoder::default();
+        let mut buffer = BytesMut::from(&b"SET k \"a b\"\r\nPING\nGET"[..]);
+
+        let set = decoder.decode(&mut buffer, &limits()).unwrap().unwrap();
+        let ping = decoder.decode(&mut buffer, &limits()).unwrap().unwrap();
+
+        assert_eq!(
+            encoded(&set),
+            &b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$3\r\na b\r\n"[..]
+        );
+        assert_eq!(encoded(&ping), &b"*1\r\n$4\r\nPING\r\n"[..]);
+        assert!(decoder.decode(&mut buffer, &limits()).unwrap().is_none());
+        assert_eq!(&buffer[..], b"GET");
+
+        assert_eq!(
+            decode(b"GET \"k\r\n").unwrap_err(),
+            ProtocolError::UnbalancedQuotes
+        );
+    }
 
-            _ => Err("Invalid Command"),
-        }
+    #[test]
+    fn inline_args_follow_sdssplitargs_quoting() {
+        let split = |line: &[u8]| {
+            split_inline_args(line).map(|args| args.iter().map(|arg| arg.to_vec()).collect())
+        };
+
+        assert_eq!(split(b"  a   b "), Some(vec![b"a".to_vec(), b"b".to_vec()]));
+        assert_eq!(split(b""), Some(vec![]));
+        assert_eq!(
+            split(br#""a\tb\x41\"" c"#),
+            Some(vec![b"a\tbA\"".to_vec(), b"c".to_vec()])
+        );
+        assert_eq!(split(br#""\xZZ""#), Some(vec![b"xZZ".to_vec()]));
+        assert_eq!(split(br"'it\'s \n'"), Some(vec![br"it's \n".to_vec()]));
+        assert_eq!(split(b"a\"b c\""), Some(vec![b"ab c".to_vec()]));
+        assert_eq!(split(b"\"unterminated"), None);
+        assert_eq!(split(b"'unterminated"), None);
+        assert_eq!(split(b"\"a\"b"), None);
+        assert_eq!(split(b"'a'b"), None);
     }
 }
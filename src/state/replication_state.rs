@@ -1,14 +1,21 @@
 use std::{net::SocketAddr, sync::Arc};
 
 use anyhow::{bail, ensure, Context};
+use bytes::{Bytes, BytesMut};
 use socket2::{SockRef, TcpKeepalive};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
-    net::TcpStream,
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpStream,
+    },
     sync::Mutex,
 };
 
-use crate::{resp::RespDataTypes, utils::gen_id};
+use crate::{
+    resp::{ProtocolVersion, RespDataTypes},
+    utils::gen_id,
+};
 
 #[allow(unused)]
 #[derive(Debug, Clone)]
@@ -17,6 +24,20 @@ pub enum Role {
     Slave,
 }
 
+/// A replica's connection to its master once the handshake is done.
+#[derive(Debug)]
+pub struct MasterLink {
+    pub reader: OwnedReadHalf,
+
+    pub writer: Arc<Mutex<OwnedWriteHalf>>,
+
+    /// RDB payload of the full resync.
+    pub snapshot: Bytes,
+
+    /// Replication stream bytes that arrived together with the snapshot.
+    pub pending: BytesMut,
+}
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub enum Replica {
@@ -25,6 +46,9 @@ pub enum Replica {
         address: SocketAddr,
         slaves: Vec<Replica>,
         replication_offset: i64,
+
+        /// Database the replication stream last switched to with `SELECT`.
+        selected_db: Option<u32>,
     },
 
     Slave {
@@ -33,7 +57,7 @@ pub enum Replica {
         master_offset: i64,
         address: SocketAddr,
         master_address: String,
-        master_connection: Option<Arc<Mutex<TcpStream>>>,
+        master_connection: Option<Arc<Mutex<OwnedWriteHalf>>>,
     },
 }
 
@@ -52,6 +76,7 @@ impl Replica {
             replication_offset: 0,
             address: SocketAddr::from(([127, 0, 0, 1], 6379)),
             slaves: Vec::new(),
+            selected_db: None,
         }
     }
 
@@ -66,7 +91,7 @@ impl Replica {
         }
     }
 
-    pub async fn init(&mut self) -> anyhow::Result<Arc<Mutex<TcpStream>>> {
+    pub async fn init(&mut self) -> anyhow::Result<MasterLink> {
         match self {
             Self::Master { .. } => bail!("Master replica cannot be initialized this way"),
 
@@ -89,13 +114,14 @@ impl Replica {
 
     pub async fn register_replica(
         &mut self,
-        connection: Arc<Mutex<TcpStream>>,
+        connection: Arc<Mutex<OwnedWriteHalf>>,
     ) -> anyhow::Result<()> {
         match self {
             Self::Master {
                 slaves,
                 id,
                 address,
+                selected_db,
                 ..
             } => {
                 println!("Registering replica...");
@@ -130,6 +156,9 @@ impl Replica {
 
                 slaves.push(slave);
 
+                // the new replica has no database selected yet
+                *selected_db = None;
+
                 println!("Slaves: {:?}", slaves);
 
                 Ok(())
@@ -139,10 +168,37 @@ impl Replica {
         }
     }
 
-    pub async fn replicate_command(&mut self, command: &RespDataTypes) -> anyhow::Result<()> {
+    /// Sends a write command executed against database `db` to every replica, preceded by
+    /// a `SELECT` when the stream was on another database.
+    pub async fn replicate_command(
+        &mut self,
+        db: u32,
+        command: &RespDataTypes,
+    ) -> anyhow::Result<()> {
         match self {
-            Self::Master { slaves, .. } => {
-                println!("Slave count: {}", slaves.len());
+            Self::Master {
+                slaves,
+                selected_db,
+                ..
+            } => {
+                if slaves.is_empty() {
+                    return Ok(());
+                }
+
+                // encoded once and shared by every replica link
+                let mut payload = BytesMut::new();
+
+                if *selected_db != Some(db) {
+                    RespDataTypes::Array(vec![
+                        RespDataTypes::BulkString("SELECT".into()),
+                        RespDataTypes::BulkString(db.to_string().into()),
+                    ])
+                    .encode_into(&mut payload, ProtocolVersion::Resp2);
+
+                    *selected_db = Some(db);
+                }
+
+                command.encode_into(&mut payload, ProtocolVersion::Resp2);
 
                 for slave in slaves {
                     match slave {
@@ -152,20 +208,14 @@ impl Replica {
                             ..
                         } => {
                             if let Some(stream) = master_connection {
-                                println!("Replicating command to slave at {address}");
-
                                 let mut stream_guard = stream.lock().await;
 
-                                stream_guard
-                                    .write_all(command.to_string().as_bytes())
-                                    .await
-                                    .with_context(|| {
-                                        format!(
-                                            "Could not send command: {command} to slave {address}"
-                                        )
-                                    })?;
-
-                                println!("Command replicated to slave at {}", address);
+                                // a broken replica link must not fail the client's command
+                                if let Err(e) = stream_guard.write_all(&payload).await {
+                                    eprintln!(
+                                        "Could not send command: {command} to slave {address}: {e:?}"
+                                    );
+                                }
                             }
                         }
 
@@ -174,9 +224,8 @@ impl Replica {
                 }
             }
 
-            Self::Slave { .. } => {
-                println!("Slave cannot replicate command");
-            }
+            // replicas do not propagate what they apply any further
+            Self::Slave { .. } => {}
         };
 
         Ok(())
@@ -228,8 +277,8 @@ impl Replica {
         }
     }
 
-    async fn master_handshake(&mut self) -> anyhow::Result<Arc<Mutex<TcpStream>>> {
-        let stream = TcpStream::connect(self.get_master_address().unwrap())
+    async fn master_handshake(&mut self) -> anyhow::Result<MasterLink> {
+        let mut stream = TcpStream::connect(self.get_master_address().unwrap())
             .await
             .with_context(|| "Could not connect to master")?;
 
@@ -237,35 +286,38 @@ impl Replica {
         let sf = SockRef::from(&stream);
         sf.set_tcp_keepalive(&ka)?;
 
-        let connection = Arc::new(Mutex::new(stream));
-
-        let mut connection_guard = connection.lock().await;
-
-        self.ping_master(&mut connection_guard)
+        self.ping_master(&mut stream)
             .await
             .with_context(|| "Could not ping master")?;
 
-        self.replconf(&mut connection_guard)
+        self.replconf(&mut stream)
             .await
             .with_context(|| "Could not send REPLCONF command to master")?;
 
-        self.psync(Some(&mut connection_guard))
+        let (snapshot, pending) = self
+            .request_full_resync(&mut stream)
             .await
             .with_context(|| "Could not send PSYNC command to master")?;
 
-        drop(connection_guard);
+        let (reader, writer) = stream.into_split();
+
+        let writer = Arc::new(Mutex::new(writer));
 
-        self.set_master_connection(connection.clone());
+        self.set_master_connection(writer.clone());
 
-        Ok(connection)
+        Ok(MasterLink {
+            reader,
+            writer,
+            snapshot,
+            pending,
+        })
     }
 
     async fn ping_master(&self, stream: &mut TcpStream) -> anyhow::Result<()> {
         stream
             .write_all(
-                RespDataTypes::Array(vec![RespDataTypes::BulkString("PING".to_string())])
-                    .to_string()
-                    .as_bytes(),
+                &RespDataTypes::Array(vec![RespDataTypes::BulkString("PING".into())])
+                    .to_bytes(ProtocolVersion::Resp2),
             )
             .await
             .with_context(|| "Could not send PING command to master")?;
@@ -290,13 +342,12 @@ impl Replica {
 
         stream
             .write_all(
-                RespDataTypes::Array(vec![
-                    RespDataTypes::BulkString("REPLCONF".to_string()),
-                    RespDataTypes::BulkString("listening-port".to_string()),
-                    RespDataTypes::BulkString(self.get_address().port().to_string()),
+                &RespDataTypes::Array(vec![
+                    RespDataTypes::BulkString("REPLCONF".into()),
+                    RespDataTypes::BulkString("listening-port".into()),
+                    RespDataTypes::BulkString(self.get_address().port().to_string().into()),
                 ])
-                .to_string()
-                .as_bytes(),
+                .to_bytes(ProtocolVersion::Resp2),
             )
             .await
             .with_context(|| "Could not send REPLCONF listening-port command to master")?;
@@ -313,13 +364,12 @@ impl Replica {
 
         stream
             .write_all(
-                RespDataTypes::Array(vec![
-                    RespDataTypes::BulkString("REPLCONF".to_string()),
-                    RespDataTypes::BulkString("capa".to_string()),
-                    RespDataTypes::BulkString("psync2".to_string()),
+                &RespDataTypes::Array(vec![
+                    RespDataTypes::BulkString("REPLCONF".into()),
+                    RespDataTypes::BulkString("capa".into()),
+                    RespDataTypes::BulkString("psync2".into()),
                 ])
-                .to_string()
-                .as_bytes(),
+                .to_bytes(ProtocolVersion::Resp2),
             )
             .await
             .with_context(|| "Could not send REPLCONF capa command to master")?;
@@ -337,10 +387,7 @@ impl Replica {
         Ok(())
     }
 
-    pub async fn psync(
-        &mut self,
-        _stream: Option<&mut TcpStream>,
-    ) -> anyhow::Result<RespDataTypes> {
+    pub fn psync(&self) -> anyhow::Result<RespDataTypes> {
         match self {
             Self::Master {
                 id,
@@ -350,74 +397,107 @@ impl Replica {
                 "FULLRESYNC {id} {replication_offset}"
             ))),
 
-            Self::Slave { .. } => {
-                let buffer = &mut [0u8; 512];
-
-                let stream = _stream.ok_or_else(|| anyhow::anyhow!("Stream is not available"))?;
-
-                stream
-                    .write_all(
-                        RespDataTypes::Array(vec![
-                            RespDataTypes::BulkString("PSYNC".to_string()),
-                            RespDataTypes::BulkString(self.get_master_id()),
-                            RespDataTypes::BulkString(self.get_replication_offset().to_string()),
-                        ])
-                        .to_string()
-                        .as_bytes(),
-                    )
-                    .await
-                    .with_context(|| "Could not send REPLCONF capa command to master")?;
-
-                stream
-                    .read(buffer)
-                    .await
-                    .with_context(|| "Could not read master response")?;
+            Self::Slave { .. } => bail!("Slave replica cannot serve PSYNC"),
+        }
+    }
 
-                let res = String::from_utf8_lossy(buffer);
+    /// Sends `PSYNC` to the master and reads the `+FULLRESYNC` line and the RDB snapshot
+    /// that follow it. The master may already have written propagated commands after the
+    /// snapshot, so whatever was read past it is returned too, to seed the connection buffer.
+    async fn request_full_resync(
+        &mut self,
+        stream: &mut TcpStream,
+    ) -> anyhow::Result<(Bytes, BytesMut)> {
+        stream
+            .write_all(
+                &RespDataTypes::Array(vec![
+                    RespDataTypes::BulkString("PSYNC".into()),
+                    RespDataTypes::BulkString(self.get_master_id().into()),
+                    RespDataTypes::BulkString(self.get_replication_offset().to_string().into()),
+                ])
+                .to_bytes(ProtocolVersion::Resp2),
+            )
+            .await
+            .with_context(|| "Could not send PSYNC command to master")?;
 
-                println!("Handshake (3/3) Master response: {}", res);
+        let mut buffer = BytesMut::with_capacity(512);
 
-                let mut splits = res.split_whitespace();
+        let line = Self::read_master_line(stream, &mut buffer).await?;
 
-                let command = splits
-                    .next()
-                    .ok_or_else(|| anyhow::anyhow!("Could not parse master response"))?;
+        println!("Handshake (3/3) Master response: {}", line);
 
-                ensure!(
-                    command == "+FULLRESYNC",
-                    "Master did not respond with FULLRESYNC to PSYNC command"
-                );
+        let mut splits = line.split_whitespace();
 
-                let master_id = splits
-                    .next()
-                    .ok_or_else(|| anyhow::anyhow!("Could not parse master ID from response"))?
-                    .to_string();
+        let command = splits
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Could not parse master response"))?;
 
-                let replication_offset = splits
-                    .next()
-                    .ok_or_else(|| {
-                        anyhow::anyhow!("Could not parse replication offset from response")
-                    })?
-                    .parse::<i64>()
-                    .with_context(|| "Could not parse replication offset")?;
+        ensure!(
+            command == "+FULLRESYNC",
+            "Master did not respond with FULLRESYNC to PSYNC command"
+        );
 
-                println!("Master ID: {master_id}");
-                println!("Replication offset: {replication_offset}");
+        let master_id = splits
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Could not parse master ID from response"))?
+            .to_string();
+
+        let replication_offset = splits
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Could not parse replication offset from response"))?
+            .parse::<i64>()
+            .with_context(|| "Could not parse replication offset")?;
+
+        println!("Master ID: {master_id}");
+        println!("Replication offset: {replication_offset}");
+
+        self.set_master_id(master_id);
+        self.set_replication_offset(replication_offset);
+
+        // the snapshot is sent as `$<len>\r\n<payload>` without a trailing CRLF
+        let header = Self::read_master_line(stream, &mut buffer).await?;
+
+        let rdb_len = header
+            .strip_prefix('$')
+            .and_then(|len| len.parse::<usize>().ok())
+            .ok_or_else(|| anyhow::anyhow!("Invalid RDB header from master: {header}"))?;
+
+        while buffer.len() < rdb_len {
+            let read = stream
+                .read_buf(&mut buffer)
+                .await
+                .with_context(|| "Could not read RDB file from master")?;
+
+            ensure!(
+                read > 0,
+                "Master closed the connection while sending RDB file"
+            );
+        }
 
-                self.set_master_id(master_id);
-                self.set_replication_offset(replication_offset);
+        let rdb = buffer.split_to(rdb_len);
 
-                stream
-                    .read(buffer)
-                    .await
-                    .with_context(|| "Could not read master response")?;
+        println!("Master Responseded with RDB file of {} bytes", rdb.len());
 
-                let res = String::from_utf8_lossy(buffer);
+        Ok((rdb.freeze(), buffer))
+    }
 
-                println!("Master Responseded with RDB file: {}", res);
+    async fn read_master_line(
+        stream: &mut TcpStream,
+        buffer: &mut BytesMut,
+    ) -> anyhow::Result<String> {
+        loop {
+            if let Some(end) = buffer.windows(2).position(|window| window == b"\r\n") {
+                let line = buffer.split_to(end + 2);
 
-                Ok(RespDataTypes::SimpleString("Ok".to_string()))
+                return Ok(String::from_utf8_lossy(&line[..end]).to_string());
             }
+
+            let read = stream
+                .read_buf(buffer)
+                .await
+                .with_context(|| "Could not read master response")?;
+
+            ensure!(read > 0, "Master closed the connection during handshake");
         }
     }
 
@@ -450,7 +530,7 @@ slave_replid:{id} "
         }
     }
 
-    fn set_master_connection(&mut self, connection: Arc<Mutex<TcpStream>>) {
+    fn set_master_connection(&mut self, connection: Arc<Mutex<OwnedWriteHalf>>) {
         match self {
             Self::Master { .. } => {}
             Self::Slave {
@@ -1,13 +1,13 @@
 use std::{path::PathBuf, sync::Arc};
 
-use tokio::{net::TcpStream, sync::Mutex};
+use tokio::{net::tcp::OwnedWriteHalf, sync::Mutex};
 
 use crate::{
     configs::{cmd_options::CmdOptions, configurations::Configuration},
-    resp::RespDataTypes,
+    resp::{ProtocolLimits, RespDataTypes},
 };
 
-use super::replication_state::Replica;
+use super::replication_state::{MasterLink, Replica, Role};
 
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -38,31 +38,60 @@ impl ServerState {
         self.config.get_rdb_path()
     }
 
+    pub fn get_protocol_limits(&self) -> ProtocolLimits {
+        self.config.get_protocol_limits()
+    }
+
+    pub fn get_databases(&self) -> u32 {
+        self.config.databases
+    }
+
+    /// Whether a client connected from `addr` may run `DEBUG`.
+    pub fn debug_command_allowed(&self, addr: &str) -> bool {
+        self.config.enable_debug_command.allows(addr)
+    }
+
+    pub fn config_parameters(&self) -> &'static [&'static str] {
+        Configuration::PARAMETERS
+    }
+
     pub fn get_from_config(&self, key: &str) -> Option<String> {
         self.config.get(key)
     }
 
+    pub fn get_role_name(&self) -> &'static str {
+        match self.config.replication_role {
+            Role::Master => "master",
+
+            Role::Slave => "replica",
+        }
+    }
+
     pub fn get_replication_status(&self) -> String {
         self.replication.get_replication_status()
     }
 
-    pub async fn init_replica(&mut self) -> anyhow::Result<Arc<Mutex<TcpStream>>> {
+    pub async fn init_replica(&mut self) -> anyhow::Result<MasterLink> {
         self.replication.init().await
     }
 
-    pub async fn psync(&mut self) -> anyhow::Result<RespDataTypes> {
-        self.replication.psync(None).await
+    pub fn psync(&self) -> anyhow::Result<RespDataTypes> {
+        self.replication.psync()
     }
 
     pub async fn register_replica(
         &mut self,
-        connection: Arc<Mutex<TcpStream>>,
+        connection: Arc<Mutex<OwnedWriteHalf>>,
     ) -> anyhow::Result<()> {
         self.replication.register_replica(connection).await
     }
 
-    pub async fn replicate_command(&mut self, command: &RespDataTypes) -> anyhow::Result<()> {
-        self.replication.replicate_command(command).await
+    pub async fn replicate_command(
+        &mut self,
+        db: u32,
+        command: &RespDataTypes,
+    ) -> anyhow::Result<()> {
+        self.replication.replicate_command(db, command).await
     }
 }
 